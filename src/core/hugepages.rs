@@ -0,0 +1,584 @@
+// Hugepage management for Exliar VFIO Automation Framework
+//
+// This module detects the hugepage sizes the kernel supports, their current
+// allocations and the transparent hugepage (THP) mode, and configures
+// hugepages for VM memory: reserved at boot through kernel parameters,
+// allocated at runtime (optionally on one NUMA node) and mounted as
+// hugetlbfs. Every change is returned for the StateTracker.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::core::bootloader::BootloaderManager;
+use crate::core::state::Change;
+use crate::utils::create_timestamped_backup;
+
+/// fstab the hugetlbfs mounts are persisted in
+const FSTAB_PATH: &str = "/etc/fstab";
+
+/// Allocation state of one hugepage size
+#[derive(Debug, Clone)]
+pub struct HugepageSize {
+    pub size_kb: u64,  // e.g., 2048 or 1048576
+    pub total: u64,    // nr_hugepages
+    pub free: u64,     // free_hugepages
+    pub reserved: u64, // resv_hugepages (promised to mappings, not yet faulted in)
+    pub surplus: u64,  // surplus_hugepages (overcommitted beyond total)
+}
+
+impl HugepageSize {
+    /// Returns the memory held by the allocated pages, in MB
+    pub fn total_mb(&self) -> u64 {
+        self.total * self.size_kb / 1024
+    }
+
+    /// Returns the memory available to a new VM, in MB
+    pub fn free_mb(&self) -> u64 {
+        self.free.saturating_sub(self.reserved) * self.size_kb / 1024
+    }
+}
+
+/// Transparent hugepage mode, from /sys/kernel/mm/transparent_hugepage/enabled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ThpMode {
+    Always,
+    Madvise, // Only for regions that ask for it (QEMU does)
+    Never,
+    Unknown,
+}
+
+impl fmt::Display for ThpMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ThpMode::Always => write!(f, "always"),
+            ThpMode::Madvise => write!(f, "madvise"),
+            ThpMode::Never => write!(f, "never"),
+            ThpMode::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
+/// Hugepage state of the host
+#[derive(Debug, Clone)]
+pub struct HugepageStatus {
+    pub sizes: Vec<HugepageSize>,     // Sorted by size
+    pub default_size_kb: Option<u64>, // Hugepagesize from /proc/meminfo
+    pub thp: ThpMode,
+    pub mounts: Vec<(PathBuf, Option<u64>)>, // hugetlbfs mount points and their page size (kB)
+}
+
+impl HugepageStatus {
+    /// Detects the hugepage state of the running system
+    pub fn detect() -> Self {
+        Self::detect_from(Path::new("/sys"), Path::new("/proc"))
+    }
+
+    /// Detects the hugepage state from the given sysfs and procfs roots
+    pub fn detect_from(sysfs_root: &Path, procfs_root: &Path) -> Self {
+        let mut sizes: Vec<HugepageSize> = fs::read_dir(sysfs_root.join("kernel/mm/hugepages"))
+            .map(|entries| entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| read_hugepage_size(&entry.path()))
+                .collect())
+            .unwrap_or_default();
+        sizes.sort_by_key(|size| size.size_kb);
+
+        let default_size_kb = fs::read_to_string(procfs_root.join("meminfo"))
+            .ok()
+            .and_then(|meminfo| meminfo.lines()
+                .find_map(|line| line.strip_prefix("Hugepagesize:"))
+                .and_then(|value| value.split_whitespace().next())
+                .and_then(|kb| kb.parse().ok()));
+
+        let thp = match fs::read_to_string(sysfs_root.join("kernel/mm/transparent_hugepage/enabled")) {
+            Ok(modes) if modes.contains("[always]") => ThpMode::Always,
+            Ok(modes) if modes.contains("[madvise]") => ThpMode::Madvise,
+            Ok(modes) if modes.contains("[never]") => ThpMode::Never,
+            _ => ThpMode::Unknown,
+        };
+
+        let mounts = fs::read_to_string(procfs_root.join("mounts"))
+            .map(|mounts| mounts.lines()
+                .filter_map(|line| {
+                    let fields: Vec<&str> = line.split_whitespace().collect();
+                    if fields.get(2) != Some(&"hugetlbfs") {
+                        return None;
+                    }
+                    let page_size = fields.get(3)
+                        .and_then(|options| options.split(',').find_map(|option| option.strip_prefix("pagesize=")))
+                        .and_then(parse_size_kb);
+                    Some((PathBuf::from(fields.get(1)?), page_size.or(default_size_kb)))
+                })
+                .collect())
+            .unwrap_or_default();
+
+        Self { sizes, default_size_kb, thp, mounts }
+    }
+
+    /// Returns the allocation state of a page size, if the kernel supports it
+    pub fn size(&self, size_kb: u64) -> Option<&HugepageSize> {
+        self.sizes.iter().find(|size| size.size_kb == size_kb)
+    }
+
+    /// Returns the hugetlbfs mount point for a page size, if one is mounted
+    pub fn mount_for(&self, size_kb: u64) -> Option<&Path> {
+        self.mounts.iter()
+            .find(|(_, page_size)| *page_size == Some(size_kb))
+            .map(|(path, _)| path.as_path())
+    }
+
+    /// Returns the number of pages needed to back `memory_mb` of guest memory
+    pub fn pages_for(memory_mb: u64, size_kb: u64) -> u64 {
+        (memory_mb * 1024).div_ceil(size_kb.max(1))
+    }
+
+    /// Returns a one-line description (e.g., "2M: 0/0, 1G: 16/16 free, THP madvise")
+    pub fn summary(&self) -> String {
+        if self.sizes.is_empty() {
+            return format!("Not supported, THP {}", self.thp);
+        }
+        let sizes: Vec<String> = self.sizes.iter()
+            .map(|size| format!("{}: {}/{} free", format_size(size.size_kb), size.free, size.total))
+            .collect();
+        format!("{}, THP {}", sizes.join(", "), self.thp)
+    }
+}
+
+/// Configures hugepages for VM memory
+///
+/// API only for now: neither the TUI nor the CLI calls it yet, since
+/// neither asks for the VM memory size the pages are sized for.
+pub struct HugepageManager {
+    status: HugepageStatus,
+}
+
+impl HugepageManager {
+    /// Creates a new HugepageManager
+    pub fn new(status: HugepageStatus) -> Self {
+        Self { status }
+    }
+
+    /// Reserves hugepages at boot through kernel parameters
+    ///
+    /// Boot-time allocation is the only reliable way to get 1G pages, since
+    /// memory fragments once the system runs. A different count or default
+    /// size already configured is replaced. A pool of another page size is
+    /// refused rather than kept: bootloaders replace parameters by key and
+    /// may reorder them, so two hugepagesz= pools can't be kept apart.
+    ///
+    /// Args:
+    ///     boot_manager: The bootloader to add the parameters to
+    ///     bootloader: Bootloader name for the recorded changes
+    ///     size_kb: Page size in kB (e.g., 1048576 for 1G)
+    ///     count: Number of pages to reserve
+    ///     dry_run: If true, only log actions without modifying files
+    ///
+    /// Returns:
+    ///     The changes made, for the state tracker (empty if the parameters were already set)
+    pub fn configure_boot_hugepages(
+        &self,
+        boot_manager: &mut dyn BootloaderManager,
+        bootloader: &str,
+        size_kb: u64,
+        count: u64,
+        dry_run: bool,
+    ) -> io::Result<Vec<Change>> {
+        self.check_size(size_kb)?;
+        let size = format_size(size_kb);
+        println!("Configuring {} {} hugepages at boot...", count, size);
+
+        let configured = boot_manager.get_config_parameters()?;
+        let running_default = self.status.default_size_kb.map(format_size);
+        let (stale, params) = plan_boot_hugepages(&configured, &size, count, running_default.as_deref())?;
+        let mut changes = Vec::new();
+        if stale.is_empty() && params.iter().all(|param| configured.contains(param)) {
+            println!("Hugepage kernel parameters are already set.");
+            return Ok(changes);
+        }
+
+        let mut remaining = configured.clone();
+        if !stale.is_empty() {
+            let stale_refs: Vec<&str> = stale.iter().map(String::as_str).collect();
+            if boot_manager.remove_parameters(&stale_refs, dry_run)? {
+                if !dry_run {
+                    for param in &stale {
+                        let (key, value) = param.split_once('=').unwrap_or((param, ""));
+                        changes.push(Change::KernelParamRemoved {
+                            parameter: key.to_string(),
+                            bootloader: bootloader.to_string(),
+                            original_value: Some(value.to_string()),
+                        });
+                    }
+                }
+                // Bootloaders may remove by key, taking a correct value along
+                remaining = if dry_run {
+                    let removed_keys: Vec<&str> = stale.iter().map(|param| param_key(param)).collect();
+                    configured.iter().filter(|param| !removed_keys.contains(&param_key(param))).cloned().collect()
+                } else {
+                    boot_manager.get_config_parameters()?
+                };
+            }
+        }
+
+        let missing: Vec<&str> = params.iter().map(String::as_str).filter(|param| !remaining.iter().any(|c| c == param)).collect();
+        if boot_manager.add_parameters(&missing, dry_run)? {
+            if !dry_run {
+                changes.extend(missing.iter().map(|param| Change::KernelParamAdded {
+                    parameter: param.to_string(),
+                    bootloader: bootloader.to_string(),
+                }));
+            }
+            boot_manager.update_bootloader(dry_run)?;
+        } else if !changes.is_empty() {
+            boot_manager.update_bootloader(dry_run)?;
+        }
+        Ok(changes)
+    }
+
+    /// Allocates hugepages now, optionally on one NUMA node
+    ///
+    /// The kernel allocates as many pages as it can find contiguous memory
+    /// for. Getting fewer than requested is only a warning; the returned
+    /// count and the recorded change hold what was actually allocated.
+    ///
+    /// Args:
+    ///     size_kb: Page size in kB
+    ///     count: Total number of pages the pool (or node) should hold
+    ///     node: NUMA node to allocate on (e.g., the GPU's), or None for all nodes
+    ///     dry_run: If true, only log actions without modifying files
+    ///
+    /// Returns:
+    ///     The change made and the number of pages the pool holds afterwards
+    pub fn allocate_runtime(&self, size_kb: u64, count: u64, node: Option<u32>, dry_run: bool) -> io::Result<(Vec<Change>, u64)> {
+        self.check_size(size_kb)?;
+        let nr_path = nr_hugepages_path(size_kb, node);
+        let original_count = read_count(&nr_path)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, format!("Cannot read {}", nr_path.display())))?;
+        if original_count == count {
+            println!("{} already holds {} pages.", nr_path.display(), count);
+            return Ok((Vec::new(), count));
+        }
+        if dry_run {
+            println!("[DRY RUN] Would write {} to {} (currently {})", count, nr_path.display(), original_count);
+            return Ok((Vec::new(), original_count));
+        }
+
+        println!("Allocating {} {} hugepages{}...", count, format_size(size_kb), node.map_or(String::new(), |n| format!(" on node {}", n)));
+        // Compacting first gives the allocator a chance at large contiguous ranges
+        let _ = fs::write("/proc/sys/vm/compact_memory", "1");
+        fs::write(&nr_path, count.to_string())?;
+        let allocated = read_count(&nr_path).unwrap_or(0);
+        let changes = vec![Change::HugepagesAllocated { size_kb, node, count: allocated, original_count }];
+        if allocated < count {
+            println!("Warning: only {} of {} {} hugepages could be allocated; memory is fragmented, reserve them at boot instead.",
+                     allocated, count, format_size(size_kb));
+        }
+        Ok((changes, allocated))
+    }
+
+    /// Mounts a hugetlbfs for a page size, optionally persisted in /etc/fstab
+    ///
+    /// Args:
+    ///     mount_point: Directory to mount at (e.g., /dev/hugepages1G)
+    ///     size_kb: Page size in kB
+    ///     persist: If true, add the mount to /etc/fstab
+    ///     dry_run: If true, only log actions without modifying files
+    ///
+    /// Returns:
+    ///     The changes made, for the state tracker
+    pub fn mount_hugetlbfs(&self, mount_point: &Path, size_kb: u64, persist: bool, dry_run: bool) -> io::Result<Vec<Change>> {
+        self.check_size(size_kb)?;
+        let options = format!("pagesize={}", format_size(size_kb));
+        let mut changes = Vec::new();
+
+        if let Some(existing) = self.status.mount_for(size_kb) {
+            println!("{} hugetlbfs is already mounted at {}.", format_size(size_kb), existing.display());
+        } else if dry_run {
+            println!("[DRY RUN] Would run: mount -t hugetlbfs -o {} hugetlbfs {}", options, mount_point.display());
+        } else {
+            let created_dir = !mount_point.exists();
+            if created_dir {
+                fs::create_dir_all(mount_point)?;
+            }
+            let status = Command::new("mount")
+                .args(["-t", "hugetlbfs", "-o", &options, "hugetlbfs"])
+                .arg(mount_point)
+                .status()?;
+            if !status.success() {
+                if created_dir {
+                    let _ = fs::remove_dir(mount_point);
+                }
+                return Err(io::Error::other(format!("mount of hugetlbfs at {} failed with status: {}", mount_point.display(), status)));
+            }
+            println!("Mounted {} hugetlbfs at {}", format_size(size_kb), mount_point.display());
+            changes.push(Change::HugetlbfsMounted { mount_point: mount_point.to_path_buf(), created_dir });
+        }
+
+        if persist {
+            let fstab_path = Path::new(FSTAB_PATH);
+            let fstab = fs::read_to_string(fstab_path).unwrap_or_default();
+            let entry = format!("hugetlbfs {} hugetlbfs {} 0 0", mount_point.display(), options);
+            let already_listed = fstab.lines()
+                .any(|line| line.split_whitespace().nth(1) == Some(&*mount_point.to_string_lossy()));
+            if already_listed {
+                println!("{} already lists {}.", fstab_path.display(), mount_point.display());
+            } else if dry_run {
+                println!("[DRY RUN] Would append to {}: {}", fstab_path.display(), entry);
+            } else {
+                let backup_path = create_timestamped_backup(fstab_path)?;
+                println!("Created backup: {}", backup_path.display());
+                let mut file = fs::OpenOptions::new().append(true).open(fstab_path)?;
+                writeln!(file, "{}", entry)?;
+                println!("Successfully updated {}", fstab_path.display());
+                changes.push(Change::FileModified { path: fstab_path.to_path_buf(), backup_path });
+            }
+        }
+        Ok(changes)
+    }
+
+    /// Fails if the kernel doesn't support the page size
+    fn check_size(&self, size_kb: u64) -> io::Result<()> {
+        if self.status.size(size_kb).is_some() {
+            return Ok(());
+        }
+        let supported: Vec<String> = self.status.sizes.iter().map(|size| format_size(size.size_kb)).collect();
+        Err(io::Error::new(io::ErrorKind::Unsupported, format!(
+            "{} hugepages are not supported (supported: {})",
+            format_size(size_kb),
+            if supported.is_empty() { "none".to_string() } else { supported.join(", ") },
+        )))
+    }
+}
+
+/// Returns the nr_hugepages attribute of a page size, globally or for one node
+pub fn nr_hugepages_path(size_kb: u64, node: Option<u32>) -> PathBuf {
+    let pool = format!("hugepages-{}kB/nr_hugepages", size_kb);
+    match node {
+        Some(node) => Path::new("/sys/devices/system/node").join(format!("node{}", node)).join("hugepages").join(pool),
+        None => Path::new("/sys/kernel/mm/hugepages").join(pool),
+    }
+}
+
+/// Formats a page size in kernel parameter notation (e.g., "2M", "1G")
+pub fn format_size(size_kb: u64) -> String {
+    match size_kb {
+        kb if kb >= 1024 * 1024 && kb % (1024 * 1024) == 0 => format!("{}G", kb / (1024 * 1024)),
+        kb if kb >= 1024 && kb % 1024 == 0 => format!("{}M", kb / 1024),
+        kb => format!("{}K", kb),
+    }
+}
+
+/// Parses a page size such as "2M", "1G" or "2048k" into kB
+pub fn parse_size_kb(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let (number, unit) = size.split_at(size.find(|c: char| !c.is_ascii_digit()).unwrap_or(size.len()));
+    let number: u64 = number.parse().ok()?;
+    match unit.to_ascii_uppercase().as_str() {
+        "" | "K" | "KB" => Some(number),
+        "M" | "MB" => Some(number * 1024),
+        "G" | "GB" => Some(number * 1024 * 1024),
+        _ => None,
+    }
+}
+
+/// Hugepage parameters a boot-time reservation sets
+const HUGEPAGE_PARAM_KEYS: [&str; 3] = ["default_hugepagesz", "hugepagesz", "hugepages"];
+
+/// Plans the kernel parameters reserving `count` pages of `size` (e.g., "1G")
+///
+/// Returns the configured hugepage parameters to remove and the ones to
+/// set. Fails if a pool of another page size is configured.
+///
+/// The parameters stay correct in any order: default_hugepagesz= sorts
+/// before hugepages=, so the count goes to the default size, which is
+/// the size being reserved.
+fn plan_boot_hugepages(
+    configured: &[String],
+    size: &str,
+    count: u64,
+    running_default: Option<&str>,
+) -> io::Result<(Vec<String>, [String; 3])> {
+    let other_sizes: Vec<String> = configured.iter()
+        .filter_map(|param| param.strip_prefix("hugepagesz="))
+        .map(String::from)
+        .chain(configured_pools(configured, running_default).into_iter().filter_map(|(pool_size, _)| pool_size))
+        .filter(|pool_size| pool_size != size)
+        .collect();
+    if let Some(other) = other_sizes.first() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!(
+            "A {} hugepage pool is already configured on the kernel command line; remove it before reserving {} pages",
+            other, size,
+        )));
+    }
+
+    let params = [format!("default_hugepagesz={}", size), format!("hugepagesz={}", size), format!("hugepages={}", count)];
+    let stale = configured.iter()
+        .filter(|param| HUGEPAGE_PARAM_KEYS.contains(&param_key(param)) && !params.contains(param))
+        .cloned()
+        .collect();
+    Ok((stale, params))
+}
+
+/// Returns the hugepage pools a kernel command line reserves, as the
+/// kernel reads them: (page size, count)
+///
+/// A hugepages= count belongs to the last hugepagesz= or
+/// default_hugepagesz= before it, or to the default size if none precedes
+/// it. The size is None if the default size isn't known.
+fn configured_pools(params: &[String], running_default: Option<&str>) -> Vec<(Option<String>, u64)> {
+    let default_size = params.iter().rev()
+        .find_map(|param| param.strip_prefix("default_hugepagesz="))
+        .or(running_default);
+    let mut pools = Vec::new();
+    let mut pool_size: Option<&str> = None;
+    for param in params {
+        if let Some(value) = param.strip_prefix("hugepagesz=").or_else(|| param.strip_prefix("default_hugepagesz=")) {
+            pool_size = Some(value);
+        } else if let Some(value) = param.strip_prefix("hugepages=") {
+            if let Ok(count) = value.parse() {
+                pools.push((pool_size.or(default_size).map(String::from), count));
+            }
+        }
+    }
+    pools
+}
+
+/// Returns the name of a `key=value` parameter
+fn param_key(param: &str) -> &str {
+    param.split_once('=').map_or(param, |(key, _)| key)
+}
+
+/// Reads one hugepages-<size>kB directory
+fn read_hugepage_size(dir: &Path) -> Option<HugepageSize> {
+    let size_kb = dir.file_name()?
+        .to_string_lossy()
+        .strip_prefix("hugepages-")?
+        .strip_suffix("kB")?
+        .parse()
+        .ok()?;
+    Some(HugepageSize {
+        size_kb,
+        total: read_count(&dir.join("nr_hugepages")).unwrap_or(0),
+        free: read_count(&dir.join("free_hugepages")).unwrap_or(0),
+        reserved: read_count(&dir.join("resv_hugepages")).unwrap_or(0),
+        surplus: read_count(&dir.join("surplus_hugepages")).unwrap_or(0),
+    })
+}
+
+/// Reads a page count attribute
+fn read_count(path: &Path) -> Option<u64> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Bootloader that edits parameters like GRUB: by key, sorted
+    struct KeyedBootloader {
+        params: Vec<String>,
+    }
+
+    impl BootloaderManager for KeyedBootloader {
+        fn get_config_parameters(&self) -> io::Result<Vec<String>> {
+            Ok(self.params.clone())
+        }
+
+        fn add_parameters(&mut self, params: &[&str], _dry_run: bool) -> io::Result<bool> {
+            let before = self.params.clone();
+            for param in params {
+                self.params.retain(|p| param_key(p) != param_key(param));
+                self.params.push(param.to_string());
+            }
+            self.params.sort();
+            Ok(self.params != before)
+        }
+
+        fn remove_parameters(&mut self, params: &[&str], _dry_run: bool) -> io::Result<bool> {
+            let before = self.params.len();
+            self.params.retain(|p| !params.iter().any(|param| param_key(p) == param_key(param)));
+            self.params.sort();
+            Ok(self.params.len() != before)
+        }
+
+        fn create_backup(&self) -> io::Result<Vec<PathBuf>> {
+            Ok(Vec::new())
+        }
+
+        fn update_bootloader(&self, _dry_run: bool) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn params(cmdline: &str) -> Vec<String> {
+        cmdline.split_whitespace().map(String::from).collect()
+    }
+
+    fn manager() -> HugepageManager {
+        let size = |size_kb| HugepageSize { size_kb, total: 0, free: 0, reserved: 0, surplus: 0 };
+        HugepageManager::new(HugepageStatus {
+            sizes: vec![size(2048), size(1048576)],
+            default_size_kb: Some(2048),
+            thp: ThpMode::Madvise,
+            mounts: Vec::new(),
+        })
+    }
+
+    #[test]
+    fn configured_pools_follow_kernel_order() {
+        let pools = configured_pools(&params("hugepages=64 hugepagesz=1G hugepages=4 hugepagesz=2M hugepages=512"), Some("2M"));
+        assert_eq!(pools, [(Some("2M".to_string()), 64), (Some("1G".to_string()), 4), (Some("2M".to_string()), 512)]);
+
+        // Sorted the way GRUB writes it, the count still goes to the 1G default
+        let pools = configured_pools(&params("default_hugepagesz=1G hugepages=16 hugepagesz=1G"), Some("2M"));
+        assert_eq!(pools, [(Some("1G".to_string()), 16)]);
+    }
+
+    #[test]
+    fn plan_replaces_count_and_default_of_same_size() {
+        let configured = params("quiet default_hugepagesz=2M hugepagesz=1G hugepages=8");
+        let (stale, wanted) = plan_boot_hugepages(&configured, "1G", 16, Some("2M")).unwrap();
+        assert_eq!(stale, ["default_hugepagesz=2M", "hugepages=8"]);
+        assert_eq!(wanted, ["default_hugepagesz=1G", "hugepagesz=1G", "hugepages=16"]);
+    }
+
+    #[test]
+    fn plan_refuses_pools_of_other_sizes() {
+        for cmdline in ["hugepagesz=2M hugepages=512", "hugepages=512", "default_hugepagesz=2M hugepages=512", "hugepagesz=2M"] {
+            let err = plan_boot_hugepages(&params(cmdline), "1G", 16, Some("2M")).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::AlreadyExists, "{}", cmdline);
+        }
+    }
+
+    #[test]
+    fn boot_hugepages_survive_keyed_sorted_bootloader() {
+        let mut bootloader = KeyedBootloader { params: params("quiet hugepagesz=1G hugepages=8") };
+        let changes = manager().configure_boot_hugepages(&mut bootloader, "Grub", 1048576, 16, false).unwrap();
+
+        assert_eq!(bootloader.params, params("default_hugepagesz=1G hugepages=16 hugepagesz=1G quiet"));
+        assert_eq!(configured_pools(&bootloader.params, Some("2M")), [(Some("1G".to_string()), 16)]);
+        assert!(changes.iter().any(|change| matches!(change,
+            Change::KernelParamRemoved { parameter, original_value, .. } if parameter == "hugepages" && original_value.as_deref() == Some("8"))));
+        let added: Vec<&str> = changes.iter()
+            .filter_map(|change| match change {
+                Change::KernelParamAdded { parameter, .. } => Some(parameter.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(added, ["default_hugepagesz=1G", "hugepages=16"]);
+
+        // A second run finds everything in place
+        let changes = manager().configure_boot_hugepages(&mut bootloader, "Grub", 1048576, 16, false).unwrap();
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn boot_hugepages_leave_other_pool_untouched() {
+        let mut bootloader = KeyedBootloader { params: params("hugepagesz=2M hugepages=512") };
+        assert!(manager().configure_boot_hugepages(&mut bootloader, "Grub", 1048576, 16, false).is_err());
+        assert_eq!(bootloader.params, params("hugepagesz=2M hugepages=512"));
+    }
+}
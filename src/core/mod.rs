@@ -3,4 +3,7 @@
 pub mod system;
 pub mod vfio;
 pub mod state;
-pub mod bootloader; // Add the bootloader module
\ No newline at end of file
+pub mod bootloader; // Add the bootloader module
+pub mod reboot;
+pub mod topology;
+pub mod hugepages;
@@ -0,0 +1,158 @@
+// Pending reboot detection for Exliar VFIO Automation Framework
+//
+// This module works out whether the running system reflects the saved
+// configuration: kernel parameters in the bootloader configuration are
+// compared with /proc/cmdline, and the module configuration files and
+// initramfs rebuilds recorded by the StateTracker with the boot time. It
+// only reads the system, so the result survives restarting the tool.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::core::bootloader::BootloaderManager;
+use crate::core::state::{Change, StateTracker};
+use crate::core::system::parse_cmdline;
+
+/// What the next boot will change
+#[derive(Debug, Clone, Default)]
+pub struct PendingReboot {
+    pub params_to_add: Vec<String>,    // In the bootloader configuration, not on the running command line
+    pub params_to_remove: Vec<String>, // Tracked parameters still running but gone from the configuration
+    pub files_changed: Vec<PathBuf>,   // Module configuration files modified since boot
+    pub initramfs_rebuilt: bool,       // The initramfs was rebuilt since boot
+    pub boot_time: Option<SystemTime>,
+}
+
+impl PendingReboot {
+    /// Compares the saved configuration with the running system
+    ///
+    /// `running_cmdline` is the active /proc/cmdline. Without a bootloader
+    /// manager only the tracked files are checked.
+    pub fn detect(
+        boot_manager: Option<&dyn BootloaderManager>,
+        state_tracker: Option<&StateTracker>,
+        running_cmdline: &str,
+    ) -> Self {
+        let changes = state_tracker.map_or(&[][..], |tracker| tracker.changes());
+        let boot_time = read_boot_time(Path::new("/proc"));
+        let mut pending = Self { boot_time, ..Self::default() };
+
+        if let Some(manager) = boot_manager {
+            match manager.get_config_parameters() {
+                Ok(configured) => pending.compare_parameters(&configured, running_cmdline, changes),
+                Err(e) => println!("Warning: Failed to read the bootloader's kernel parameters: {}", e),
+            }
+        }
+
+        // Without the boot time every change is treated as applied
+        let Some(boot_time) = boot_time else { return pending };
+        for change in changes {
+            match change {
+                Change::FileModified { path, .. } | Change::ModuleLoaded { config_path: path, .. } => {
+                    let modified = fs::metadata(path).and_then(|metadata| metadata.modified());
+                    if modified.is_ok_and(|time| time > boot_time) && !pending.files_changed.contains(path) {
+                        pending.files_changed.push(path.clone());
+                    }
+                }
+                Change::InitramfsRebuilt { rebuilt_at, .. } => {
+                    let rebuilt = u64::try_from(*rebuilt_at).map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+                    if rebuilt.is_ok_and(|time| time > boot_time) {
+                        pending.initramfs_rebuilt = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+        pending
+    }
+
+    /// Returns true if the running system doesn't reflect the saved configuration
+    pub fn is_required(&self) -> bool {
+        !self.params_to_add.is_empty()
+            || !self.params_to_remove.is_empty()
+            || !self.files_changed.is_empty()
+            || self.initramfs_rebuilt
+    }
+
+    /// Returns one line per pending item
+    pub fn details(&self) -> Vec<String> {
+        let mut details = Vec::new();
+        if !self.params_to_add.is_empty() {
+            details.push(format!("Kernel parameters not active yet: {}", self.params_to_add.join(" ")));
+        }
+        if !self.params_to_remove.is_empty() {
+            details.push(format!("Kernel parameters still active after removal: {}", self.params_to_remove.join(" ")));
+        }
+        for path in &self.files_changed {
+            details.push(format!("{} changed since boot", path.display()));
+        }
+        if self.initramfs_rebuilt {
+            details.push("Initramfs rebuilt since boot".to_string());
+        }
+        details
+    }
+
+    /// Returns a one-line description (e.g., "2 kernel parameter(s), initramfs")
+    pub fn summary(&self) -> String {
+        if !self.is_required() {
+            return "Not required".to_string();
+        }
+        let mut parts = Vec::new();
+        let params = self.params_to_add.len() + self.params_to_remove.len();
+        if params > 0 {
+            parts.push(format!("{} kernel parameter(s)", params));
+        }
+        if !self.files_changed.is_empty() {
+            parts.push(format!("{} config file(s)", self.files_changed.len()));
+        }
+        if self.initramfs_rebuilt {
+            parts.push("initramfs".to_string());
+        }
+        format!("Pending: {}", parts.join(", "))
+    }
+
+    /// Fills in the parameters that differ between configuration and running kernel
+    fn compare_parameters(&mut self, configured: &[String], running_cmdline: &str, changes: &[Change]) {
+        let running = parse_cmdline(running_cmdline);
+        self.params_to_add = configured.iter()
+            // GRUB variables (e.g., $vt_handoff) are expanded at boot
+            .filter(|param| !param.starts_with('$') && !running.contains(param))
+            .cloned()
+            .collect();
+
+        // Anything else on the running command line may come from elsewhere
+        // (GRUB_CMDLINE_LINUX, the boot entry), so only parameters we added
+        // or removed count as pending removals. An empty configuration means
+        // the manager can't read it, not that everything was removed.
+        if configured.is_empty() {
+            return;
+        }
+        let tracked_keys: Vec<&str> = changes.iter()
+            .filter_map(|change| match change {
+                Change::KernelParamAdded { parameter, .. } | Change::KernelParamRemoved { parameter, .. } => Some(param_key(parameter)),
+                _ => None,
+            })
+            .collect();
+        self.params_to_remove = running.iter()
+            .filter(|param| tracked_keys.contains(&param_key(param)))
+            .filter(|param| !configured.iter().any(|conf| param_key(conf) == param_key(param)))
+            .cloned()
+            .collect();
+    }
+}
+
+/// Reads the boot time from the btime line of /proc/stat
+pub fn read_boot_time(procfs_root: &Path) -> Option<SystemTime> {
+    fs::read_to_string(procfs_root.join("stat"))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))
+        .and_then(|secs| secs.trim().parse::<u64>().ok())
+        .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Returns the name of a `key=value` parameter
+fn param_key(param: &str) -> &str {
+    param.split_once('=').map_or(param, |(key, _)| key)
+}
@@ -4,10 +4,16 @@
 // allowing for potential rollback or cleanup operations.
 
 use std::fs;
-use std::io; // Removed unused Write import
+use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use serde::{Serialize, Deserialize};
 
+use std::process::Command;
+
+use crate::core::hugepages::{format_size, nr_hugepages_path};
+use crate::gpu::mdev::{remove_mdev, remove_persisted_mdev};
+use crate::gpu::sriov::set_num_vfs;
+
 // Assume PciDevice is accessible, e.g., from crate::gpu::detection
 // We might need to adjust imports based on actual project structure
 // use crate::gpu::detection::PciDevice;
@@ -31,6 +37,24 @@ pub enum Change {
     DriverBound { device_bdf: String, new_driver: String, original_driver: Option<String> },
     /// A device driver was unbound (original driver might be needed to rebind)
     DriverUnbound { device_bdf: String, original_driver: Option<String> },
+    /// SR-IOV virtual functions were created on a physical function
+    VfsCreated { pf_bdf: String, num_vfs: u16, original_num_vfs: u16 },
+    /// All SR-IOV virtual functions of a physical function were destroyed
+    VfsDestroyed { pf_bdf: String, original_num_vfs: u16 },
+    /// An SR-IOV virtual function was bound to vfio-pci
+    VfBound { vf_bdf: String, pf_bdf: String, original_driver: Option<String> },
+    /// A mediated device (e.g., GVT-g) was created, optionally persisted for mdevctl
+    MdevCreated { parent_bdf: String, uuid: String, mdev_type: String, definition_path: Option<PathBuf> },
+    /// A udev rule was installed (backup_path is None if the file didn't exist)
+    UdevRuleInstalled { path: PathBuf, backup_path: Option<PathBuf> },
+    /// A device's reset_method was changed (original_methods as read from sysfs, space-separated)
+    ResetMethodChanged { device_bdf: String, original_methods: String },
+    /// The initramfs was rebuilt (rebuilt_at in seconds since the Unix epoch)
+    InitramfsRebuilt { initramfs_system: String, rebuilt_at: i64 },
+    /// Hugepages were allocated at runtime (node is None for the global pool)
+    HugepagesAllocated { size_kb: u64, node: Option<u32>, count: u64, original_count: u64 },
+    /// A hugetlbfs was mounted (created_dir if the mount point didn't exist)
+    HugetlbfsMounted { mount_point: PathBuf, created_dir: bool },
     // Add other change types as needed (e.g., ServiceStarted, DirectoryCreated)
 }
 
@@ -72,6 +96,24 @@ impl StateTracker {
         self.save_state() // Save state after every change
     }
 
+    /// Drops the recorded changes that were reverted outside of a rollback and saves the state
+    ///
+    /// Returns the number of changes dropped.
+    pub fn forget_changes(&mut self, reverted: impl Fn(&Change) -> bool) -> io::Result<usize> {
+        let initial_len = self.changes.len();
+        self.changes.retain(|change| !reverted(change));
+        let dropped = initial_len - self.changes.len();
+        if dropped > 0 {
+            self.save_state()?;
+        }
+        Ok(dropped)
+    }
+
+    /// Returns the recorded changes, oldest first
+    pub fn changes(&self) -> &[Change] {
+        &self.changes
+    }
+
     /// Saves the current state to the specified file
     pub fn save_state(&self) -> io::Result<()> {
         let serialized_state = serde_json::to_string_pretty(&self)
@@ -213,6 +255,74 @@ impl StateTracker {
                      println!("  Original driver for {} unknown, cannot automatically rebind.", device_bdf);
                  }
             },
+            Change::VfsCreated { pf_bdf, num_vfs: _, original_num_vfs } |
+            Change::VfsDestroyed { pf_bdf, original_num_vfs } => {
+                println!("  Restoring {} VF(s) on {}", original_num_vfs, pf_bdf);
+                let pf_path = Path::new("/sys/bus/pci/devices").join(&pf_bdf);
+                set_num_vfs(&pf_path, original_num_vfs, false)?;
+            },
+            Change::VfBound { vf_bdf, pf_bdf: _, original_driver } => {
+                println!("  Releasing VF {} from vfio-pci (original driver: {:?})", vf_bdf, original_driver);
+                let vf_path = Path::new("/sys/bus/pci/devices").join(&vf_bdf);
+                if !vf_path.exists() {
+                    println!("  VF {} no longer exists, nothing to do.", vf_bdf);
+                    return Ok(());
+                }
+                write_sysfs(Path::new("/sys/bus/pci/drivers/vfio-pci/unbind"), &vf_bdf)?;
+                write_sysfs(&vf_path.join("driver_override"), "")?;
+                if original_driver.is_some() {
+                    write_sysfs(Path::new("/sys/bus/pci/drivers_probe"), &vf_bdf)?;
+                }
+            },
+            Change::MdevCreated { parent_bdf, uuid, mdev_type, definition_path } => {
+                println!("  Removing mediated device {} ({}) on {}", uuid, mdev_type, parent_bdf);
+                remove_mdev(&uuid, false)?;
+                if definition_path.is_some() {
+                    remove_persisted_mdev(&parent_bdf, &uuid, false)?;
+                }
+            },
+            Change::UdevRuleInstalled { path, backup_path } => {
+                match backup_path {
+                    Some(bp) if bp.exists() => {
+                        println!("  Restoring backup {} to {}", bp.display(), path.display());
+                        fs::rename(&bp, &path)?;
+                    },
+                    _ => {
+                        println!("  Removing udev rule {}", path.display());
+                        if path.exists() {
+                            fs::remove_file(&path)?;
+                        }
+                    },
+                }
+                println!("  Run 'udevadm control --reload-rules' to apply.");
+            },
+            Change::ResetMethodChanged { device_bdf, original_methods } => {
+                println!("  Restoring reset_method of {} to '{}'", device_bdf, original_methods);
+                let reset_method_path = Path::new("/sys/bus/pci/devices").join(&device_bdf).join("reset_method");
+                if reset_method_path.exists() {
+                    write_sysfs(&reset_method_path, &original_methods)?;
+                } else {
+                    println!("  Device {} no longer exists, nothing to do.", device_bdf);
+                }
+            },
+            Change::InitramfsRebuilt { initramfs_system, rebuilt_at: _ } => {
+                // Nothing to restore; the image is rebuilt from the reverted files
+                println!("  Manual action needed: Rebuild the initramfs ({}) once the configuration files are restored.", initramfs_system);
+            },
+            Change::HugepagesAllocated { size_kb, node, count: _, original_count } => {
+                println!("  Restoring {} {} hugepages{}", original_count, format_size(size_kb), node.map_or(String::new(), |n| format!(" on node {}", n)));
+                write_sysfs(&nr_hugepages_path(size_kb, node), &original_count.to_string())?;
+            },
+            Change::HugetlbfsMounted { mount_point, created_dir } => {
+                println!("  Unmounting hugetlbfs at {}", mount_point.display());
+                let status = Command::new("umount").arg(&mount_point).status()?;
+                if !status.success() {
+                    return Err(io::Error::other(format!("umount {} failed (still used by a VM?)", mount_point.display())));
+                }
+                if created_dir {
+                    fs::remove_dir(&mount_point)?;
+                }
+            },
             // Handle other change types...
         }
         Ok(())
@@ -311,7 +421,7 @@ impl StateTracker {
                          // Simplified rebind attempt - might not always work
                          script_content.push_str("# Clear override first\n");
                          script_content.push_str(&format!(
-                            "echo -n > \"/sys/bus/pci/devices/{}/driver_override\" 2>/dev/null\n", device_bdf.replace(':', "/")
+                            "echo -n > \"/sys/bus/pci/devices/{}/driver_override\" 2>/dev/null\n", device_bdf
                          ));
                          script_content.push_str("# Unbind from current (likely vfio-pci)\n");
                          script_content.push_str(&format!(
@@ -330,6 +440,128 @@ impl StateTracker {
                         ));
                      }
                  },
+                Change::VfsCreated { pf_bdf, num_vfs: _, original_num_vfs } |
+                Change::VfsDestroyed { pf_bdf, original_num_vfs } => {
+                    let numvfs_path = format!("/sys/bus/pci/devices/{}/sriov_numvfs", pf_bdf);
+                    script_content.push_str(&format!(
+                        "# Restore {} SR-IOV VF(s) on {}\n", original_num_vfs, pf_bdf
+                    ));
+                    // The kernel only accepts a new count while no VFs exist
+                    script_content.push_str(&format!(
+                        "echo 0 > \"{}\" || echo \"Failed to remove VFs of {}\"\n", numvfs_path, pf_bdf
+                    ));
+                    if *original_num_vfs > 0 {
+                        script_content.push_str(&format!(
+                            "echo {} > \"{}\" || echo \"Failed to recreate VFs of {}\"\n", original_num_vfs, numvfs_path, pf_bdf
+                        ));
+                    }
+                    script_content.push('\n');
+                },
+                Change::VfBound { vf_bdf, pf_bdf, original_driver } => {
+                    script_content.push_str(&format!(
+                        "# Release VF {} of {} from vfio-pci\n", vf_bdf, pf_bdf
+                    ));
+                    script_content.push_str(&format!(
+                        "if [ -e \"/sys/bus/pci/devices/{}\" ]; then\n", vf_bdf
+                    ));
+                    script_content.push_str(&format!(
+                        "  echo \"{}\" > \"/sys/bus/pci/drivers/vfio-pci/unbind\" 2>/dev/null\n", vf_bdf
+                    ));
+                    script_content.push_str(&format!(
+                        "  echo -n > \"/sys/bus/pci/devices/{}/driver_override\" 2>/dev/null\n", vf_bdf
+                    ));
+                    if original_driver.is_some() {
+                        script_content.push_str(&format!(
+                            "  echo \"{}\" > /sys/bus/pci/drivers_probe 2>/dev/null || echo \"Failed to trigger re-probe for {}\"\n", vf_bdf, vf_bdf
+                        ));
+                    }
+                    script_content.push_str("fi\n\n");
+                },
+                Change::MdevCreated { parent_bdf, uuid, mdev_type, definition_path } => {
+                    script_content.push_str(&format!(
+                        "# Remove mediated device {} ({}) on {}\n", uuid, mdev_type, parent_bdf
+                    ));
+                    script_content.push_str(&format!(
+                        "if [ -e \"/sys/bus/mdev/devices/{}\" ]; then\n", uuid
+                    ));
+                    script_content.push_str(&format!(
+                        "  echo 1 > \"/sys/bus/mdev/devices/{}/remove\" || echo \"Failed to remove {} (still used by a VM?)\"\n", uuid, uuid
+                    ));
+                    script_content.push_str("fi\n");
+                    if let Some(path) = definition_path {
+                        script_content.push_str(&format!(
+                            "rm -f \"{}\" || echo \"Error removing mdevctl definition\"\n", path.display()
+                        ));
+                    }
+                    script_content.push('\n');
+                },
+                Change::UdevRuleInstalled { path, backup_path } => {
+                    script_content.push_str(&format!(
+                        "# Revert udev rule {}\n", path.display()
+                    ));
+                    if let Some(bp) = backup_path {
+                        script_content.push_str(&format!(
+                            "if [ -f \"{}\" ]; then\n", bp.display()
+                        ));
+                        script_content.push_str(&format!(
+                            "  mv -f \"{}\" \"{}\" || echo \"Error restoring udev rule backup\"\n", bp.display(), path.display()
+                        ));
+                        script_content.push_str("else\n");
+                        script_content.push_str(&format!(
+                            "  rm -f \"{}\" || echo \"Error removing udev rule\"\n", path.display()
+                        ));
+                        script_content.push_str("fi\n");
+                    } else {
+                        script_content.push_str(&format!(
+                            "rm -f \"{}\" || echo \"Error removing udev rule\"\n", path.display()
+                        ));
+                    }
+                    script_content.push_str("udevadm control --reload-rules || echo \"Failed to reload udev rules\"\n\n");
+                },
+                Change::ResetMethodChanged { device_bdf, original_methods } => {
+                    let reset_method_path = format!("/sys/bus/pci/devices/{}/reset_method", device_bdf);
+                    script_content.push_str(&format!(
+                        "# Restore reset_method of {}\n", device_bdf
+                    ));
+                    script_content.push_str(&format!(
+                        "if [ -e \"{}\" ]; then\n", reset_method_path
+                    ));
+                    script_content.push_str(&format!(
+                        "  echo \"{}\" > \"{}\" || echo \"Failed to restore reset_method of {}\"\n", original_methods, reset_method_path, device_bdf
+                    ));
+                    script_content.push_str("fi\n\n");
+                },
+                Change::InitramfsRebuilt { initramfs_system, rebuilt_at: _ } => {
+                    script_content.push_str(&format!(
+                        "# Rebuild the initramfs ({}) once the configuration files below are restored\n", initramfs_system
+                    ));
+                    script_content.push_str(&format!(
+                        "echo \"Manual action needed: Rebuild the initramfs ({}) and reboot\"\n\n", initramfs_system
+                    ));
+                },
+                Change::HugepagesAllocated { size_kb, node, count: _, original_count } => {
+                    let nr_path = nr_hugepages_path(*size_kb, *node);
+                    script_content.push_str(&format!(
+                        "# Restore {} {} hugepages\n", original_count, format_size(*size_kb)
+                    ));
+                    script_content.push_str(&format!(
+                        "echo {} > \"{}\" || echo \"Failed to restore hugepages\"\n\n", original_count, nr_path.display()
+                    ));
+                },
+                Change::HugetlbfsMounted { mount_point, created_dir } => {
+                    script_content.push_str(&format!(
+                        "# Unmount hugetlbfs at {}\n", mount_point.display()
+                    ));
+                    script_content.push_str(&format!(
+                        "umount \"{}\" || echo \"Failed to unmount {} (still used by a VM?)\"\n", mount_point.display(), mount_point.display()
+                    ));
+                    if *created_dir {
+                        script_content.push_str(&format!(
+                            "rmdir \"{}\" 2>/dev/null || true\n", mount_point.display()
+                        ));
+                    }
+                    script_content.push('\n');
+                },
                 // Add cases for other Change types here...
                 // _ => {
                 //     script_content.push_str(&format!("# Cleanup action for {:?} not implemented\n\n", change));
@@ -347,4 +579,10 @@ impl StateTracker {
         self.changes.clear();
         self.save_state()
     }
-}
\ No newline at end of file
+}
+
+/// Writes a value to a sysfs attribute
+fn write_sysfs(path: &Path, value: &str) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    write!(file, "{}", value)
+}
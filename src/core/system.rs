@@ -3,14 +3,21 @@
 // This module handles detection of system properties such as:
 // - Bootloader type (GRUB, systemd-boot, etc.)
 // - Kernel version information
-// - CPU vendor and features
+// - CPU vendor, features and topology
 // - Init system (systemd, OpenRC, etc.)
 // - Distribution details
+// - Runtime IOMMU state (active units, groups, kernel command line)
+// - Hugepage sizes, allocations and THP mode
 
+use std::cmp::Ordering;
 use std::fs;
 use std::path::Path;
 use std::process::Command;
 
+use crate::core::hugepages::HugepageStatus;
+use crate::core::topology::CpuTopology;
+use crate::gpu::numa::format_cpulist;
+
 /// Represents the bootloader type detected on the system
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum BootloaderType {
@@ -72,6 +79,9 @@ pub struct Distribution { // Make struct public
 }
 
 /// Holds information about the kernel version
+///
+/// Versions compare by major, minor and patch (a missing patch counts as 0);
+/// the distribution suffix in `full_version` is ignored.
 #[derive(Debug, Clone)]
 pub struct KernelVersion { // Make struct public
     pub major: u32,
@@ -80,17 +90,149 @@ pub struct KernelVersion { // Make struct public
     pub full_version: String,
 }
 
+/// Kernel features whose availability depends on the kernel version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelFeature {
+    VfioVirqfdModule,  // vfio_virqfd is a module of its own (folded into vfio in 6.2)
+    ResetMethodSysfs,  // Per-device reset_method attribute
+    ResizableBarSysfs, // resourceN_resize attributes
+    Iommufd,           // /dev/iommu and iommufd-backed VFIO
+    VfioCdev,          // Per-device VFIO character devices (/dev/vfio/devices)
+}
+
+/// A (major, minor) kernel version bound of a feature
+type VersionBound = Option<(u32, u32)>;
+
+/// Feature table: feature, name, first version with it, first version without it
+const KERNEL_FEATURES: &[(KernelFeature, &str, VersionBound, VersionBound)] = &[
+    (KernelFeature::VfioVirqfdModule, "vfio_virqfd separate module", None, Some((6, 2))),
+    (KernelFeature::ResetMethodSysfs, "reset_method sysfs", Some((5, 15)), None),
+    (KernelFeature::ResizableBarSysfs, "resizable BAR sysfs", Some((6, 1)), None),
+    (KernelFeature::Iommufd, "iommufd", Some((6, 2)), None),
+    (KernelFeature::VfioCdev, "VFIO device cdev", Some((6, 6)), None),
+];
+
+impl KernelFeature {
+    /// Returns every feature of the table
+    pub fn all() -> impl Iterator<Item = KernelFeature> {
+        KERNEL_FEATURES.iter().map(|(feature, ..)| *feature)
+    }
+
+    /// Returns the feature's name (e.g., "reset_method sysfs")
+    pub fn name(&self) -> &'static str {
+        self.entry().1
+    }
+
+    /// Returns the first kernel version with the feature, if it was added
+    pub fn since(&self) -> Option<KernelVersion> {
+        self.entry().2.map(|(major, minor)| KernelVersion::new(major, minor, 0))
+    }
+
+    /// Returns the first kernel version without the feature, if it was removed
+    pub fn until(&self) -> Option<KernelVersion> {
+        self.entry().3.map(|(major, minor)| KernelVersion::new(major, minor, 0))
+    }
+
+    fn entry(&self) -> &'static (KernelFeature, &'static str, VersionBound, VersionBound) {
+        KERNEL_FEATURES.iter()
+            .find(|(feature, ..)| feature == self)
+            .expect("every KernelFeature has a KERNEL_FEATURES entry")
+    }
+}
+
+impl KernelVersion {
+    /// Creates a version for comparisons (e.g., `KernelVersion::new(6, 2, 0)`)
+    pub fn new(major: u32, minor: u32, patch: u32) -> Self {
+        Self { major, minor, patch: Some(patch), full_version: format!("{}.{}.{}", major, minor, patch) }
+    }
+
+    /// Detects the version of the running kernel
+    pub fn running() -> Self {
+        detect_kernel_version()
+    }
+
+    /// Returns true if the version could be detected
+    pub fn is_known(&self) -> bool {
+        self.major > 0
+    }
+
+    /// Returns true if this kernel has the feature
+    ///
+    /// An unknown version (0.0) has only features that were removed later,
+    /// which keeps the old behavior for them.
+    pub fn supports(&self, feature: KernelFeature) -> bool {
+        feature.since().is_none_or(|since| *self >= since)
+            && feature.until().is_none_or(|until| *self < until)
+    }
+
+    /// Returns the features of the table this kernel has
+    pub fn features(&self) -> Vec<KernelFeature> {
+        KernelFeature::all().filter(|feature| self.supports(*feature)).collect()
+    }
+
+    fn ordering_key(&self) -> (u32, u32, u32) {
+        (self.major, self.minor, self.patch.unwrap_or(0))
+    }
+}
+
+impl PartialEq for KernelVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.ordering_key() == other.ordering_key()
+    }
+}
+
+impl Eq for KernelVersion {}
+
+impl PartialOrd for KernelVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for KernelVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.ordering_key().cmp(&other.ordering_key())
+    }
+}
+
+/// Kind of IOMMU the kernel registered in /sys/class/iommu
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IommuKind {
+    IntelVtd, // dmarN units
+    AmdVi,    // ivhdN units
+    Other(String),
+    None,
+}
+
+/// IOMMU state of the running kernel
+///
+/// The CPU flags only say the machine can do virtualization; this reads
+/// whether the kernel actually enabled the IOMMU, which is what vfio-pci
+/// needs.
+#[derive(Debug, Clone)]
+pub struct IommuStatus {
+    pub kind: IommuKind,
+    pub units: Vec<String>,                // Entries of /sys/class/iommu (e.g., dmar0, ivhd0)
+    pub group_count: usize,                // Entries of /sys/kernel/iommu_groups
+    pub cmdline: String,                   // Active /proc/cmdline
+    pub interrupt_remapping: Option<bool>, // IR-* interrupt domains in /proc/interrupts; None if unreadable
+    pub passthrough: bool,                 // Host DMA bypasses translation (iommu=pt / identity domains)
+}
+
 /// Contains all detected system information
 #[derive(Debug, Clone)] // Add Clone derive
 pub struct SystemInfo { // Make struct public
     pub bootloader: BootloaderType,
     pub kernel_version: KernelVersion,
     pub cpu_vendor: CpuVendor,
+    pub cpu_topology: CpuTopology,
     pub virtualization_enabled: bool,
     pub init_system: InitSystem,
     pub initramfs_system: InitramfsSystem,
     pub secure_boot_enabled: Option<bool>,
     pub distribution: Option<Distribution>,
+    pub iommu: IommuStatus,
+    pub hugepages: HugepageStatus,
 }
 
 impl SystemInfo {
@@ -100,11 +242,14 @@ impl SystemInfo {
             bootloader: detect_bootloader(),
             kernel_version: detect_kernel_version(),
             cpu_vendor: detect_cpu_vendor(),
+            cpu_topology: CpuTopology::detect(),
             virtualization_enabled: check_virtualization_support(),
             init_system: detect_init_system(),
             initramfs_system: detect_initramfs_system(),
             secure_boot_enabled: detect_secure_boot(),
             distribution: detect_distribution(),
+            iommu: IommuStatus::detect(),
+            hugepages: HugepageStatus::detect(),
         }
     }
 
@@ -113,8 +258,17 @@ impl SystemInfo {
         let mut summary = String::new();
         
         summary.push_str(&format!("Kernel: {}\n", self.kernel_version.full_version));
+        let features: Vec<&str> = self.kernel_version.features().iter().map(|feature| feature.name()).collect();
+        summary.push_str(&format!("Kernel Features: {}\n", if features.is_empty() { "none".to_string() } else { features.join(", ") }));
         summary.push_str(&format!("Bootloader: {:?}\n", self.bootloader));
         summary.push_str(&format!("CPU Vendor: {:?}\n", self.cpu_vendor));
+        summary.push_str(&format!("CPU Topology: {}\n", self.cpu_topology.summary()));
+        if self.cpu_topology.l3_domains.len() > 1 {
+            summary.push_str(&format!("L3 Domains: {}\n", self.cpu_topology.l3_summary()));
+        }
+        if !self.cpu_topology.isolated.is_empty() {
+            summary.push_str(&format!("Isolated CPUs: {}\n", format_cpulist(&self.cpu_topology.isolated)));
+        }
         summary.push_str(&format!("Virtualization: {}\n", 
             if self.virtualization_enabled { "Enabled" } else { "Disabled" }));
         
@@ -135,11 +289,132 @@ impl SystemInfo {
         } else {
             summary.push_str("Secure Boot: Unknown\n");
         }
+
+        summary.push_str(&format!("IOMMU: {}\n", self.iommu.summary()));
+        summary.push_str(&format!("Hugepages: {}\n", self.hugepages.summary()));
         
         summary
     }
 }
 
+impl IommuStatus {
+    /// Detects the IOMMU state of the running kernel
+    pub fn detect() -> Self {
+        Self::detect_from(Path::new("/sys"), Path::new("/proc"))
+    }
+
+    /// Detects the IOMMU state from the given sysfs and procfs roots
+    pub fn detect_from(sysfs_root: &Path, procfs_root: &Path) -> Self {
+        let units: Vec<String> = read_dir_names(&sysfs_root.join("class/iommu"));
+        let kind = match units.first() {
+            None => IommuKind::None,
+            Some(unit) if unit.starts_with("dmar") => IommuKind::IntelVtd,
+            Some(unit) if unit.starts_with("ivhd") => IommuKind::AmdVi,
+            Some(unit) => IommuKind::Other(unit.clone()),
+        };
+        let groups_dir = sysfs_root.join("kernel/iommu_groups");
+        let groups = read_dir_names(&groups_dir);
+        let cmdline = fs::read_to_string(procfs_root.join("cmdline"))
+            .map(|cmdline| cmdline.trim().to_string())
+            .unwrap_or_default();
+
+        // The default domain type of the groups is authoritative; the command
+        // line is the fallback for kernels without the type attribute
+        let group_types: Vec<String> = groups.iter()
+            .filter_map(|group| fs::read_to_string(groups_dir.join(group).join("type")).ok())
+            .map(|domain| domain.trim().to_string())
+            .collect();
+        let passthrough = if group_types.is_empty() {
+            cmdline_has(&cmdline, "iommu=pt") || cmdline_has(&cmdline, "amd_iommu=pt")
+        } else {
+            group_types.iter().any(|domain| domain == "identity")
+        };
+
+        let interrupt_remapping = fs::read_to_string(procfs_root.join("interrupts"))
+            .ok()
+            .map(|interrupts| interrupts.contains(" IR-"));
+
+        Self { kind, units, group_count: groups.len(), cmdline, interrupt_remapping, passthrough }
+    }
+
+    /// Returns true if the kernel registered an IOMMU
+    pub fn is_active(&self) -> bool {
+        !self.units.is_empty()
+    }
+
+    /// Returns true if devices can be assigned to vfio-pci: the IOMMU is
+    /// active and the kernel built IOMMU groups
+    pub fn is_usable(&self) -> bool {
+        self.is_active() && self.group_count > 0
+    }
+
+    /// Returns true if the active command line contains the parameter
+    /// (either `key=value` exactly or a bare flag)
+    pub fn has_parameter(&self, parameter: &str) -> bool {
+        cmdline_has(&self.cmdline, parameter)
+    }
+
+    /// Returns true if vfio will assign devices without interrupt remapping
+    pub fn allows_unsafe_interrupts(&self) -> bool {
+        self.has_parameter("vfio_iommu_type1.allow_unsafe_interrupts=1")
+    }
+
+    /// Returns the kernel parameters that enable the IOMMU for the CPU vendor
+    ///
+    /// AMD-Vi is on by default, Intel VT-d needs intel_iommu=on on most
+    /// distribution kernels. Passthrough mode keeps host DMA fast.
+    pub fn required_parameters(cpu_vendor: &CpuVendor) -> Vec<&'static str> {
+        match cpu_vendor {
+            CpuVendor::Intel => vec!["intel_iommu=on", "iommu=pt"],
+            CpuVendor::AMD | CpuVendor::Other(_) => vec!["iommu=pt"],
+        }
+    }
+
+    /// Returns the problems that prevent or weaken device assignment
+    pub fn issues(&self, cpu_vendor: &CpuVendor) -> Vec<String> {
+        let mut issues = Vec::new();
+        if !self.is_active() {
+            let missing: Vec<&str> = Self::required_parameters(cpu_vendor).into_iter()
+                .filter(|param| !self.has_parameter(param))
+                .collect();
+            if missing.is_empty() {
+                issues.push("IOMMU is not active although the kernel parameters are set; enable VT-d/AMD-Vi (IOMMU) in the firmware setup".to_string());
+            } else {
+                issues.push(format!("IOMMU is not active; add {} to the kernel command line and check VT-d/AMD-Vi in the firmware setup", missing.join(" ")));
+            }
+        } else if self.group_count == 0 {
+            issues.push("IOMMU is active but no IOMMU groups exist; the kernel may be running in translation-off mode".to_string());
+        }
+        if self.is_active() && self.interrupt_remapping == Some(false) && !self.allows_unsafe_interrupts() {
+            issues.push("No interrupt remapping: vfio will refuse devices unless vfio_iommu_type1.allow_unsafe_interrupts=1 is set".to_string());
+        }
+        issues
+    }
+
+    /// Returns a one-line description (e.g., "Intel VT-d, 24 groups, passthrough, IR")
+    pub fn summary(&self) -> String {
+        if !self.is_active() {
+            return "Not active".to_string();
+        }
+        let kind = match &self.kind {
+            IommuKind::IntelVtd => "Intel VT-d".to_string(),
+            IommuKind::AmdVi => "AMD-Vi".to_string(),
+            IommuKind::Other(unit) => unit.clone(),
+            IommuKind::None => "Unknown".to_string(),
+        };
+        let mut parts = vec![kind, format!("{} groups", self.group_count)];
+        if self.passthrough {
+            parts.push("passthrough".to_string());
+        }
+        match self.interrupt_remapping {
+            Some(true) => parts.push("interrupt remapping".to_string()),
+            Some(false) => parts.push("no interrupt remapping".to_string()),
+            None => {}
+        }
+        parts.join(", ")
+    }
+}
+
 /// Detects the bootloader type used by the system
 fn detect_bootloader() -> BootloaderType {
     // Check for GRUB
@@ -389,4 +664,49 @@ fn determine_distro_family(id: &str, id_like: &str) -> Option<DistroFamily> {
     } else {
          None
     }
-}
\ No newline at end of file
+}
+
+/// Returns the sorted entry names of a directory, or nothing if it doesn't exist
+fn read_dir_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(dir)
+        .map(|entries| entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Splits a kernel command line into parameters
+///
+/// Double quotes group a value containing spaces (e.g., `dyndbg="file x +p"`)
+/// and are kept, as in the bootloader configuration.
+pub fn parse_cmdline(cmdline: &str) -> Vec<String> {
+    let mut params = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in cmdline.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c);
+            }
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    params.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        params.push(current);
+    }
+    params
+}
+
+/// Returns true if a kernel command line contains the parameter
+fn cmdline_has(cmdline: &str, parameter: &str) -> bool {
+    parse_cmdline(cmdline).iter().any(|arg| arg == parameter)
+}
@@ -0,0 +1,161 @@
+// CPU topology detection for Exliar VFIO Automation Framework
+//
+// This module reads the host's CPU layout from /sys/devices/system/cpu:
+// packages, dies, physical cores with their SMT siblings, L3 cache domains
+// (CCDs/CCXs on AMD) and the CPUs isolated from the scheduler. It is the
+// base for CPU pinning, isolcpus and emulator thread placement.
+
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::gpu::numa::{format_cpulist, parse_cpulist};
+
+/// A physical core and its hardware threads
+#[derive(Debug, Clone)]
+pub struct CpuCore {
+    pub package: u32,      // physical_package_id
+    pub die: u32,          // die_id (0 if the kernel doesn't report dies)
+    pub core_id: u32,      // core_id, unique within a die
+    pub threads: Vec<u32>, // Logical CPUs of the core (SMT siblings), sorted
+}
+
+/// CPUs sharing one last-level (L3) cache
+#[derive(Debug, Clone)]
+pub struct CacheDomain {
+    pub cpus: Vec<u32>,
+    pub size_kb: Option<u32>,
+}
+
+/// CPU topology of the host
+#[derive(Debug, Clone, Default)]
+pub struct CpuTopology {
+    pub online: Vec<u32>,
+    pub cores: Vec<CpuCore>,          // Sorted by package, die and first thread
+    pub l3_domains: Vec<CacheDomain>, // Sorted by first CPU
+    pub isolated: Vec<u32>,           // isolcpus= (removed from the scheduler)
+    pub nohz_full: Vec<u32>,          // nohz_full= (tickless)
+}
+
+impl CpuTopology {
+    /// Reads the topology of the running system
+    pub fn detect() -> Self {
+        Self::detect_from(Path::new("/sys"))
+    }
+
+    /// Reads the topology from the given sysfs root
+    pub fn detect_from(sysfs_root: &Path) -> Self {
+        let cpu_dir = sysfs_root.join("devices/system/cpu");
+        let read_list = |name: &str| fs::read_to_string(cpu_dir.join(name)).map(|list| parse_cpulist(&list)).unwrap_or_default();
+        let online = read_list("online");
+
+        let mut cores: Vec<CpuCore> = Vec::new();
+        let mut l3_domains: Vec<CacheDomain> = Vec::new();
+        for &cpu in &online {
+            let path = cpu_dir.join(format!("cpu{}", cpu));
+            let read_id = |attr: &str| fs::read_to_string(path.join("topology").join(attr))
+                .ok()
+                .and_then(|value| value.trim().parse::<i64>().ok())
+                .and_then(|id| u32::try_from(id).ok());
+
+            let package = read_id("physical_package_id").unwrap_or(0);
+            let die = read_id("die_id").unwrap_or(0);
+            let core_id = read_id("core_id").unwrap_or(cpu);
+            match cores.iter_mut().find(|core| core.package == package && core.die == die && core.core_id == core_id) {
+                Some(core) => core.threads.push(cpu),
+                None => cores.push(CpuCore { package, die, core_id, threads: vec![cpu] }),
+            }
+
+            if let Some(domain) = read_l3_domain(&path) {
+                if !l3_domains.iter().any(|known| known.cpus == domain.cpus) {
+                    l3_domains.push(domain);
+                }
+            }
+        }
+        for core in &mut cores {
+            core.threads.sort_unstable();
+        }
+        cores.sort_by_key(|core| (core.package, core.die, core.threads.first().copied()));
+        l3_domains.sort_by_key(|domain| domain.cpus.first().copied());
+
+        Self { online, cores, l3_domains, isolated: read_list("isolated"), nohz_full: read_list("nohz_full") }
+    }
+
+    /// Returns the number of CPU packages (sockets)
+    pub fn package_count(&self) -> usize {
+        self.cores.iter().map(|core| core.package).collect::<BTreeSet<_>>().len()
+    }
+
+    /// Returns the number of dies across all packages
+    pub fn die_count(&self) -> usize {
+        self.cores.iter().map(|core| (core.package, core.die)).collect::<BTreeSet<_>>().len()
+    }
+
+    /// Returns true if any core runs more than one hardware thread
+    pub fn smt_active(&self) -> bool {
+        self.cores.iter().any(|core| core.threads.len() > 1)
+    }
+
+    /// Returns the SMT siblings of a CPU, including the CPU itself
+    pub fn siblings_of(&self, cpu: u32) -> &[u32] {
+        self.cores.iter()
+            .find(|core| core.threads.contains(&cpu))
+            .map_or(&[], |core| core.threads.as_slice())
+    }
+
+    /// Returns the L3 cache domain a CPU belongs to
+    pub fn l3_domain_of(&self, cpu: u32) -> Option<&CacheDomain> {
+        self.l3_domains.iter().find(|domain| domain.cpus.contains(&cpu))
+    }
+
+    /// Returns the online CPUs the host scheduler may use (not isolated)
+    pub fn housekeeping_cpus(&self) -> Vec<u32> {
+        self.online.iter().copied().filter(|cpu| !self.isolated.contains(cpu)).collect()
+    }
+
+    /// Returns a one-line description (e.g., "1 package, 16 cores / 32 threads, 2 L3 domains of 32 MB")
+    pub fn summary(&self) -> String {
+        if self.cores.is_empty() {
+            return "Unknown".to_string();
+        }
+        let count = |n: usize, noun: &str| format!("{} {}{}", n, noun, if n == 1 { "" } else { "s" });
+        let packages = self.package_count();
+        let mut summary = count(packages, "package");
+        let dies = self.die_count();
+        if dies > packages {
+            summary.push_str(&format!(", {} dies", dies));
+        }
+        summary.push_str(&format!(", {} / {}", count(self.cores.len(), "core"), count(self.online.len(), "thread")));
+        if !self.l3_domains.is_empty() {
+            summary.push_str(&format!(", {}", count(self.l3_domains.len(), "L3 domain")));
+            let first_size = self.l3_domains[0].size_kb;
+            if let Some(size_kb) = first_size.filter(|_| self.l3_domains.iter().all(|domain| domain.size_kb == first_size)) {
+                summary.push_str(&format!(" of {} MB", size_kb / 1024));
+            }
+        }
+        summary
+    }
+
+    /// Returns the CPU lists of the L3 domains (e.g., "0-7,16-23 | 8-15,24-31")
+    pub fn l3_summary(&self) -> String {
+        self.l3_domains.iter()
+            .map(|domain| format_cpulist(&domain.cpus))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+/// Reads the L3 (unified, level 3) cache a CPU shares
+fn read_l3_domain(cpu_path: &Path) -> Option<CacheDomain> {
+    let entries = fs::read_dir(cpu_path.join("cache")).ok()?;
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_name().to_string_lossy().starts_with("index"))
+        .map(|entry| entry.path())
+        .find(|index| fs::read_to_string(index.join("level")).is_ok_and(|level| level.trim() == "3"))
+        .map(|index| CacheDomain {
+            cpus: fs::read_to_string(index.join("shared_cpu_list")).map(|list| parse_cpulist(&list)).unwrap_or_default(),
+            size_kb: fs::read_to_string(index.join("size")).ok().and_then(|size| size.trim().trim_end_matches('K').parse().ok()),
+        })
+        .filter(|domain| !domain.cpus.is_empty())
+}
@@ -4,13 +4,47 @@
 // for specified PCI devices, including modprobe configuration,
 // initramfs updates, and device binding.
 
+use std::fmt;
 use std::fs;
 use std::io::{self, Write};
 use std::path::{Path, PathBuf};
 use std::process::Command; // Import Command
 
-use crate::core::system::{SystemInfo, InitramfsSystem}; // Import InitramfsSystem
+use crate::core::state::Change;
+use crate::core::system::{SystemInfo, InitramfsSystem, KernelFeature, KernelVersion}; // Import InitramfsSystem
 use crate::gpu::detection::PciDevice;
+use crate::gpu::pci::read_pci_device;
+use crate::gpu::GpuDevice;
+use crate::gpu::config_space::read_config_space;
+use crate::gpu::reset::{
+    check_config_after_reset, read_reset_methods, trigger_reset, wait_for_config_space, ResetMethod, ResetTestError,
+    ResetTestOutcome, ResetTestResult, RESET_SETTLE_TIMEOUT, RESET_SETTLE_TIMEOUT_BUS,
+};
+use crate::gpu::sriov::{list_virtual_functions, physical_function, read_sriov_info, set_num_vfs};
+use crate::gpu::vendor_reset::{
+    load_vendor_reset, reload_udev_rules, set_device_specific_reset, vendor_reset_family, vendor_reset_udev_rule,
+    VendorResetStatus, VENDOR_RESET_MODULE, VENDOR_RESET_MODULES_LOAD_PATH, VENDOR_RESET_UDEV_RULE_PATH,
+};
+
+/// A vendor-reset setup that failed part way
+#[derive(Debug)]
+pub struct VendorResetError {
+    pub error: io::Error,
+    pub changes: Vec<Change>, // Changes made before the failure, still to be recorded
+}
+
+impl VendorResetError {
+    /// Creates an error for a setup that changed nothing
+    pub fn new(error: io::Error) -> Self {
+        Self { error, changes: Vec::new() }
+    }
+}
+
+impl fmt::Display for VendorResetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
 
 /// Manages VFIO configuration and device binding
 pub struct VfioManager {
@@ -31,8 +65,8 @@ impl VfioManager {
     ///     dry_run: If true, only log actions without modifying files
     ///
     /// Returns:
-    ///     Result indicating success or failure
-    pub fn configure_modprobe(&self, device_ids: &[String], dry_run: bool) -> io::Result<()> {
+    ///     The changes made, for the state tracker (empty if the files were up-to-date)
+    pub fn configure_modprobe(&self, device_ids: &[String], dry_run: bool) -> io::Result<Vec<Change>> {
         println!("Configuring VFIO driver options via modprobe...");
 
         let modprobe_dir = Path::new("/etc/modprobe.d");
@@ -69,9 +103,11 @@ impl VfioManager {
             // Also ensure modules load early via /etc/modules-load.d/
             let modules_load_path = Path::new("/etc/modules-load.d/vfio-pci-load.conf");
             println!("[DRY RUN] Would ensure VFIO modules are listed in {}", modules_load_path.display());
-            return Ok(());
+            return Ok(Vec::new());
         }
 
+        let mut changes = Vec::new();
+
         // --- Configure vfio.conf ---
         let current_vfio_content = fs::read_to_string(&vfio_conf_path).unwrap_or_default();
         let mut new_vfio_lines = Vec::new();
@@ -112,11 +148,9 @@ impl VfioManager {
         }
         let new_vfio_content_str = new_vfio_lines.join("\n") + "\n";
         if new_vfio_content_str != current_vfio_content {
-            let backup_path = create_timestamped_backup(&vfio_conf_path)?;
-            println!("Created backup: {}", backup_path.display());
-            let mut file = fs::File::create(&vfio_conf_path)?;
-            file.write_all(new_vfio_content_str.as_bytes())?;
+            let backup_path = write_with_backup(&vfio_conf_path, &new_vfio_content_str)?;
             println!("Successfully updated {}", vfio_conf_path.display());
+            changes.push(Change::ModuleLoaded { name: "vfio-pci".to_string(), config_path: vfio_conf_path.clone(), backup_path });
         } else {
             println!("{} is already up-to-date.", vfio_conf_path.display());
         }
@@ -128,27 +162,79 @@ impl VfioManager {
              fs::create_dir_all(modules_load_dir)?;
         }
         let modules_load_path = modules_load_dir.join("vfio-pci-load.conf");
-        let vfio_modules_to_load = [
-            "vfio",
-            "vfio_iommu_type1",
-            "vfio_pci",
-            // vfio_virqfd is often needed for older kernels, include for broader compatibility
-            "vfio_virqfd",
-        ];
+        let vfio_modules_to_load = vfio_modules(&self.system_info.kernel_version);
         let modules_load_content = vfio_modules_to_load.join("\n") + "\n";
         let current_load_content = fs::read_to_string(&modules_load_path).unwrap_or_default();
 
         if modules_load_content != current_load_content {
-             let backup_path = create_timestamped_backup(&modules_load_path)?;
-             println!("Created backup: {}", backup_path.display());
-             let mut file = fs::File::create(&modules_load_path)?;
-             file.write_all(modules_load_content.as_bytes())?;
+             let backup_path = write_with_backup(&modules_load_path, &modules_load_content)?;
              println!("Successfully updated {}", modules_load_path.display());
+             changes.push(Change::ModuleLoaded { name: "vfio_pci".to_string(), config_path: modules_load_path, backup_path });
         } else {
              println!("{} is already up-to-date.", modules_load_path.display());
         }
 
-        Ok(())
+        Ok(changes)
+    }
+
+    /// Adds the VFIO modules to the initramfs configuration
+    ///
+    /// Loading vfio-pci from the initramfs lets it claim the GPU before a
+    /// graphics driver does. Each initramfs system gets a drop-in file where
+    /// it supports one.
+    ///
+    /// Args:
+    ///     dry_run: If true, only log actions without modifying files
+    ///
+    /// Returns:
+    ///     The changes made, for the state tracker (empty if the configuration was up-to-date)
+    pub fn configure_initramfs_modules(&self, dry_run: bool) -> io::Result<Vec<Change>> {
+        // vfio-pci first so it's loaded before anything it softdeps on is probed
+        let mut modules = vfio_modules(&self.system_info.kernel_version);
+        modules.sort_by_key(|module| *module != "vfio_pci");
+
+        let (config_path, content) = match self.system_info.initramfs_system {
+            InitramfsSystem::Mkinitcpio => (
+                PathBuf::from("/etc/mkinitcpio.conf.d/10-exliar-vfio.conf"),
+                format!("# Generated by Exliar VFIO\nMODULES+=({})\n", modules.join(" ")),
+            ),
+            InitramfsSystem::Dracut => (
+                PathBuf::from("/etc/dracut.conf.d/10-exliar-vfio.conf"),
+                format!("# Generated by Exliar VFIO\nforce_drivers+=\" {} \"\n", modules.join(" ")),
+            ),
+            InitramfsSystem::Debian => {
+                // initramfs-tools has no drop-in directory; keep the user's lines
+                let path = PathBuf::from("/etc/initramfs-tools/modules");
+                let current = fs::read_to_string(&path).unwrap_or_default();
+                let mut lines: Vec<&str> = current.lines()
+                    // Drop modules this kernel doesn't have (e.g., vfio_virqfd after 6.2)
+                    .filter(|line| line.trim() != "vfio_virqfd" || modules.contains(&"vfio_virqfd"))
+                    .collect();
+                for module in &modules {
+                    if !lines.iter().any(|line| line.trim() == *module) {
+                        lines.push(module);
+                    }
+                }
+                (path, lines.join("\n") + "\n")
+            }
+            _ => {
+                println!("Warning: Cannot add VFIO modules to the {:?} initramfs automatically; add {} yourself.",
+                         self.system_info.initramfs_system, modules.join(", "));
+                return Ok(Vec::new());
+            }
+        };
+
+        if fs::read_to_string(&config_path).unwrap_or_default() == content {
+            println!("{} is already up-to-date.", config_path.display());
+            return Ok(Vec::new());
+        }
+        if dry_run {
+            println!("[DRY RUN] Would write {}:\n{}", config_path.display(), content);
+            return Ok(Vec::new());
+        }
+        let backup_path = write_with_backup(&config_path, &content)?;
+        println!("Successfully updated {}", config_path.display());
+        Ok(vec![Change::ModuleLoaded { name: "vfio_pci".to_string(), config_path, backup_path }])
     }
 
     /// Updates the initramfs based on the detected system type
@@ -157,8 +243,8 @@ impl VfioManager {
     ///     dry_run: If true, only log actions without modifying files
     ///
     /// Returns:
-    ///     Result indicating success or failure
-    pub fn update_initramfs(&self, dry_run: bool) -> io::Result<()> {
+    ///     True if the initramfs was rebuilt, false if it couldn't be (unknown system, dry run)
+    pub fn update_initramfs(&self, dry_run: bool) -> io::Result<bool> {
         println!("Updating initramfs using {:?}...", self.system_info.initramfs_system);
 
         // Determine the command based on the detected initramfs system
@@ -173,7 +259,7 @@ impl VfioManager {
             InitramfsSystem::Booster => Some(vec!["booster", "build"]), // Assuming booster command
             _ => {
                 println!("Warning: Unsupported or unknown initramfs system ({:?}). Cannot update automatically.", self.system_info.initramfs_system);
-                return Ok(()); // Not an error, just can't proceed
+                return Ok(false); // Not an error, just can't proceed
             }
         };
 
@@ -181,7 +267,7 @@ impl VfioManager {
             let command_str = parts.join(" ");
             if dry_run {
                 println!("[DRY RUN] Would execute: {}", command_str);
-                return Ok(());
+                return Ok(false);
             }
 
             println!("Executing: {}", command_str);
@@ -191,7 +277,7 @@ impl VfioManager {
 
             if status.success() {
                 println!("Initramfs updated successfully.");
-                Ok(())
+                Ok(true)
             } else {
                 let err_msg = format!("Initramfs update command failed with exit code: {:?}", status.code());
                 println!("Error: {}", err_msg);
@@ -199,7 +285,7 @@ impl VfioManager {
             }
         } else {
              // This case should ideally not be reached due to the match statement covering '_'
-             Ok(())
+             Ok(false)
         }
     }
 
@@ -269,6 +355,322 @@ impl VfioManager {
         Ok(())
     }
 
+    /// Binds every function of a GPU's slot to vfio-pci as one unit
+    ///
+    /// Args:
+    ///     gpu: The GPU whose slot (GPU plus companion functions) should be bound
+    ///     dry_run: If true, only log actions without modifying files
+    ///
+    /// Returns:
+    ///     Result indicating success or failure
+    pub fn bind_slot(&self, gpu: &GpuDevice, dry_run: bool) -> io::Result<()> {
+        for device in slot_devices(gpu)? {
+            self.bind_device(&device, dry_run)?;
+        }
+        Ok(())
+    }
+
+    /// Unbinds every function of a GPU's slot from vfio-pci
+    ///
+    /// Args:
+    ///     gpu: The GPU whose slot should be released back to host drivers
+    ///     dry_run: If true, only log actions without modifying files
+    ///
+    /// Returns:
+    ///     Result indicating success or failure
+    pub fn unbind_slot(&self, gpu: &GpuDevice, dry_run: bool) -> io::Result<()> {
+        for device in slot_devices(gpu)? {
+            self.unbind_device(&device, dry_run)?;
+        }
+        Ok(())
+    }
+
+    /// Tests whether a GPU survives a device reset
+    ///
+    /// Binds the slot to vfio-pci (the functions not on it already), triggers a reset
+    /// through sysfs and checks that config space still reads back sanely.
+    /// The original host drivers are restored afterwards.
+    ///
+    /// Args:
+    ///     gpu: The GPU to reset
+    ///     dry_run: If true, only log actions without touching the device
+    ///
+    /// Returns:
+    ///     The test result, or an error listing the functions it left on vfio-pci
+    pub fn reset_test(&self, gpu: &GpuDevice, dry_run: bool) -> Result<ResetTestResult, ResetTestError> {
+        println!("Testing reset of {}...", gpu.bdf);
+
+        let sysfs_path = Path::new("/sys/bus/pci/devices").join(&gpu.bdf);
+        let methods = read_reset_methods(&sysfs_path);
+        let method = methods.as_ref().and_then(|m| m.first().cloned());
+
+        if methods.as_ref().is_some_and(|m| m.is_empty()) || !sysfs_path.join("reset").exists() {
+            return Ok(ResetTestResult::new(ResetTestOutcome::NotRun, None, "The kernel has no reset method for this device"));
+        }
+
+        // The drivers detected at startup may be stale; only functions not
+        // on vfio-pci right now are taken over and handed back
+        let to_bind: Vec<PciDevice> = slot_devices(gpu).map_err(ResetTestError::new)?
+            .into_iter()
+            .filter(|device| device.driver.as_deref() != Some("vfio-pci"))
+            .collect();
+        if dry_run {
+            for device in &to_bind {
+                self.bind_device(device, true).map_err(ResetTestError::new)?;
+            }
+            trigger_reset(&sysfs_path, true).map_err(ResetTestError::new)?;
+            for device in &to_bind {
+                self.unbind_device(device, true).map_err(ResetTestError::new)?;
+            }
+            return Ok(ResetTestResult::new(ResetTestOutcome::NotRun, method, "Dry run, device not reset"));
+        }
+
+        // 1. Take the device away from its host driver, one function at a time
+        let mut bound = Vec::new();
+        for device in to_bind {
+            if let Err(e) = self.bind_device(&device, false) {
+                // Hand back what was already taken before giving up
+                let (left_on_vfio, _) = self.release_devices(bound);
+                return Err(ResetTestError { error: e, left_on_vfio });
+            }
+            bound.push(device);
+        }
+
+        // 2. Reset and compare config space before and after
+        let result = match read_config_space(&sysfs_path) {
+            Ok(before) => match trigger_reset(&sysfs_path, false) {
+                Ok(()) => {
+                    // Bus resets take the whole secondary bus down and need longer to come back
+                    let timeout = if method == Some(ResetMethod::Bus) { RESET_SETTLE_TIMEOUT_BUS } else { RESET_SETTLE_TIMEOUT };
+                    match wait_for_config_space(&sysfs_path, timeout).map_err(|e| e.to_string())
+                        .and_then(|after| check_config_after_reset(&before, &after)) {
+                        Ok(()) => ResetTestResult::new(ResetTestOutcome::Passed, method, "Config space intact after reset"),
+                        Err(e) => ResetTestResult::new(ResetTestOutcome::Failed, method, e),
+                    }
+                }
+                Err(e) => ResetTestResult::new(ResetTestOutcome::Failed, method, format!("Reset failed: {}", e)),
+            },
+            Err(e) => ResetTestResult::new(ResetTestOutcome::NotRun, method, format!("Cannot read config space: {}", e)),
+        };
+
+        // 3. Hand the device back to its host driver
+        if let (left_on_vfio, Some(e)) = self.release_devices(bound) {
+            return Err(ResetTestError {
+                error: io::Error::new(
+                    e.kind(),
+                    format!("Reset test {:?} ({}), but restoring the host driver failed: {}", result.outcome, result.detail, e),
+                ),
+                left_on_vfio,
+            });
+        }
+
+        println!("Reset test of {}: {:?} ({})", gpu.bdf, result.outcome, result.detail);
+        Ok(result)
+    }
+
+    /// Unbinds devices from vfio-pci so their host drivers can claim them again
+    ///
+    /// Returns the devices that are still bound and the first error, if any.
+    fn release_devices(&self, devices: Vec<PciDevice>) -> (Vec<PciDevice>, Option<io::Error>) {
+        let mut left_on_vfio = Vec::new();
+        let mut first_error = None;
+        for device in devices {
+            if let Err(e) = self.unbind_device(&device, false) {
+                println!("Failed to restore the host driver of {}: {}", device.bdf, e);
+                first_error.get_or_insert(e);
+                left_on_vfio.push(device);
+            }
+        }
+        (left_on_vfio, first_error)
+    }
+
+    /// Creates SR-IOV virtual functions on a GPU
+    ///
+    /// Any existing VFs are destroyed first, as the kernel requires.
+    ///
+    /// Args:
+    ///     gpu: The physical function to create VFs on
+    ///     num_vfs: Number of VFs to enable (at most sriov_totalvfs)
+    ///     dry_run: If true, only log actions without modifying the device
+    ///
+    /// Returns:
+    ///     The resulting VF devices (empty in dry-run mode)
+    pub fn create_vfs(&self, gpu: &GpuDevice, num_vfs: u16, dry_run: bool) -> io::Result<Vec<PciDevice>> {
+        println!("Creating {} virtual function(s) on {}...", num_vfs, gpu.bdf);
+
+        let sysfs_path = Path::new("/sys/bus/pci/devices").join(&gpu.bdf);
+        set_num_vfs(&sysfs_path, num_vfs, dry_run)?;
+        if dry_run {
+            return Ok(Vec::new());
+        }
+
+        // VFs are added asynchronously by the PF driver
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        let vfs = list_virtual_functions(&sysfs_path);
+        if vfs.len() != num_vfs as usize {
+            println!("Warning: Expected {} VFs on {}, found {}", num_vfs, gpu.bdf, vfs.len());
+        }
+        for vf in &vfs {
+            println!("  VF {} [{}] driver: {}", vf.bdf, vf.id_pair(), vf.driver.as_deref().unwrap_or("None"));
+        }
+        Ok(vfs)
+    }
+
+    /// Destroys all SR-IOV virtual functions of a GPU
+    ///
+    /// Args:
+    ///     gpu: The physical function whose VFs should be removed
+    ///     dry_run: If true, only log actions without modifying the device
+    ///
+    /// Returns:
+    ///     Result indicating success or failure
+    pub fn destroy_vfs(&self, gpu: &GpuDevice, dry_run: bool) -> io::Result<()> {
+        println!("Destroying virtual functions of {}...", gpu.bdf);
+
+        let sysfs_path = Path::new("/sys/bus/pci/devices").join(&gpu.bdf);
+        // VFs assigned to a running VM block removal until the VM releases them
+        for vf in list_virtual_functions(&sysfs_path) {
+            if vf.driver.as_deref() == Some("vfio-pci") {
+                println!("  Warning: VF {} is bound to vfio-pci; removal waits until no VM uses it.", vf.bdf);
+            }
+        }
+        set_num_vfs(&sysfs_path, 0, dry_run)
+    }
+
+    /// Binds a single SR-IOV virtual function to vfio-pci
+    ///
+    /// Args:
+    ///     vf: The virtual function to bind
+    ///     dry_run: If true, only log actions without modifying files
+    ///
+    /// Returns:
+    ///     Result indicating success or failure
+    pub fn bind_vf(&self, vf: &PciDevice, dry_run: bool) -> io::Result<()> {
+        let Some(pf_bdf) = physical_function(&vf.sysfs_path) else {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{} is not an SR-IOV virtual function", vf.bdf)));
+        };
+        // Only VFs of an active PF can be bound; a stale listing would bind nothing
+        let pf_path = Path::new("/sys/bus/pci/devices").join(&pf_bdf);
+        if !dry_run && !read_sriov_info(&pf_path).is_some_and(|info| info.vf_bdfs.contains(&vf.bdf)) {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("VF {} no longer exists on {}", vf.bdf, pf_bdf)));
+        }
+        println!("Binding VF {} of {} to vfio-pci...", vf.bdf, pf_bdf);
+        self.bind_device(vf, dry_run)
+    }
+
+    /// Sets up vendor-reset for an AMD GPU with the reset bug
+    ///
+    /// Loads the module at boot via modules-load.d, installs a udev rule that
+    /// selects the device-specific reset and applies it to the GPU right away.
+    ///
+    /// Args:
+    ///     gpu: The GPU to use vendor-reset for
+    ///     dry_run: If true, only log actions without modifying files
+    ///
+    /// Returns:
+    ///     The changes made, for the state tracker (empty in dry-run mode), or
+    ///     an error carrying the changes made before the failure
+    pub fn setup_vendor_reset(&self, gpu: &GpuDevice, dry_run: bool) -> Result<Vec<Change>, VendorResetError> {
+        let Some(family) = vendor_reset_family(&gpu.device_id) else {
+            return Err(VendorResetError::new(io::Error::new(io::ErrorKind::Unsupported, format!(
+                "vendor-reset has no reset for {} ({}:{})", gpu.model_name, gpu.vendor_id, gpu.device_id,
+            ))));
+        };
+        println!("Setting up vendor-reset for {} ({})...", gpu.bdf, family);
+
+        let status = VendorResetStatus::detect();
+        if !status.installed {
+            return Err(VendorResetError::new(io::Error::new(io::ErrorKind::NotFound,
+                "vendor-reset is not installed; install it with DKMS (https://github.com/gnif/vendor-reset)")));
+        }
+        println!("Found {}", status.summary());
+        status.check_signature(self.system_info.secure_boot_enabled)
+            .map_err(|e| VendorResetError::new(io::Error::new(io::ErrorKind::PermissionDenied, e)))?;
+
+        // Files written before a later step fails still have to be recorded
+        let mut changes = Vec::new();
+        match self.install_vendor_reset(gpu, &status, dry_run, &mut changes) {
+            Ok(()) => Ok(changes),
+            Err(error) => Err(VendorResetError { error, changes }),
+        }
+    }
+
+    /// Writes the vendor-reset configuration and applies it, collecting the
+    /// changes as they are made
+    fn install_vendor_reset(&self, gpu: &GpuDevice, status: &VendorResetStatus, dry_run: bool, changes: &mut Vec<Change>) -> io::Result<()> {
+        // --- Load the module at boot ---
+        let modules_load_path = Path::new(VENDOR_RESET_MODULES_LOAD_PATH);
+        let modules_load_content = format!("{}\n", VENDOR_RESET_MODULE);
+        if fs::read_to_string(modules_load_path).unwrap_or_default() == modules_load_content {
+            println!("{} is already up-to-date.", modules_load_path.display());
+        } else if dry_run {
+            println!("[DRY RUN] Would write {} to {}", VENDOR_RESET_MODULE, modules_load_path.display());
+        } else {
+            let backup_path = write_with_backup(modules_load_path, &modules_load_content)?;
+            println!("Successfully updated {}", modules_load_path.display());
+            changes.push(Change::ModuleLoaded {
+                name: VENDOR_RESET_MODULE.to_string(),
+                config_path: modules_load_path.to_path_buf(),
+                backup_path,
+            });
+        }
+
+        // Before reset_method existed, the kernel used vendor-reset's reset on its own
+        if !self.system_info.kernel_version.supports(KernelFeature::ResetMethodSysfs) {
+            println!("Kernel {} has no reset_method attribute; vendor-reset's reset is used by default once the module is loaded.",
+                     self.system_info.kernel_version.full_version);
+            if !status.loaded {
+                load_vendor_reset(dry_run)?;
+            }
+            return Ok(());
+        }
+
+        // --- Select the device-specific reset for this GPU model on every boot ---
+        let rule_path = Path::new(VENDOR_RESET_UDEV_RULE_PATH);
+        let current_rule = fs::read_to_string(rule_path).unwrap_or_default();
+        // Keep the IDs of other GPUs set up before
+        let mut device_ids: Vec<String> = current_rule.lines()
+            .filter_map(|line| line.split("ATTR{device}==\"0x").nth(1))
+            .filter_map(|rest| rest.split('"').next())
+            .map(str::to_string)
+            .collect();
+        if !device_ids.contains(&gpu.device_id.to_lowercase()) {
+            device_ids.push(gpu.device_id.to_lowercase());
+        }
+        let rule_content = vendor_reset_udev_rule(&device_ids);
+        if current_rule == rule_content {
+            println!("{} is already up-to-date.", rule_path.display());
+        } else if dry_run {
+            println!("[DRY RUN] Would write {}:\n{}", rule_path.display(), rule_content);
+        } else {
+            let backup_path = write_with_backup(rule_path, &rule_content)?;
+            println!("Successfully updated {}", rule_path.display());
+            changes.push(Change::UdevRuleInstalled { path: rule_path.to_path_buf(), backup_path });
+        }
+        reload_udev_rules(dry_run)?;
+
+        // --- Apply it now ---
+        if !status.loaded {
+            load_vendor_reset(dry_run)?;
+        }
+        let sysfs_path = Path::new("/sys/bus/pci/devices").join(&gpu.bdf);
+        match read_reset_methods(&sysfs_path) {
+            Some(methods) if methods.first() == Some(&ResetMethod::DeviceSpecific) => {
+                println!("{} already uses the device-specific reset.", gpu.bdf);
+            }
+            Some(_) => {
+                let original_methods = set_device_specific_reset(&sysfs_path, dry_run)?;
+                println!("Reset method of {} set to device_specific (was: {}).", gpu.bdf, original_methods);
+                if !dry_run {
+                    changes.push(Change::ResetMethodChanged { device_bdf: gpu.bdf.clone(), original_methods });
+                }
+            }
+            None => println!("Warning: {} has no reset_method attribute; the device-specific reset can't be selected.", gpu.bdf),
+        }
+
+        Ok(())
+    }
+
      /// Unbinds a specific PCI device from the vfio-pci driver
     ///
     /// Args:
@@ -330,6 +732,49 @@ impl VfioManager {
     }
 }
 
+/// Re-reads every function of a GPU's slot from sysfs so that bind/unbind
+/// work with the current driver state
+fn slot_devices(gpu: &GpuDevice) -> io::Result<Vec<PciDevice>> {
+    gpu.slot_bdfs().iter()
+        .map(|bdf| {
+            let sysfs_path = Path::new("/sys/bus/pci/devices").join(bdf);
+            read_pci_device(&sysfs_path, bdf).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, format!("PCI device {} not found in sysfs", bdf))
+            })
+        })
+        .collect()
+}
+
+/// Returns the VFIO modules to load for a kernel version
+///
+/// vfio_virqfd was merged into vfio in 6.2; listing it there makes
+/// systemd-modules-load fail.
+pub fn vfio_modules(kernel: &KernelVersion) -> Vec<&'static str> {
+    let mut modules = vec!["vfio", "vfio_iommu_type1", "vfio_pci"];
+    if kernel.supports(KernelFeature::VfioVirqfdModule) {
+        modules.push("vfio_virqfd");
+    }
+    modules
+}
+
+/// Writes a file, backing up the previous version if there was one
+///
+/// Returns the backup path, or None if the file didn't exist.
+fn write_with_backup(path: &Path, content: &str) -> io::Result<Option<PathBuf>> {
+    let backup_path = if path.exists() {
+        let backup_path = create_timestamped_backup(path)?;
+        println!("Created backup: {}", backup_path.display());
+        Some(backup_path)
+    } else {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        None
+    };
+    fs::write(path, content)?;
+    Ok(backup_path)
+}
+
 /// Helper to create a timestamped backup of a file
 fn create_timestamped_backup(file_path: &Path) -> io::Result<PathBuf> {
     if !file_path.exists() {
@@ -0,0 +1,171 @@
+// ACS analysis for Exliar VFIO Automation Framework
+//
+// This module walks from a device up through its switches and root port
+// using sysfs parent links, reads the Access Control Services state of
+// every bridge on the way, and works out where the device's IOMMU
+// isolation actually comes from.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::gpu::config_space::{read_capabilities, AcsCapability, PcieDeviceType};
+use crate::gpu::pci::PciAddress;
+
+/// ACS features the kernel requires on a bridge before it will split IOMMU
+/// groups below it (see REQ_ACS_FLAGS in drivers/pci/pci.c)
+pub const REQUIRED_ACS_FLAGS: &[(u16, &str)] = &[
+    (AcsCapability::SOURCE_VALIDATION, "SV"),
+    (AcsCapability::REQUEST_REDIRECT, "RR"),
+    (AcsCapability::COMPLETION_REDIRECT, "CR"),
+    (AcsCapability::UPSTREAM_FORWARDING, "UF"),
+];
+
+/// ACS state of one bridge on a device's upstream path
+#[derive(Debug, Clone)]
+pub struct BridgeAcs {
+    pub bdf: String,
+    pub device_type: Option<PcieDeviceType>, // None for conventional PCI bridges or if unreadable
+    pub acs: Option<AcsCapability>,
+    pub readable: bool, // Capability lists could be read (requires root)
+}
+
+impl BridgeAcs {
+    /// Returns true if the kernel checks this bridge's ACS when forming groups
+    ///
+    /// Upstream switch ports only forward to their own downstream ports,
+    /// so only root ports and downstream ports need to isolate.
+    pub fn needs_acs(&self) -> bool {
+        self.device_type != Some(PcieDeviceType::SwitchUpstream)
+    }
+
+    /// Returns the required ACS features that are unsupported or disabled
+    pub fn missing_flags(&self) -> Vec<&'static str> {
+        REQUIRED_ACS_FLAGS.iter()
+            .filter(|(flag, _)| !self.acs.as_ref().is_some_and(|acs| acs.is_enabled(*flag)))
+            .map(|(_, name)| *name)
+            .collect()
+    }
+
+    /// Returns true if this bridge isolates the devices below it in hardware
+    pub fn provides_isolation(&self) -> bool {
+        !self.needs_acs() || self.missing_flags().is_empty()
+    }
+
+    /// Returns a short name for the bridge's role
+    pub fn role(&self) -> &'static str {
+        match self.device_type {
+            Some(PcieDeviceType::RootPort) => "Root Port",
+            Some(PcieDeviceType::SwitchUpstream) => "Switch Upstream Port",
+            Some(PcieDeviceType::SwitchDownstream) => "Switch Downstream Port",
+            Some(PcieDeviceType::PcieToPciBridge) => "PCIe-to-PCI Bridge",
+            Some(_) => "PCIe Bridge",
+            None if self.readable => "PCI Bridge",
+            None => "Bridge",
+        }
+    }
+}
+
+/// Where a device's IOMMU isolation comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AcsIsolationSource {
+    /// Every bridge on the path enforces ACS
+    Hardware,
+    /// A bridge lacks ACS, but the kernel still isolates the device
+    /// (device-specific ACS quirk for the platform's root ports)
+    PlatformQuirk,
+    /// A bridge lacks ACS and the groups are only split by pcie_acs_override
+    OverrideOnly,
+    /// The device shares its IOMMU group with other endpoints
+    NotIsolated,
+    /// Config space unreadable or IOMMU inactive
+    Unknown,
+}
+
+impl fmt::Display for AcsIsolationSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AcsIsolationSource::Hardware => write!(f, "Hardware ACS"),
+            AcsIsolationSource::PlatformQuirk => write!(f, "Platform quirk"),
+            AcsIsolationSource::OverrideOnly => write!(f, "pcie_acs_override only"),
+            AcsIsolationSource::NotIsolated => write!(f, "Not isolated"),
+            AcsIsolationSource::Unknown => write!(f, "Unknown"),
+        }
+    }
+}
+
+/// Result of analysing a device's upstream PCIe path
+#[derive(Debug, Clone)]
+pub struct AcsAnalysis {
+    pub path: Vec<BridgeAcs>, // Nearest bridge first, root port last
+    pub source: AcsIsolationSource,
+    pub override_active: bool, // pcie_acs_override is on the running kernel's command line
+}
+
+impl AcsAnalysis {
+    /// Returns true if passthrough depends on (or would need) the ACS override patch
+    pub fn requires_acs_override(&self) -> bool {
+        matches!(self.source, AcsIsolationSource::OverrideOnly | AcsIsolationSource::NotIsolated)
+    }
+
+    /// Returns the bridges that don't isolate the devices below them
+    pub fn non_isolating_bridges(&self) -> impl Iterator<Item = &BridgeAcs> {
+        self.path.iter().filter(|bridge| !bridge.provides_isolation())
+    }
+}
+
+/// Lists the sysfs directories of every bridge above a device, nearest first
+///
+/// /sys/bus/pci/devices/<bdf> links into /sys/devices/pciDDDD:BB/..., where
+/// each parent directory named after a PCI address is an upstream bridge.
+pub fn upstream_bridges(sysfs_path: &Path) -> Vec<PathBuf> {
+    let Ok(real_path) = fs::canonicalize(sysfs_path) else { return Vec::new() };
+
+    real_path.ancestors()
+        .skip(1)
+        .take_while(|dir| {
+            dir.file_name()
+                .is_some_and(|name| PciAddress::parse(&name.to_string_lossy()).is_some())
+        })
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Analyses the ACS state of a device's upstream path
+///
+/// `group_isolated` is whether the device's IOMMU group is free of other
+/// endpoints (None if the IOMMU is inactive).
+pub fn analyze_upstream_path(sysfs_path: &Path, group_isolated: Option<bool>, override_active: bool) -> AcsAnalysis {
+    let path: Vec<BridgeAcs> = upstream_bridges(sysfs_path).iter()
+        .map(|bridge_path| {
+            let caps = read_capabilities(bridge_path);
+            BridgeAcs {
+                bdf: bridge_path.file_name().unwrap_or_default().to_string_lossy().to_string(),
+                device_type: caps.as_ref().and_then(|c| c.pcie.as_ref()).map(|p| p.device_type),
+                acs: caps.as_ref().and_then(|c| c.acs.clone()),
+                readable: caps.is_some(),
+            }
+        })
+        .collect();
+
+    let source = if group_isolated == Some(false) {
+        AcsIsolationSource::NotIsolated
+    } else if path.iter().any(|bridge| !bridge.readable) {
+        AcsIsolationSource::Unknown
+    } else if path.iter().all(|bridge| bridge.provides_isolation()) {
+        AcsIsolationSource::Hardware
+    } else if override_active {
+        AcsIsolationSource::OverrideOnly
+    } else if group_isolated == Some(true) {
+        AcsIsolationSource::PlatformQuirk
+    } else {
+        AcsIsolationSource::Unknown
+    };
+
+    AcsAnalysis { path, source, override_active }
+}
+
+/// Returns true if a kernel command line enables the ACS override patch
+pub fn acs_override_active(cmdline: &str) -> bool {
+    cmdline.split_whitespace().any(|param| param.starts_with("pcie_acs_override="))
+}
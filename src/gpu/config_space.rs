@@ -0,0 +1,516 @@
+// PCI configuration space access for Exliar VFIO Automation Framework
+//
+// This module reads a device's configuration space from sysfs and walks
+// both the standard and extended capability chains into typed structs.
+// Note that unprivileged users can only read the first 64 bytes; the
+// capability lists and the extended space need root.
+
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::gpu::rebar::{parse_rebar_capability, RebarInfo};
+
+/// Size of the conventional (non-extended) configuration space
+pub const STANDARD_CONFIG_SIZE: usize = 0x100;
+
+/// Offset of the first extended capability
+pub const EXTENDED_CAP_START: usize = 0x100;
+
+// Standard capability IDs
+pub const CAP_ID_PM: u8 = 0x01;
+pub const CAP_ID_MSI: u8 = 0x05;
+pub const CAP_ID_VENDOR: u8 = 0x09;
+pub const CAP_ID_PCIE: u8 = 0x10;
+pub const CAP_ID_MSIX: u8 = 0x11;
+pub const CAP_ID_AF: u8 = 0x13;
+
+// Extended capability IDs
+pub const EXT_CAP_ID_AER: u16 = 0x0001;
+pub const EXT_CAP_ID_ACS: u16 = 0x000d;
+pub const EXT_CAP_ID_SRIOV: u16 = 0x0010;
+pub const EXT_CAP_ID_REBAR: u16 = 0x0015;
+
+/// PCI Power Management capability
+#[derive(Debug, Clone)]
+pub struct PowerManagement {
+    pub version: u8,
+    pub d1_support: bool,
+    pub d2_support: bool,
+    pub pme_from_d3cold: bool,
+    pub no_soft_reset: bool, // If set, a D3hot->D0 transition does not reset the device
+    pub current_state: u8,   // 0 = D0 .. 3 = D3hot
+}
+
+/// Device/port type from the PCI Express capability
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PcieDeviceType {
+    Endpoint,
+    LegacyEndpoint,
+    RootPort,
+    SwitchUpstream,
+    SwitchDownstream,
+    PcieToPciBridge,
+    PciToPcieBridge,
+    RootComplexIntegratedEndpoint,
+    RootComplexEventCollector,
+    Unknown(u8),
+}
+
+impl PcieDeviceType {
+    fn from_raw(value: u8) -> Self {
+        match value {
+            0x0 => PcieDeviceType::Endpoint,
+            0x1 => PcieDeviceType::LegacyEndpoint,
+            0x4 => PcieDeviceType::RootPort,
+            0x5 => PcieDeviceType::SwitchUpstream,
+            0x6 => PcieDeviceType::SwitchDownstream,
+            0x7 => PcieDeviceType::PcieToPciBridge,
+            0x8 => PcieDeviceType::PciToPcieBridge,
+            0x9 => PcieDeviceType::RootComplexIntegratedEndpoint,
+            0xa => PcieDeviceType::RootComplexEventCollector,
+            other => PcieDeviceType::Unknown(other),
+        }
+    }
+}
+
+/// PCIe link capabilities and status
+#[derive(Debug, Clone)]
+pub struct PcieLink {
+    pub max_speed: u8,     // Link speed generation (1 = 2.5GT/s .. 6 = 64GT/s)
+    pub max_width: u8,     // Lanes (x1 .. x32)
+    pub current_speed: u8,
+    pub current_width: u8,
+}
+
+/// PCI Express capability
+#[derive(Debug, Clone)]
+pub struct PcieCapability {
+    pub version: u8,
+    pub device_type: PcieDeviceType,
+    pub slot_implemented: bool,
+    pub flr: bool, // Function Level Reset supported
+    pub link: Option<PcieLink>,
+}
+
+/// MSI capability
+#[derive(Debug, Clone)]
+pub struct MsiCapability {
+    pub enabled: bool,
+    pub is_64bit: bool,
+    pub max_vectors: u8,
+}
+
+/// MSI-X capability
+#[derive(Debug, Clone)]
+pub struct MsixCapability {
+    pub enabled: bool,
+    pub table_size: u16,
+}
+
+/// Access Control Services capability (raw capability and control registers)
+#[derive(Debug, Clone)]
+pub struct AcsCapability {
+    pub capability: u16,
+    pub control: u16,
+}
+
+impl AcsCapability {
+    pub const SOURCE_VALIDATION: u16 = 0x0001;
+    pub const TRANSLATION_BLOCKING: u16 = 0x0002;
+    pub const REQUEST_REDIRECT: u16 = 0x0004;
+    pub const COMPLETION_REDIRECT: u16 = 0x0008;
+    pub const UPSTREAM_FORWARDING: u16 = 0x0010;
+    pub const EGRESS_CONTROL: u16 = 0x0020;
+    pub const DIRECT_TRANSLATED_P2P: u16 = 0x0040;
+
+    /// Returns true if a feature is both supported and enabled
+    pub fn is_enabled(&self, flag: u16) -> bool {
+        self.capability & flag != 0 && self.control & flag != 0
+    }
+}
+
+/// Single Root I/O Virtualization capability
+#[derive(Debug, Clone)]
+pub struct SriovCapability {
+    pub total_vfs: u16,
+    pub initial_vfs: u16,
+    pub num_vfs: u16,
+    pub vf_offset: u16,
+    pub vf_stride: u16,
+    pub vf_device_id: u16,
+}
+
+/// Everything parsed from a device's capability lists
+#[derive(Debug, Clone, Default)]
+pub struct PciCapabilities {
+    pub standard_ids: Vec<u8>,  // IDs in chain order
+    pub extended_ids: Vec<u16>, // IDs in chain order
+    pub extended_readable: bool, // Extended config space was available
+    pub power_management: Option<PowerManagement>,
+    pub msi: Option<MsiCapability>,
+    pub msix: Option<MsixCapability>,
+    pub pcie: Option<PcieCapability>,
+    pub af_flr: bool, // FLR via the Advanced Features capability (conventional PCI)
+    pub aer: bool,
+    pub acs: Option<AcsCapability>,
+    pub sriov: Option<SriovCapability>,
+    pub rebar: Option<RebarInfo>,
+}
+
+impl PciCapabilities {
+    /// Returns true if the device supports Function Level Reset
+    pub fn supports_flr(&self) -> bool {
+        self.af_flr || self.pcie.as_ref().is_some_and(|p| p.flr)
+    }
+
+    /// Returns true if a D3hot->D0 transition resets the device (PM reset)
+    pub fn supports_pm_reset(&self) -> bool {
+        self.power_management.as_ref().is_some_and(|pm| !pm.no_soft_reset)
+    }
+
+    /// Returns short names of the notable capabilities present (e.g., ["PM", "PCIe", "FLR"])
+    pub fn names(&self) -> Vec<&'static str> {
+        let mut names = Vec::new();
+        if self.power_management.is_some() { names.push("PM"); }
+        if self.msi.is_some() { names.push("MSI"); }
+        if self.msix.is_some() { names.push("MSI-X"); }
+        if self.pcie.is_some() { names.push("PCIe"); }
+        if self.supports_flr() { names.push("FLR"); }
+        if self.aer { names.push("AER"); }
+        if self.acs.is_some() { names.push("ACS"); }
+        if self.sriov.is_some() { names.push("SR-IOV"); }
+        if self.rebar.is_some() { names.push("ReBAR"); }
+        names
+    }
+
+    /// Returns true if the device is a root complex integrated endpoint (iGPU-style)
+    pub fn is_integrated_endpoint(&self) -> bool {
+        self.pcie.as_ref().is_some_and(|p| p.device_type == PcieDeviceType::RootComplexIntegratedEndpoint)
+    }
+}
+
+/// Reads the raw configuration space of a device
+pub fn read_config_space(sysfs_path: &Path) -> io::Result<Vec<u8>> {
+    fs::read(sysfs_path.join("config"))
+}
+
+/// Reads and parses the capability lists of a device
+///
+/// Returns None if the capability lists aren't readable (non-root).
+pub fn read_capabilities(sysfs_path: &Path) -> Option<PciCapabilities> {
+    let config = read_config_space(sysfs_path).ok()?;
+    if config.len() < STANDARD_CONFIG_SIZE {
+        return None;
+    }
+    Some(parse_capabilities(&config))
+}
+
+/// Parses both capability chains from raw configuration space
+pub fn parse_capabilities(config: &[u8]) -> PciCapabilities {
+    let mut caps = PciCapabilities {
+        extended_readable: config.len() > EXTENDED_CAP_START,
+        ..Default::default()
+    };
+
+    for (id, offset) in standard_capabilities(config) {
+        caps.standard_ids.push(id);
+        match id {
+            CAP_ID_PM => caps.power_management = parse_pm(config, offset),
+            CAP_ID_MSI => caps.msi = parse_msi(config, offset),
+            CAP_ID_MSIX => caps.msix = parse_msix(config, offset),
+            CAP_ID_PCIE => caps.pcie = parse_pcie(config, offset),
+            CAP_ID_AF => caps.af_flr = read_u8(config, offset + 3).is_some_and(|af| af & 0x02 != 0),
+            _ => {}
+        }
+    }
+
+    for (id, offset) in extended_capabilities(config) {
+        caps.extended_ids.push(id);
+        match id {
+            EXT_CAP_ID_AER => caps.aer = true,
+            EXT_CAP_ID_ACS => {
+                caps.acs = read_u16(config, offset + 4).zip(read_u16(config, offset + 6))
+                    .map(|(capability, control)| AcsCapability { capability, control });
+            }
+            EXT_CAP_ID_SRIOV => caps.sriov = parse_sriov(config, offset),
+            EXT_CAP_ID_REBAR => caps.rebar = parse_rebar_capability(config),
+            _ => {}
+        }
+    }
+
+    caps
+}
+
+/// Lists (id, offset) of every standard capability
+pub fn standard_capabilities(config: &[u8]) -> Vec<(u8, usize)> {
+    let mut found = Vec::new();
+
+    // Status register bit 4 indicates a capability list is present
+    if read_u16(config, 0x06).is_none_or(|status| status & 0x10 == 0) {
+        return found;
+    }
+
+    // Header type 2 (CardBus) keeps the pointer elsewhere
+    let pointer_offset = if read_u8(config, 0x0e).is_some_and(|h| h & 0x7f == 2) { 0x14 } else { 0x34 };
+    let mut offset = read_u8(config, pointer_offset).map_or(0, |p| p as usize & 0xfc);
+
+    // At most 48 capabilities fit in the standard space; stop early on a revisited offset
+    while offset >= 0x40 && found.len() < 48 && !found.iter().any(|&(_, seen)| seen == offset) {
+        let (Some(id), Some(next)) = (read_u8(config, offset), read_u8(config, offset + 1)) else { break };
+        found.push((id, offset));
+        offset = next as usize & 0xfc;
+    }
+
+    found
+}
+
+/// Lists (id, offset) of every extended capability
+pub fn extended_capabilities(config: &[u8]) -> Vec<(u16, usize)> {
+    let mut found = Vec::new();
+    let mut offset = EXTENDED_CAP_START;
+
+    // Bound the walk in case of a corrupted (looping) chain
+    while found.len() < (4096 - EXTENDED_CAP_START) / 4 && !found.iter().any(|&(_, seen)| seen == offset) {
+        let Some(header) = read_u32(config, offset) else { break };
+        if header == 0 || header == 0xffff_ffff {
+            break;
+        }
+        found.push(((header & 0xffff) as u16, offset));
+        let next = (header >> 20) as usize & 0xffc;
+        if next < EXTENDED_CAP_START {
+            break;
+        }
+        offset = next;
+    }
+
+    found
+}
+
+/// Finds the offset of a standard capability by ID
+pub fn find_capability(config: &[u8], cap_id: u8) -> Option<usize> {
+    standard_capabilities(config).into_iter()
+        .find(|(id, _)| *id == cap_id)
+        .map(|(_, offset)| offset)
+}
+
+/// Finds the offset of an extended capability by ID
+pub fn find_extended_capability(config: &[u8], cap_id: u16) -> Option<usize> {
+    extended_capabilities(config).into_iter()
+        .find(|(id, _)| *id == cap_id)
+        .map(|(_, offset)| offset)
+}
+
+fn parse_pm(config: &[u8], offset: usize) -> Option<PowerManagement> {
+    let pmc = read_u16(config, offset + 2)?;
+    let pmcsr = read_u16(config, offset + 4)?;
+    Some(PowerManagement {
+        version: (pmc & 0x7) as u8,
+        d1_support: pmc & (1 << 9) != 0,
+        d2_support: pmc & (1 << 10) != 0,
+        pme_from_d3cold: pmc & (1 << 15) != 0,
+        no_soft_reset: pmcsr & (1 << 3) != 0,
+        current_state: (pmcsr & 0x3) as u8,
+    })
+}
+
+fn parse_msi(config: &[u8], offset: usize) -> Option<MsiCapability> {
+    let control = read_u16(config, offset + 2)?;
+    Some(MsiCapability {
+        enabled: control & 0x1 != 0,
+        is_64bit: control & (1 << 7) != 0,
+        max_vectors: 1 << ((control >> 1) & 0x7).min(5),
+    })
+}
+
+fn parse_msix(config: &[u8], offset: usize) -> Option<MsixCapability> {
+    let control = read_u16(config, offset + 2)?;
+    Some(MsixCapability {
+        enabled: control & (1 << 15) != 0,
+        table_size: (control & 0x7ff) + 1,
+    })
+}
+
+fn parse_pcie(config: &[u8], offset: usize) -> Option<PcieCapability> {
+    let flags = read_u16(config, offset + 2)?;
+    let device_caps = read_u32(config, offset + 4)?;
+    let device_type = PcieDeviceType::from_raw(((flags >> 4) & 0xf) as u8);
+
+    // Integrated endpoints and event collectors have no link registers
+    let has_link = !matches!(
+        device_type,
+        PcieDeviceType::RootComplexIntegratedEndpoint | PcieDeviceType::RootComplexEventCollector
+    );
+    let link = if has_link {
+        read_u32(config, offset + 0x0c).zip(read_u16(config, offset + 0x12))
+            .map(|(link_caps, link_status)| PcieLink {
+                max_speed: (link_caps & 0xf) as u8,
+                max_width: ((link_caps >> 4) & 0x3f) as u8,
+                current_speed: (link_status & 0xf) as u8,
+                current_width: ((link_status >> 4) & 0x3f) as u8,
+            })
+    } else {
+        None
+    };
+
+    Some(PcieCapability {
+        version: (flags & 0xf) as u8,
+        device_type,
+        slot_implemented: flags & (1 << 8) != 0,
+        flr: device_caps & (1 << 28) != 0,
+        link,
+    })
+}
+
+fn parse_sriov(config: &[u8], offset: usize) -> Option<SriovCapability> {
+    Some(SriovCapability {
+        initial_vfs: read_u16(config, offset + 0x0c)?,
+        total_vfs: read_u16(config, offset + 0x0e)?,
+        num_vfs: read_u16(config, offset + 0x10)?,
+        vf_offset: read_u16(config, offset + 0x14)?,
+        vf_stride: read_u16(config, offset + 0x16)?,
+        vf_device_id: read_u16(config, offset + 0x1a)?,
+    })
+}
+
+/// Reads a byte at the given offset
+pub fn read_u8(config: &[u8], offset: usize) -> Option<u8> {
+    config.get(offset).copied()
+}
+
+/// Reads a little-endian u16 at the given offset
+pub fn read_u16(config: &[u8], offset: usize) -> Option<u16> {
+    let bytes = config.get(offset..offset + 2)?;
+    Some(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+/// Reads a little-endian u32 at the given offset
+pub fn read_u32(config: &[u8], offset: usize) -> Option<u32> {
+    let bytes = config.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Builds a 4 KiB config space with the capability-list status bit set
+    fn config_with_caps() -> Vec<u8> {
+        let mut config = vec![0u8; 4096];
+        config[0x06] = 0x10;
+        config
+    }
+
+    /// Writes a standard capability header
+    fn put_cap(config: &mut [u8], offset: usize, id: u8, next: u8) {
+        config[offset] = id;
+        config[offset + 1] = next;
+    }
+
+    /// Writes an extended capability header (version 1)
+    fn put_ext_cap(config: &mut [u8], offset: usize, id: u16, next: usize) {
+        let header = id as u32 | 1 << 16 | (next as u32) << 20;
+        config[offset..offset + 4].copy_from_slice(&header.to_le_bytes());
+    }
+
+    fn put_u16(config: &mut [u8], offset: usize, value: u16) {
+        config[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+    }
+
+    fn put_u32(config: &mut [u8], offset: usize, value: u32) {
+        config[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+    }
+
+    #[test]
+    fn parses_both_chains() {
+        let mut config = config_with_caps();
+        config[0x34] = 0x50;
+        put_cap(&mut config, 0x50, CAP_ID_PM, 0x60);
+        put_u16(&mut config, 0x54, 1 << 3); // No_Soft_Reset
+        put_cap(&mut config, 0x60, CAP_ID_PCIE, 0);
+        put_u16(&mut config, 0x62, 0x0002); // Version 2 endpoint
+        put_u32(&mut config, 0x64, 1 << 28); // FLR
+        put_u32(&mut config, 0x6c, 0x0000_0104); // Gen4 x16
+        put_u16(&mut config, 0x72, 0x0083); // Gen3 x8
+
+        put_ext_cap(&mut config, 0x100, EXT_CAP_ID_AER, 0x150);
+        put_ext_cap(&mut config, 0x150, EXT_CAP_ID_ACS, 0x200);
+        put_u16(&mut config, 0x154, AcsCapability::SOURCE_VALIDATION | AcsCapability::REQUEST_REDIRECT);
+        put_u16(&mut config, 0x156, AcsCapability::SOURCE_VALIDATION);
+        put_ext_cap(&mut config, 0x200, EXT_CAP_ID_SRIOV, 0);
+        put_u16(&mut config, 0x20e, 16);
+        put_u16(&mut config, 0x21a, 0x1234);
+
+        let caps = parse_capabilities(&config);
+        assert_eq!(caps.standard_ids, vec![CAP_ID_PM, CAP_ID_PCIE]);
+        assert_eq!(caps.extended_ids, vec![EXT_CAP_ID_AER, EXT_CAP_ID_ACS, EXT_CAP_ID_SRIOV]);
+        assert!(caps.extended_readable);
+        assert!(caps.supports_flr());
+        assert!(!caps.supports_pm_reset());
+        let link = caps.pcie.as_ref().and_then(|p| p.link.clone()).unwrap();
+        assert_eq!((link.max_speed, link.max_width, link.current_speed, link.current_width), (4, 16, 3, 8));
+        let acs = caps.acs.unwrap();
+        assert!(acs.is_enabled(AcsCapability::SOURCE_VALIDATION));
+        assert!(!acs.is_enabled(AcsCapability::REQUEST_REDIRECT));
+        let sriov = caps.sriov.unwrap();
+        assert_eq!((sriov.total_vfs, sriov.vf_device_id), (16, 0x1234));
+    }
+
+    #[test]
+    fn looped_chains_stop_at_the_first_revisit() {
+        let mut config = config_with_caps();
+        config[0x34] = 0x40;
+        put_cap(&mut config, 0x40, CAP_ID_PM, 0x50);
+        put_cap(&mut config, 0x50, CAP_ID_MSI, 0x40);
+        put_ext_cap(&mut config, 0x100, EXT_CAP_ID_AER, 0x140);
+        put_ext_cap(&mut config, 0x140, EXT_CAP_ID_ACS, 0x100);
+
+        assert_eq!(standard_capabilities(&config), vec![(CAP_ID_PM, 0x40), (CAP_ID_MSI, 0x50)]);
+        assert_eq!(extended_capabilities(&config), vec![(EXT_CAP_ID_AER, 0x100), (EXT_CAP_ID_ACS, 0x140)]);
+    }
+
+    #[test]
+    fn truncated_chains_stop_at_the_end_of_the_buffer() {
+        let mut config = config_with_caps();
+        config[0x34] = 0x40;
+        put_cap(&mut config, 0x40, CAP_ID_PM, 0xfc);
+        put_ext_cap(&mut config, 0x100, EXT_CAP_ID_AER, 0x200);
+        config.truncate(0x180);
+
+        // 0xfc is in range but holds nothing, so the chain ends with an ID of 0
+        assert_eq!(standard_capabilities(&config), vec![(CAP_ID_PM, 0x40), (0, 0xfc)]);
+        assert_eq!(extended_capabilities(&config), vec![(EXT_CAP_ID_AER, 0x100)]);
+
+        // A pointer beyond the 64 bytes an unprivileged read returns yields nothing
+        config.truncate(0x40);
+        assert!(standard_capabilities(&config).is_empty());
+    }
+
+    #[test]
+    fn standard_only_read_has_no_extended_caps() {
+        // Without root, or on conventional PCI, sysfs returns only 256 bytes
+        let mut config = config_with_caps();
+        config[0x34] = 0x40;
+        put_cap(&mut config, 0x40, CAP_ID_PCIE, 0);
+        put_u32(&mut config, 0x44, 1 << 28);
+        put_ext_cap(&mut config, 0x100, EXT_CAP_ID_REBAR, 0);
+        config.truncate(STANDARD_CONFIG_SIZE);
+
+        let caps = parse_capabilities(&config);
+        assert!(!caps.extended_readable);
+        assert!(caps.extended_ids.is_empty());
+        assert!(caps.rebar.is_none());
+        assert!(caps.supports_flr());
+    }
+
+    #[test]
+    fn short_reads_are_not_parsed() {
+        let dir = std::env::temp_dir().join(format!("exliar-vfio-config-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("config"), vec![0u8; 64]).unwrap();
+        assert!(read_capabilities(&dir).is_none());
+        fs::write(dir.join("config"), vec![0u8; STANDARD_CONFIG_SIZE]).unwrap();
+        assert!(read_capabilities(&dir).is_some_and(|caps| !caps.extended_readable));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
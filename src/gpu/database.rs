@@ -0,0 +1,441 @@
+// GPU compatibility database for Exliar VFIO Automation Framework
+//
+// This module loads the per-vendor, per-family, per-model knowledge base
+// described in docs/exliar-vfio/gpu-compatibility.md. A default copy is
+// compiled into the binary; JSON files dropped into the override
+// directories are merged on top at runtime, so a misclassified card can be
+// corrected without rebuilding.
+
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::gpu::GpuVendor;
+
+/// Highest database schema version this build understands
+pub const SCHEMA_VERSION: u64 = 1;
+
+/// System-wide override directory (*.json, applied in file name order)
+pub const SYSTEM_OVERRIDE_DIR: &str = "/etc/exliar-vfio/gpu-database.d";
+
+/// Default database shipped with the binary
+const BUNDLED_DATABASE: &str = include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/gpu-database.json"));
+
+/// Properties that can be set per family, per architecture or per model
+/// (more specific levels override less specific ones)
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GpuTraits {
+    pub reset_bug: Option<bool>,
+    pub code_43_vulnerable: Option<bool>,
+    pub supports_gvt_g: Option<bool>,
+    pub supports_sriov: Option<bool>,
+    pub recommended_driver: Option<String>,
+    pub quirks: Option<Vec<String>>,             // An explicit [] clears the less specific lists
+    pub recommended_quirks: Option<Vec<String>>,
+}
+
+impl GpuTraits {
+    /// Overlays the values set in `other` on top of these
+    fn overlay(&mut self, other: &GpuTraits) {
+        if other.reset_bug.is_some() { self.reset_bug = other.reset_bug; }
+        if other.code_43_vulnerable.is_some() { self.code_43_vulnerable = other.code_43_vulnerable; }
+        if other.supports_gvt_g.is_some() { self.supports_gvt_g = other.supports_gvt_g; }
+        if other.supports_sriov.is_some() { self.supports_sriov = other.supports_sriov; }
+        if other.recommended_driver.is_some() { self.recommended_driver = other.recommended_driver.clone(); }
+        if other.quirks.is_some() { self.quirks = other.quirks.clone(); }
+        if other.recommended_quirks.is_some() { self.recommended_quirks = other.recommended_quirks.clone(); }
+    }
+}
+
+/// An architecture within a family, matched by device ID ranges
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ArchitectureEntry {
+    #[serde(default)]
+    pub device_ids: Vec<String>, // "67df" or "67c0-67ff"
+    #[serde(flatten)]
+    pub traits: GpuTraits,
+}
+
+/// A GPU family (e.g., "GCN", "Ampere")
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct FamilyEntry {
+    #[serde(default)]
+    pub device_ids: Vec<String>, // Ranges not tied to a specific architecture
+    #[serde(default)]
+    pub architectures: HashMap<String, ArchitectureEntry>,
+    #[serde(flatten)]
+    pub traits: GpuTraits,
+}
+
+/// Recommended VM configuration for a model
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VmConfigEntry {
+    #[serde(default)]
+    pub xml_snippets: BTreeMap<String, String>, // Snippet name -> libvirt XML
+}
+
+/// A specific model, keyed by "vendor:device"
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ModelEntry {
+    pub name: Option<String>,
+    pub family: Option<String>,
+    pub architecture: Option<String>,
+    #[serde(default)]
+    pub specific_quirks: Vec<String>,
+    pub recommended_vm_config: Option<VmConfigEntry>,
+    #[serde(flatten)]
+    pub traits: GpuTraits,
+}
+
+/// All families and models of a vendor
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct VendorEntry {
+    #[serde(default)]
+    pub families: HashMap<String, FamilyEntry>,
+    #[serde(default)]
+    pub models: HashMap<String, ModelEntry>,
+}
+
+/// The merged GPU compatibility database
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GpuDatabase {
+    #[serde(default = "default_version")]
+    pub version: u64,
+    #[serde(default)]
+    pub vendors: HashMap<String, VendorEntry>, // Keyed by vendor name ("AMD", "NVIDIA", "Intel")
+    #[serde(skip)]
+    pub sources: Vec<String>, // "bundled" followed by every override file applied
+}
+
+fn default_version() -> u64 {
+    1
+}
+
+/// Everything the database knows about one device
+#[derive(Debug, Clone)]
+pub struct GpuProfile {
+    pub key: String,                  // "vendor:device"
+    pub name: Option<String>,         // Model name, if the model is listed
+    pub family: Option<String>,
+    pub architecture: Option<String>,
+    pub traits: GpuTraits,            // Family, architecture and model traits combined
+    pub specific_quirks: Vec<String>, // Free-form notes for this model
+    pub xml_snippets: BTreeMap<String, String>,
+    pub model_listed: bool,           // Matched an explicit model entry, not just an ID range
+}
+
+impl GpuProfile {
+    /// Returns true if the family, architecture or model lists the named quirk
+    pub fn has_quirk(&self, name: &str) -> bool {
+        self.traits.quirks.iter().chain(self.traits.recommended_quirks.iter()).flatten().any(|q| q == name)
+    }
+
+    /// Returns "Family (Architecture)" or whichever part is known
+    pub fn classification(&self) -> String {
+        match (&self.family, &self.architecture) {
+            (Some(family), Some(arch)) if family != arch => format!("{} ({})", family, arch),
+            (Some(family), _) => family.clone(),
+            (None, Some(arch)) => arch.clone(),
+            (None, None) => "Unknown".to_string(),
+        }
+    }
+}
+
+impl GpuDatabase {
+    /// Returns the database loaded once per process
+    pub fn shared() -> &'static GpuDatabase {
+        static DATABASE: OnceLock<GpuDatabase> = OnceLock::new();
+        DATABASE.get_or_init(Self::load)
+    }
+
+    /// Loads the bundled database and merges every override file on top
+    pub fn load() -> Self {
+        let mut merged: Value = match serde_json::from_str(BUNDLED_DATABASE) {
+            Ok(value) => value,
+            Err(e) => {
+                println!("Warning: Bundled GPU database is invalid: {}", e);
+                Value::Object(Default::default())
+            }
+        };
+        let mut sources = vec!["bundled".to_string()];
+
+        for path in override_files() {
+            match apply_override(&merged, &path) {
+                Ok(candidate) => {
+                    merged = candidate;
+                    sources.push(path.display().to_string());
+                }
+                Err(e) => println!("Warning: Ignoring GPU database override {}: {}", path.display(), e),
+            }
+        }
+
+        let mut database: GpuDatabase = serde_json::from_value(merged).unwrap_or_default();
+        database.sources = sources;
+        database
+    }
+
+    /// Parses a single database document
+    pub fn parse(json: &str) -> Result<Self, String> {
+        let database: GpuDatabase = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        if database.version > SCHEMA_VERSION {
+            return Err(format!("schema version {} is newer than supported version {}", database.version, SCHEMA_VERSION));
+        }
+        Ok(database)
+    }
+
+    /// Resolves a device to its family, architecture and known traits
+    ///
+    /// An explicit model entry wins; otherwise the narrowest matching device
+    /// ID range decides the family and architecture.
+    pub fn resolve(&self, vendor: &GpuVendor, vendor_id: &str, device_id: &str) -> Option<GpuProfile> {
+        let vendor_entry = self.vendors.get(&vendor.to_string())?;
+        let key = format!("{}:{}", vendor_id, device_id).to_lowercase();
+        let model = vendor_entry.models.get(&key);
+        let id = u16::from_str_radix(device_id, 16).ok();
+
+        let by_range = id.and_then(|id| match_device_range(vendor_entry, id));
+        let (family, architecture) = match model.and_then(|m| m.family.clone()) {
+            Some(family) => {
+                // Use the ID range for the architecture only if it agrees on the family
+                let range_arch = by_range.filter(|(f, _)| *f == family).and_then(|(_, a)| a);
+                (Some(family), model.and_then(|m| m.architecture.clone()).or(range_arch))
+            }
+            None => match by_range {
+                Some((family, arch)) => (Some(family), model.and_then(|m| m.architecture.clone()).or(arch)),
+                None => (None, model.and_then(|m| m.architecture.clone())),
+            },
+        };
+
+        if model.is_none() && family.is_none() {
+            return None;
+        }
+
+        let family_entry = family.as_ref().and_then(|f| vendor_entry.families.get(f));
+        let mut traits = GpuTraits::default();
+        if let Some(entry) = family_entry {
+            traits.overlay(&entry.traits);
+        }
+        if let Some(arch) = architecture.as_ref().and_then(|a| family_entry?.architectures.get(a)) {
+            traits.overlay(&arch.traits);
+        }
+        if let Some(model) = model {
+            traits.overlay(&model.traits);
+        }
+
+        Some(GpuProfile {
+            key,
+            name: model.and_then(|m| m.name.clone()),
+            family,
+            architecture,
+            traits,
+            specific_quirks: model.map(|m| m.specific_quirks.clone()).unwrap_or_default(),
+            xml_snippets: model
+                .and_then(|m| m.recommended_vm_config.as_ref())
+                .map(|config| config.xml_snippets.clone())
+                .unwrap_or_default(),
+            model_listed: model.is_some(),
+        })
+    }
+}
+
+/// Directories searched for override files, lowest priority first
+pub fn override_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(SYSTEM_OVERRIDE_DIR)];
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    if let Some(config_home) = config_home {
+        dirs.push(config_home.join("exliar-vfio/gpu-database.d"));
+    }
+    dirs
+}
+
+/// Lists override files in application order
+fn override_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for dir in override_dirs() {
+        let Ok(entries) = fs::read_dir(&dir) else { continue };
+        let mut dir_files: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        dir_files.sort();
+        files.extend(dir_files);
+    }
+    files
+}
+
+/// Merges one override file into the database, validating the result
+fn apply_override(base: &Value, path: &Path) -> Result<Value, String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    merge_override(base, &content)
+}
+
+/// Merges the contents of an override file into the database
+fn merge_override(base: &Value, content: &str) -> Result<Value, String> {
+    let overlay: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+
+    let version = overlay.get("version").and_then(Value::as_u64).unwrap_or(1);
+    if version > SCHEMA_VERSION {
+        return Err(format!("schema version {} is newer than supported version {}", version, SCHEMA_VERSION));
+    }
+
+    let mut merged = base.clone();
+    merge_json(&mut merged, overlay);
+    serde_json::from_value::<GpuDatabase>(merged.clone()).map_err(|e| e.to_string())?;
+    Ok(merged)
+}
+
+/// Deep-merges JSON objects; any other value in `overlay` replaces the base
+fn merge_json(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (key, value) in overlay_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => { base_map.insert(key, value); }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Finds the (family, architecture) whose device ID range is the narrowest match
+fn match_device_range(vendor: &VendorEntry, id: u16) -> Option<(String, Option<String>)> {
+    let mut best: Option<(u32, String, Option<String>)> = None;
+    let mut consider = |ranges: &[String], family: &str, arch: Option<&str>| {
+        for (start, end) in ranges.iter().filter_map(|r| parse_id_range(r)) {
+            if id < start || id > end {
+                continue;
+            }
+            let width = (end - start) as u32;
+            let candidate = (width, family.to_string(), arch.map(str::to_string));
+            // Narrowest range wins; names break ties so the result is stable
+            if best.as_ref().is_none_or(|current| candidate < *current) {
+                best = Some(candidate);
+            }
+        }
+    };
+
+    for (family_name, family) in &vendor.families {
+        consider(&family.device_ids, family_name, None);
+        for (arch_name, arch) in &family.architectures {
+            consider(&arch.device_ids, family_name, Some(arch_name));
+        }
+    }
+
+    best.map(|(_, family, arch)| (family, arch))
+}
+
+/// Parses "67df" or "67c0-67ff" into an inclusive range
+fn parse_id_range(spec: &str) -> Option<(u16, u16)> {
+    let (start, end) = spec.split_once('-').unwrap_or((spec, spec));
+    let start = u16::from_str_radix(start.trim(), 16).ok()?;
+    let end = u16::from_str_radix(end.trim(), 16).ok()?;
+    (start <= end).then_some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = r#"{
+      "version": 1,
+      "vendors": {
+        "AMD": {
+          "families": {
+            "RDNA": {
+              "reset_bug": false,
+              "quirks": ["rom_loading_required"],
+              "device_ids": ["7300-75ff"],
+              "architectures": {
+                "RDNA1": { "device_ids": ["7310-731f"], "reset_bug": true, "quirks": ["vendor_reset"] },
+                "RDNA2": { "device_ids": ["73a0-73ff"] }
+              }
+            }
+          },
+          "models": {
+            "1002:731f": { "name": "Radeon RX 5700 XT", "family": "RDNA", "architecture": "RDNA1" },
+            "1002:7312": { "name": "Radeon Pro W5700", "reset_bug": false, "quirks": [] }
+          }
+        }
+      }
+    }"#;
+
+    fn resolve(database: &GpuDatabase, device_id: &str) -> GpuProfile {
+        database.resolve(&GpuVendor::AMD, "1002", device_id).unwrap()
+    }
+
+    #[test]
+    fn architecture_beats_family_and_model_beats_architecture() {
+        let database = GpuDatabase::parse(BASE).unwrap();
+
+        // Unlisted RDNA1 card: the architecture overrides the family's reset_bug and quirks
+        let rdna1 = resolve(&database, "7318");
+        assert_eq!(rdna1.architecture.as_deref(), Some("RDNA1"));
+        assert_eq!(rdna1.traits.reset_bug, Some(true));
+        assert!(rdna1.has_quirk("vendor_reset"));
+        assert!(!rdna1.has_quirk("rom_loading_required"));
+        assert!(!rdna1.model_listed);
+
+        // Listed model without its own traits inherits from its architecture
+        let listed = resolve(&database, "731f");
+        assert_eq!(listed.name.as_deref(), Some("Radeon RX 5700 XT"));
+        assert_eq!(listed.traits.reset_bug, Some(true));
+
+        // A model entry overrides the architecture, and [] clears its quirks
+        let model = resolve(&database, "7312");
+        assert_eq!(model.architecture.as_deref(), Some("RDNA1"));
+        assert_eq!(model.traits.reset_bug, Some(false));
+        assert!(!model.has_quirk("vendor_reset"));
+
+        // RDNA2 sets nothing, so the family decides
+        let rdna2 = resolve(&database, "73bf");
+        assert_eq!(rdna2.traits.reset_bug, Some(false));
+        assert!(rdna2.has_quirk("rom_loading_required"));
+    }
+
+    #[test]
+    fn narrowest_device_range_wins() {
+        let database = GpuDatabase::parse(BASE).unwrap();
+        assert_eq!(resolve(&database, "7315").architecture.as_deref(), Some("RDNA1"));
+        assert_eq!(resolve(&database, "73a5").architecture.as_deref(), Some("RDNA2"));
+        // Only the family range matches
+        let family_only = resolve(&database, "7500");
+        assert_eq!(family_only.family.as_deref(), Some("RDNA"));
+        assert_eq!(family_only.architecture, None);
+        assert!(database.resolve(&GpuVendor::AMD, "1002", "6800").is_none());
+    }
+
+    #[test]
+    fn override_replaces_bundled_traits() {
+        let base: Value = serde_json::from_str(BASE).unwrap();
+        let merged = merge_override(&base, r#"{
+          "vendors": { "AMD": { "families": { "RDNA": { "architectures": {
+            "RDNA1": { "reset_bug": false, "quirks": [] }
+          } } } } }
+        }"#).unwrap();
+        let database: GpuDatabase = serde_json::from_value(merged).unwrap();
+
+        let rdna1 = resolve(&database, "7318");
+        assert_eq!(rdna1.traits.reset_bug, Some(false));
+        assert!(!rdna1.has_quirk("vendor_reset"));
+        // Untouched parts of the bundled entry survive the merge
+        assert_eq!(rdna1.architecture.as_deref(), Some("RDNA1"));
+        assert!(resolve(&database, "73bf").has_quirk("rom_loading_required"));
+    }
+
+    #[test]
+    fn override_with_newer_schema_is_rejected() {
+        let base: Value = serde_json::from_str(BASE).unwrap();
+        assert!(merge_override(&base, r#"{ "version": 2 }"#).is_err());
+        assert!(merge_override(&base, r#"{ "vendors": { "AMD": { "families": [] } } }"#).is_err());
+    }
+}
@@ -4,62 +4,125 @@
 // PCI bus scanning and device identification
 
 use std::fs;
-use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::path::Path;
 
 use crate::gpu::{GpuDevice, GpuVendor, GpuDriverCapabilities};
+use crate::gpu::pci::{enumerate_pci_devices, PciAddress, SYSFS_ROOT};
+use crate::gpu::pci_ids::PciIds;
+use crate::gpu::iommu::{assign_iommu_groups, group_peers, read_iommu_groups, IommuPeerKind};
+use crate::gpu::link::{read_link_status, upstream_links};
+use crate::gpu::mdev::{list_mdev_types, list_mdevs};
+use crate::gpu::numa::read_numa_locality;
+use crate::gpu::acs::{acs_override_active, analyze_upstream_path};
+use crate::gpu::display::{read_boot_vga, read_drm_cards};
+use crate::gpu::hybrid::assign_hybrid_info;
+use crate::gpu::config_space::{read_capabilities, PciCapabilities};
+use crate::gpu::database::{GpuDatabase, GpuProfile};
+use crate::gpu::reset::{read_reset_methods, ResetMethod};
+use crate::gpu::sriov::{is_virtual_function, read_sriov_info};
+use crate::gpu::rebar::{bar_config_breaks_passthrough, detect_resize_knobs, infer_above_4g_decoding};
 
-/// Describes a detected PCI device
-#[derive(Debug, Clone)]
-pub struct PciDevice {
-    pub bdf: String,               // Bus:Device.Function address (e.g., "01:00.0")
-    pub vendor_id: String,         // Vendor ID (e.g., "10de" for NVIDIA)
-    pub device_id: String,         // Device ID
-    pub class: String,             // Device class (e.g., "VGA compatible controller")
-    pub vendor_name: String,       // Vendor name (e.g., "NVIDIA Corporation")
-    pub device_name: String,       // Device name (e.g., "GeForce RTX 3080")
-    pub driver: Option<String>,    // Current driver
-    pub sysfs_path: PathBuf,       // Path to device in sysfs
-}
+// PciDevice lives in the pci module; re-exported here for existing users
+pub use crate::gpu::pci::PciDevice;
 
 /// Detects all GPU devices in the system
 pub fn detect_gpus() -> Vec<GpuDevice> {
+    detect_gpus_from(Path::new(SYSFS_ROOT), Path::new("/proc"))
+}
+
+/// Detects all GPU devices below the given sysfs root, reading the kernel
+/// command line from the given procfs root
+pub fn detect_gpus_from(sysfs_root: &Path, procfs_root: &Path) -> Vec<GpuDevice> {
     // First, get all PCI devices
-    let pci_devices = get_pci_devices();
+    let pci_devices = get_pci_devices(sysfs_root);
     
-    // Filter for GPU devices
+    // Group splitting may be forced by the ACS override patch
+    let acs_override = fs::read_to_string(procfs_root.join("cmdline"))
+        .map(|cmdline| acs_override_active(&cmdline))
+        .unwrap_or(false);
+    
+    // Filter for GPU devices; SR-IOV VFs are listed under their physical function
     let gpu_pci_devices: Vec<_> = pci_devices.iter()
-        .filter(|dev| is_gpu_device(dev))
+        .filter(|dev| is_gpu_device(dev) && !is_virtual_function(&dev.sysfs_path))
         .collect();
     
     // Convert to GpuDevice objects with additional information
-    gpu_pci_devices.iter()
-        .map(|dev| pci_to_gpu_device(dev))
-        .collect()
+    let mut gpus: Vec<GpuDevice> = gpu_pci_devices.iter()
+        .map(|dev| pci_to_gpu_device(sysfs_root, dev, &pci_devices, acs_override))
+        .collect();
+    
+    // Laptop dGPUs are classified against the integrated GPU
+    assign_hybrid_info(sysfs_root, &mut gpus, &pci_devices);
+    gpus
 }
 
 /// Check if a PCI device is a GPU
 fn is_gpu_device(device: &PciDevice) -> bool {
-    // VGA compatible controller, Display controller, or 3D controller
-    device.class.contains("VGA") || 
-    device.class.contains("display controller") ||
-    device.class.contains("3D controller")
+    // Base class 0x03 covers VGA compatible, 3D and other display controllers
+    device.base_class() == 0x03
 }
 
 /// Converts a PCI device to a GPU device with additional information
-fn pci_to_gpu_device(device: &PciDevice) -> GpuDevice {
+fn pci_to_gpu_device(sysfs_root: &Path, device: &PciDevice, all_devices: &[PciDevice], acs_override: bool) -> GpuDevice {
     // Determine vendor
     let vendor = detect_gpu_vendor(device);
     
-    // Set GPU capabilities based on vendor and device ID
-    let capabilities = detect_gpu_capabilities(device, &vendor);
+    // Walk the config space capability lists (needs root)
+    let mut pci_capabilities = read_capabilities(&device.sysfs_path);
+    if let Some(rebar) = pci_capabilities.as_mut().and_then(|caps| caps.rebar.as_mut()) {
+        detect_resize_knobs(rebar, &device.sysfs_path);
+    }
+    
+    // Look the device up in the compatibility database
+    let profile = GpuDatabase::shared().resolve(&vendor, &device.vendor_id, &device.device_id);
+    
+    // Set GPU capabilities from the database, the parsed capabilities and vendor
+    let mut capabilities = detect_gpu_capabilities(device, &vendor, pci_capabilities.as_ref(), profile.as_ref());
+    
+    // Resizable BAR state and above-4G decoding
+    let rebar = pci_capabilities.as_ref().and_then(|caps| caps.rebar.clone());
+    let above_4g_decoding = infer_above_4g_decoding(&device.bars);
+    capabilities.bar_config_breaks_passthrough = bar_config_breaks_passthrough(rebar.as_ref(), &device.bars);
     
     // Determine if integrated
-    let is_integrated = is_integrated_gpu(device, &vendor);
+    let is_integrated = is_integrated_gpu(device, &vendor, pci_capabilities.as_ref());
     
     // Try to determine VRAM size
     let vram_size = detect_vram_size(device);
     
+    // Gather the GPU functions of the same slot (HDMI audio, USB-C, UCSI)
+    let (companion_functions, shared_slot_functions) = find_companion_functions(device, all_devices, is_integrated);
+    
+    // Find everything that shares the GPU's IOMMU group
+    let iommu_group_peers = group_peers(device, all_devices, &companion_functions);
+    
+    // Work out where the group isolation comes from (hardware ACS, quirk or override)
+    let group_isolated = device.iommu_group
+        .map(|_| !iommu_group_peers.iter().any(|peer| peer.kind == IommuPeerKind::Endpoint));
+    let acs = analyze_upstream_path(&device.sysfs_path, group_isolated, acs_override);
+    capabilities.requires_acs_override = acs.requires_acs_override();
+    
+    // Negotiated link of the GPU and every bridge above it
+    let pcie_link = read_link_status(&device.sysfs_path);
+    let upstream_links = upstream_links(&device.sysfs_path, &acs.path);
+    
+    // NUMA node the GPU hangs off and the CPUs local to it
+    let numa = read_numa_locality(sysfs_root, &device.sysfs_path);
+    
+    // Boot console device and the displays it drives
+    let boot_vga = read_boot_vga(&device.sysfs_path);
+    let drm_cards = read_drm_cards(sysfs_root, &device.sysfs_path);
+
+    // Virtual functions, taken from the enumerated devices so they carry pci.ids names
+    let sriov = read_sriov_info(&device.sysfs_path);
+    let virtual_functions = sriov.as_ref()
+        .map(|info| all_devices.iter().filter(|dev| info.vf_bdfs.contains(&dev.bdf)).cloned().collect())
+        .unwrap_or_default();
+
+    // Mediated device types and instances (Intel GVT-g)
+    let mdev_types = list_mdev_types(&device.sysfs_path);
+    let mdevs = list_mdevs(&device.sysfs_path, &device.bdf);
+    
     GpuDevice {
         bdf: device.bdf.clone(),
         vendor_id: device.vendor_id.clone(),
@@ -70,6 +133,58 @@ fn pci_to_gpu_device(device: &PciDevice) -> GpuDevice {
         vram_size,
         driver: device.driver.clone(),
         capabilities,
+        profile,
+        iommu_group: device.iommu_group,
+        iommu_group_peers,
+        companion_functions,
+        shared_slot_functions,
+        bars: device.bars.clone(),
+        rebar,
+        above_4g_decoding,
+        pci_capabilities,
+        acs,
+        pcie_link,
+        upstream_links,
+        numa,
+        boot_vga,
+        drm_cards,
+        sriov,
+        virtual_functions,
+        mdev_types,
+        mdevs,
+        hybrid: None,
+    }
+}
+
+/// Finds the sibling functions sharing a device's bus:device slot
+///
+/// Returns the GPU's own functions, passed through with it, and the other
+/// functions of the slot, which stay with the host (an APU's xHCI, PSP or
+/// ACP audio sit next to its iGPU).
+fn find_companion_functions(device: &PciDevice, all_devices: &[PciDevice], is_integrated: bool) -> (Vec<PciDevice>, Vec<PciDevice>) {
+    let Some(address) = PciAddress::parse(&device.bdf) else { return (Vec::new(), Vec::new()) };
+
+    all_devices.iter()
+        .filter(|other| other.bdf != device.bdf)
+        .filter(|other| PciAddress::parse(&other.bdf).is_some_and(|a| a.same_slot(&address)))
+        // With ARI, VFs can share the PF's slot number but are separate devices
+        .filter(|other| !is_virtual_function(&other.sysfs_path))
+        .cloned()
+        .partition(|other| is_gpu_companion(device, other, is_integrated))
+}
+
+/// Checks whether a function of a GPU's slot belongs to the GPU itself
+///
+/// HDMI/DP audio always does; USB-C and UCSI controllers (serial bus) only
+/// on a discrete card. Functions of another vendor never do.
+fn is_gpu_companion(gpu: &PciDevice, function: &PciDevice, is_integrated: bool) -> bool {
+    if function.vendor_id != gpu.vendor_id {
+        return false;
+    }
+    match (function.base_class(), function.subclass()) {
+        (0x04, 0x03) => true,                          // HD audio
+        (0x0c, 0x03) | (0x0c, 0x80) => !is_integrated, // USB-C, UCSI
+        _ => false,
     }
 }
 
@@ -96,56 +211,100 @@ fn detect_gpu_vendor(device: &PciDevice) -> GpuVendor {
     }
 }
 
-/// Detects GPU capabilities based on vendor and device ID
-fn detect_gpu_capabilities(device: &PciDevice, vendor: &GpuVendor) -> GpuDriverCapabilities {
+/// Detects GPU capabilities from the compatibility database, the device's
+/// PCI capabilities and vendor
+///
+/// Database entries take precedence so that known misclassifications can be
+/// corrected; the hardware capabilities fill in for unlisted devices.
+fn detect_gpu_capabilities(
+    device: &PciDevice,
+    vendor: &GpuVendor,
+    pci_caps: Option<&PciCapabilities>,
+    profile: Option<&GpuProfile>,
+) -> GpuDriverCapabilities {
     let mut capabilities = GpuDriverCapabilities::default();
+    let traits = profile.map(|p| &p.traits);
+    
+    // The kernel's reset_method list is authoritative; otherwise a device can be
+    // reset on its own if it has FLR or a working PM (D3hot) reset
+    let reset_methods = read_reset_methods(&device.sysfs_path);
+    let hardware_reset = match &reset_methods {
+        Some(methods) => Some(methods.iter().any(|m| m.is_function_level())),
+        None => pci_caps.map(|caps| caps.supports_flr() || caps.supports_pm_reset()),
+    };
+    capabilities.reset_methods = reset_methods.unwrap_or_default();
     
     match vendor {
         GpuVendor::AMD => {
-            // AMD reset bug affects many older cards
-            // This is a simplified check - we would want more detailed per-device checks
-            let device_id = u32::from_str_radix(&device.device_id, 16).unwrap_or(0);
-            if device_id < 0x7000 {  // Rough estimation for pre-RDNA cards
-                capabilities.has_reset_bug = true;
-            }
+            // Missing FLR says nothing here: hardly any AMD GPU has it, the
+            // ones without the reset bug included. Unlisted cards stay unknown
+            // until a reset test is run.
+            capabilities.has_reset_bug = traits.and_then(|t| t.reset_bug);
             
-            capabilities.supports_reset = !capabilities.has_reset_bug;
+            // A device-specific method (vendor-reset) works around the bug
+            capabilities.supports_reset = capabilities.has_reset_bug != Some(true)
+                || capabilities.reset_methods.contains(&ResetMethod::DeviceSpecific);
             capabilities.supports_vbios_loading = true;
         },
         GpuVendor::NVIDIA => {
             // NVIDIA GPUs need code 43 workaround for Windows guests
-            capabilities.needs_code_43_workaround = true;
-            capabilities.supports_reset = true;
+            capabilities.needs_code_43_workaround = traits.and_then(|t| t.code_43_vulnerable).unwrap_or(true);
+            capabilities.supports_reset = hardware_reset.unwrap_or(true);
             capabilities.supports_vbios_loading = true;
         },
         GpuVendor::Intel => {
-            // Intel integrated GPUs often support GVT-g
-            if device.device_name.to_lowercase().contains("hd graphics") ||
-               device.device_name.to_lowercase().contains("uhd graphics") {
-                capabilities.supports_gvt = true;
-            }
-            // Arc GPUs are discrete
-            if device.device_name.to_lowercase().contains("arc") {
-                capabilities.supports_reset = true;
+            if is_integrated_gpu(device, vendor, pci_caps) {
+                // GVT-g mediated devices are offered by the i915 driver (with kvmgt)
+                // on supported integrated GPUs
+                capabilities.supports_gvt = device.sysfs_path.join("mdev_supported_types").exists()
+                    || traits.and_then(|t| t.supports_gvt_g).unwrap_or(false);
+            } else {
+                // Discrete (Arc) GPUs
+                capabilities.supports_reset = hardware_reset.unwrap_or(true);
                 capabilities.supports_vbios_loading = true;
             }
         },
         GpuVendor::Other(_) => {
-            // Unknown vendor - assume minimal capabilities
-            capabilities.supports_reset = false;
+            // Unknown vendor - trust the hardware, otherwise assume minimal capabilities
+            capabilities.supports_reset = hardware_reset.unwrap_or(false);
             capabilities.supports_vbios_loading = false;
         }
     }
     
+    // Family- and model-level quirks
+    if let Some(profile) = profile {
+        if profile.has_quirk("rom_loading_required") {
+            capabilities.supports_vbios_loading = true;
+        }
+        if !matches!(vendor, GpuVendor::AMD) && profile.traits.reset_bug == Some(true) {
+            capabilities.has_reset_bug = Some(true);
+            capabilities.supports_reset = false;
+        }
+    }
+    
+    // The reset bug is an AMD problem; other vendors are unaffected unless listed
+    if !matches!(vendor, GpuVendor::AMD) && capabilities.has_reset_bug.is_none() {
+        capabilities.has_reset_bug = Some(false);
+    }
+    
     capabilities
 }
 
 /// Determines if a GPU is integrated with the CPU
-fn is_integrated_gpu(device: &PciDevice, vendor: &GpuVendor) -> bool {
+fn is_integrated_gpu(device: &PciDevice, vendor: &GpuVendor, pci_caps: Option<&PciCapabilities>) -> bool {
+    // Root complex integrated endpoints sit directly in the CPU/chipset
+    if pci_caps.is_some_and(|caps| caps.is_integrated_endpoint()) {
+        return true;
+    }
+    
     match vendor {
         GpuVendor::Intel => {
-            // Most Intel GPUs are integrated except for Arc series
-            !device.device_name.to_lowercase().contains("arc")
+            match pci_caps {
+                // Intel iGPUs are integrated endpoints; discrete Arc cards have a PCIe link
+                Some(caps) => caps.pcie.is_none(),
+                // Most Intel GPUs are integrated except for Arc series
+                None => !device.device_name.to_lowercase().contains("arc"),
+            }
         },
         GpuVendor::AMD => {
             // AMD APUs have integrated graphics
@@ -157,110 +316,111 @@ fn is_integrated_gpu(device: &PciDevice, vendor: &GpuVendor) -> bool {
     }
 }
 
-/// Attempts to detect GPU VRAM size
-fn detect_vram_size(_device: &PciDevice) -> Option<u64> {
-    // For now, we'll use a simplified implementation
-    // A real implementation would read from sysfs or other sources
+/// Attempts to detect GPU VRAM size in MB from driver-exposed attributes
+fn detect_vram_size(device: &PciDevice) -> Option<u64> {
+    // amdgpu reports the real VRAM total in bytes
+    if let Ok(total) = fs::read_to_string(device.sysfs_path.join("mem_info_vram_total")) {
+        if let Ok(bytes) = total.trim().parse::<u64>() {
+            if bytes > 0 {
+                return Some(bytes / (1024 * 1024));
+            }
+        }
+    }
+
+    // Other drivers don't expose a total; the BAR aperture is only a window
+    // into VRAM, so it is reported separately rather than guessed from here
     None
 }
 
-/// Gets a list of all PCI devices in the system (simplified version)
-/// In a real implementation, we'd use a proper PCI library or direct sysfs access
-fn get_pci_devices() -> Vec<PciDevice> {
-    let mut devices = Vec::new();
-    
-    // This is a simplified implementation using lspci
-    // Ideally, we'd use a library for this or direct sysfs access
-    let output = Command::new("lspci")
-        .args(["-vmm"])
-        .output();
-        
-    if let Ok(output) = output {
-        if output.status.success() {
-            // Parse lspci -vmm output (simplified)
-            let stdout = String::from_utf8_lossy(&output.stdout);
-            
-            // Split output by device (double newline)
-            let device_sections: Vec<&str> = stdout.split("\n\n").collect();
-            
-            for section in device_sections {
-                if section.trim().is_empty() {
-                    continue;
-                }
-                
-                // Parse device attributes
-                let mut bdf = String::new();
-                let mut class = String::new();
-                let mut vendor_name = String::new();
-                let mut device_name = String::new();
-                // vendor_id and device_id will be set later
-                
-                for line in section.lines() {
-                    let parts: Vec<&str> = line.splitn(2, ':').collect();
-                    if parts.len() != 2 {
-                        continue;
-                    }
-                    
-                    let key = parts[0].trim();
-                    let value = parts[1].trim();
-                    
-                    match key {
-                        "Slot" => bdf = value.to_string(),
-                        "Class" => class = value.to_string(),
-                        "Vendor" => vendor_name = value.to_string(),
-                        "Device" => device_name = value.to_string(),
-                        _ => {}
-                    }
-                }
-                
-                // We need to get vendor/device IDs separately
-                if !bdf.is_empty() {
-                    // Initialize vendor_id based on vendor name
-                    let vendor_id = if vendor_name.contains("NVIDIA") {
-                        "10de".to_string()
-                    } else if vendor_name.contains("AMD") || vendor_name.contains("ATI") {
-                        "1002".to_string()
-                    } else if vendor_name.contains("Intel") {
-                        "8086".to_string()
-                    } else {
-                        "0000".to_string()
-                    };
-                    
-                    // Initialize device_id
-                    let device_id = "0000".to_string();
-                    
-                    // Build the sysfs path
-                    let sysfs_path = PathBuf::from(format!("/sys/bus/pci/devices/{}", bdf.replace(':', "/")));
-                    
-                    // Check if the device has a driver
-                    let driver = get_device_driver(&sysfs_path);
-                    
-                    devices.push(PciDevice {
-                        bdf,
-                        vendor_id,
-                        device_id,
-                        class,
-                        vendor_name,
-                        device_name,
-                        driver,
-                        sysfs_path,
-                    });
+/// Gets a list of all PCI devices in the system from sysfs,
+/// with names resolved from the pci.ids database when available
+fn get_pci_devices(sysfs_root: &Path) -> Vec<PciDevice> {
+    match enumerate_pci_devices(sysfs_root) {
+        Ok(mut devices) => {
+            if let Some(pci_ids) = PciIds::load() {
+                for device in devices.iter_mut() {
+                    pci_ids.annotate(device);
                 }
+            } else {
+                println!("Warning: pci.ids database not found, device names will be generic.");
+            }
+            // Map every device to its IOMMU group (no groups means the IOMMU is off)
+            match read_iommu_groups(sysfs_root) {
+                Ok(groups) => assign_iommu_groups(&mut devices, &groups),
+                Err(e) => println!("Warning: Failed to read IOMMU groups: {}", e),
             }
+            devices
+        }
+        Err(e) => {
+            println!("Warning: Failed to enumerate PCI devices from {}: {}", sysfs_root.display(), e);
+            Vec::new()
         }
     }
-    
-    devices
 }
 
-/// Gets the current driver for a PCI device from sysfs
-fn get_device_driver(sysfs_path: &Path) -> Option<String> {
-    let driver_path = sysfs_path.join("driver");
-    if driver_path.exists() {
-        if let Ok(driver) = fs::read_link(driver_path) {
-            return driver.file_name()
-                .map(|name| name.to_string_lossy().to_string());
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn function(bdf: &str, vendor_id: &str, class_code: u32) -> PciDevice {
+        PciDevice {
+            bdf: bdf.to_string(),
+            vendor_id: vendor_id.to_string(),
+            device_id: "0000".to_string(),
+            subsystem_vendor_id: String::new(),
+            subsystem_device_id: String::new(),
+            revision: String::new(),
+            class_code,
+            class: String::new(),
+            vendor_name: String::new(),
+            device_name: String::new(),
+            subsystem_name: None,
+            driver: None,
+            iommu_group: None,
+            bars: Vec::new(),
+            sysfs_path: PathBuf::from("/nonexistent").join(bdf),
         }
     }
-    None
-}
\ No newline at end of file
+
+    fn bdfs(devices: &[PciDevice]) -> Vec<&str> {
+        devices.iter().map(|dev| dev.bdf.as_str()).collect()
+    }
+
+    #[test]
+    fn discrete_gpu_takes_its_audio_usb_and_ucsi() {
+        let gpu = function("0000:01:00.0", "10de", 0x030000);
+        let all = [
+            gpu.clone(),
+            function("0000:01:00.1", "10de", 0x040300),
+            function("0000:01:00.2", "10de", 0x0c0330),
+            function("0000:01:00.3", "10de", 0x0c8000),
+            function("0000:02:00.0", "10de", 0x040300), // Another slot
+        ];
+        let (companions, shared) = find_companion_functions(&gpu, &all, false);
+        assert_eq!(bdfs(&companions), ["0000:01:00.1", "0000:01:00.2", "0000:01:00.3"]);
+        assert!(shared.is_empty());
+    }
+
+    #[test]
+    fn apu_slot_keeps_host_functions() {
+        let igpu = function("0000:07:00.0", "1002", 0x030000);
+        let all = [
+            igpu.clone(),
+            function("0000:07:00.1", "1002", 0x040300), // HDMI audio
+            function("0000:07:00.2", "1022", 0x108000), // PSP/CCP
+            function("0000:07:00.3", "1022", 0x0c0330), // xHCI
+            function("0000:07:00.5", "1022", 0x048000), // ACP audio
+            function("0000:07:00.6", "1022", 0x040300), // HD audio of another vendor
+        ];
+        let (companions, shared) = find_companion_functions(&igpu, &all, true);
+        assert_eq!(bdfs(&companions), ["0000:07:00.1"]);
+        assert_eq!(bdfs(&shared), ["0000:07:00.2", "0000:07:00.3", "0000:07:00.5", "0000:07:00.6"]);
+
+        // In a shared IOMMU group the host functions count as endpoints
+        let all: Vec<PciDevice> = all.into_iter().map(|dev| PciDevice { iommu_group: Some(5), ..dev }).collect();
+        let peers = group_peers(&all[0], &all, &companions);
+        let endpoints: Vec<&str> = peers.iter().filter(|peer| peer.kind == IommuPeerKind::Endpoint).map(|peer| peer.bdf.as_str()).collect();
+        assert_eq!(endpoints, ["0000:07:00.2", "0000:07:00.3", "0000:07:00.5", "0000:07:00.6"]);
+    }
+}
@@ -0,0 +1,98 @@
+// Display output detection for Exliar VFIO Automation Framework
+//
+// This module identifies the boot VGA device and maps GPUs to their DRM
+// cards and connectors, so the GPU driving the host's displays can be
+// protected from being passed through by accident.
+
+use std::fs;
+use std::path::Path;
+
+use crate::gpu::GpuDevice;
+
+/// A display connector of a DRM card (e.g., "card0-DP-1")
+#[derive(Debug, Clone)]
+pub struct DrmConnector {
+    pub name: String,   // Connector name without the card prefix (e.g., "DP-1")
+    pub status: String, // "connected", "disconnected" or "unknown"
+    pub enabled: bool,  // A CRTC is currently scanning out to it
+}
+
+impl DrmConnector {
+    /// Returns true if a display is plugged into this connector
+    pub fn is_connected(&self) -> bool {
+        self.status == "connected"
+    }
+}
+
+/// A DRM card exposed by a GPU's driver
+#[derive(Debug, Clone)]
+pub struct DrmCard {
+    pub name: String, // e.g., "card0"
+    pub connectors: Vec<DrmConnector>,
+}
+
+/// Returns true if the firmware used this device as the boot VGA device
+pub fn read_boot_vga(sysfs_path: &Path) -> bool {
+    fs::read_to_string(sysfs_path.join("boot_vga"))
+        .is_ok_and(|value| value.trim() == "1")
+}
+
+/// Finds the DRM cards of a device and their connectors
+///
+/// Cards are listed under the device's own drm/ directory; connectors are
+/// read from `<sysfs_root>/class/drm/cardN-*`. Devices bound to vfio-pci
+/// (or no driver) have no cards.
+pub fn read_drm_cards(sysfs_root: &Path, sysfs_path: &Path) -> Vec<DrmCard> {
+    let Ok(entries) = fs::read_dir(sysfs_path.join("drm")) else { return Vec::new() };
+
+    let mut cards: Vec<DrmCard> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| name.strip_prefix("card").is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit())))
+        .map(|name| DrmCard {
+            connectors: read_connectors(sysfs_root, &name),
+            name,
+        })
+        .collect();
+
+    cards.sort_by(|a, b| a.name.cmp(&b.name));
+    cards
+}
+
+/// Reads every connector of a DRM card from /sys/class/drm
+fn read_connectors(sysfs_root: &Path, card: &str) -> Vec<DrmConnector> {
+    let Ok(entries) = fs::read_dir(sysfs_root.join("class/drm")) else { return Vec::new() };
+    let prefix = format!("{}-", card);
+
+    let mut connectors: Vec<DrmConnector> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let file_name = entry.file_name().to_string_lossy().to_string();
+            let name = file_name.strip_prefix(&prefix)?.to_string();
+            let read_attr = |attr: &str| fs::read_to_string(entry.path().join(attr))
+                .map(|value| value.trim().to_string())
+                .ok();
+            Some(DrmConnector {
+                name,
+                status: read_attr("status").unwrap_or_else(|| "unknown".to_string()),
+                enabled: read_attr("enabled").is_some_and(|value| value == "enabled"),
+            })
+        })
+        .collect();
+
+    connectors.sort_by(|a, b| a.name.cmp(&b.name));
+    connectors
+}
+
+/// Returns true if passing `selected` through would leave the host without a display
+///
+/// That is the case when it is the only GPU with connected displays, or when
+/// no connector information is available and it is the boot VGA device.
+pub fn is_sole_host_display(selected: &GpuDevice, gpus: &[GpuDevice]) -> bool {
+    if gpus.iter().any(|gpu| gpu.bdf != selected.bdf && gpu.is_driving_display()) {
+        return false;
+    }
+
+    let connector_info = gpus.iter().any(|gpu| gpu.drm_cards.iter().any(|card| !card.connectors.is_empty()));
+    selected.is_driving_display() || (!connector_info && selected.boot_vga)
+}
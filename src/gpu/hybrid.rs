@@ -0,0 +1,335 @@
+// Hybrid graphics (laptop) support for Exliar VFIO Automation Framework
+//
+// This module recognises Optimus / AMD hybrid laptops, where a discrete GPU
+// renders for an integrated GPU that owns the panel. Muxless dGPUs have no
+// display outputs and no ROM BAR: their VBIOS is only reachable through
+// ACPI, and NVIDIA's mobile driver additionally wants a battery and the
+// laptop's subsystem IDs in the guest. The kit generated here covers both.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::gpu::config_space::{read_u16, read_u32};
+use crate::gpu::pci::PciDevice;
+use crate::gpu::rom::{managed_rom_path, save_rom, user_rom_path};
+use crate::gpu::vbios::VbiosInfo;
+use crate::gpu::{GpuDevice, GpuVendor};
+
+/// Directory the generated SSDT and its source are stored in
+pub const HYBRID_DIR: &str = "/var/lib/exliar-vfio/hybrid";
+
+/// ACPI tables exposed by the kernel (root only)
+pub const ACPI_TABLES_DIR: &str = "/sys/firmware/acpi/tables";
+
+const SUBCLASS_3D_CONTROLLER: u8 = 0x02;
+const ACPI_HEADER_LEN: usize = 36;
+const VFCT_IMAGE_HEADER_LEN: usize = 28;
+
+/// DMI chassis types of portable machines (laptop, notebook, sub-notebook,
+/// convertible, detachable, ...)
+const PORTABLE_CHASSIS_TYPES: &[u32] = &[8, 9, 10, 14, 30, 31, 32];
+
+/// Whether the panel can be switched to the discrete GPU
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MuxState {
+    Present, // The dGPU can drive the internal panel (MUX switch or Apple gmux)
+    Absent,  // Muxless: the dGPU renders, the iGPU scans out
+    Unknown, // The dGPU is not bound to a DRM driver, so its outputs are unknown
+}
+
+impl std::fmt::Display for MuxState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            MuxState::Present => write!(f, "MUX present"),
+            MuxState::Absent => write!(f, "muxless"),
+            MuxState::Unknown => write!(f, "MUX unknown"),
+        }
+    }
+}
+
+/// Hybrid topology of a discrete laptop GPU
+#[derive(Debug, Clone)]
+pub struct HybridInfo {
+    pub igpu_bdf: String,              // The integrated GPU that owns the panel
+    pub is_3d_controller: bool,        // Class 0x0302: the dGPU has no display engine outputs
+    pub connectors: usize,             // DRM connectors of the dGPU (0 if unbound)
+    pub mux: MuxState,
+    pub subsystem_vendor_id: String,   // Laptop vendor, which NVIDIA's mobile driver checks
+    pub subsystem_device_id: String,
+}
+
+impl HybridInfo {
+    /// Returns true if the dGPU has no path to a display
+    pub fn is_muxless(&self) -> bool {
+        self.mux == MuxState::Absent
+    }
+
+    /// Returns a one-line description of the topology
+    pub fn summary(&self) -> String {
+        format!(
+            "{} ({}, {} connector(s)), iGPU {}",
+            self.mux,
+            if self.is_3d_controller { "3D controller" } else { "VGA controller" },
+            self.connectors, self.igpu_bdf,
+        )
+    }
+}
+
+/// Files written by generate_hybrid_kit
+#[derive(Debug, Clone, Default)]
+pub struct HybridKitFiles {
+    pub vbios: Option<PathBuf>,       // VBIOS extracted from ACPI, at the managed ROM path
+    pub vbios_source: Option<String>, // ACPI table it came from
+    pub ssdt: Option<PathBuf>,        // Compiled fake-battery SSDT (for -acpitable)
+    pub ssdt_source: Option<PathBuf>, // Its ASL source
+}
+
+/// Returns true if DMI or a battery says the machine is portable
+pub fn is_portable(sysfs_root: &Path) -> bool {
+    let chassis_portable = fs::read_to_string(sysfs_root.join("class/dmi/id/chassis_type"))
+        .ok()
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .is_some_and(|chassis| PORTABLE_CHASSIS_TYPES.contains(&chassis));
+    let has_battery = fs::read_dir(sysfs_root.join("class/power_supply"))
+        .map(|entries| entries.filter_map(|e| e.ok()).any(|e| e.file_name().to_string_lossy().starts_with("BAT")))
+        .unwrap_or(false);
+    chassis_portable || has_battery
+}
+
+/// Determines the hybrid topology of each discrete GPU of a laptop
+///
+/// `devices` are the PCI devices the GPUs were built from. Desktops and
+/// machines without an integrated GPU get no hybrid information.
+pub fn assign_hybrid_info(sysfs_root: &Path, gpus: &mut [GpuDevice], devices: &[PciDevice]) {
+    let Some(igpu_bdf) = gpus.iter().find(|gpu| gpu.is_integrated).map(|gpu| gpu.bdf.clone()) else { return };
+    if !is_portable(sysfs_root) {
+        return;
+    }
+    let apple_gmux = sysfs_root.join("bus/acpi/devices/APP000B:00").exists();
+
+    for gpu in gpus.iter_mut().filter(|gpu| !gpu.is_integrated) {
+        let Some(device) = devices.iter().find(|d| d.bdf == gpu.bdf) else { continue };
+        let is_3d_controller = device.subclass() == SUBCLASS_3D_CONTROLLER;
+        let connectors: Vec<&str> = gpu.drm_cards.iter()
+            .flat_map(|card| card.connectors.iter().map(|c| c.name.as_str()))
+            .collect();
+        let drives_panel = connectors.iter().any(|name| name.starts_with("eDP") || name.starts_with("LVDS"));
+
+        let mux = if apple_gmux || drives_panel {
+            MuxState::Present
+        } else if is_3d_controller || !gpu.drm_cards.is_empty() {
+            // External ports wired to the dGPU don't make the panel switchable
+            MuxState::Absent
+        } else {
+            MuxState::Unknown
+        };
+        gpu.hybrid = Some(HybridInfo {
+            igpu_bdf: igpu_bdf.clone(),
+            is_3d_controller,
+            connectors: connectors.len(),
+            mux,
+            subsystem_vendor_id: device.subsystem_vendor_id.clone(),
+            subsystem_device_id: device.subsystem_device_id.clone(),
+        });
+    }
+}
+
+/// Extracts a GPU's VBIOS from the ACPI tables
+///
+/// AMD laptops publish it in the VFCT table; NVIDIA laptops embed it in an
+/// SSDT/DSDT for the _ROM method. Returns the image and the table name.
+pub fn extract_acpi_vbios(tables_dir: &Path, gpu: &GpuDevice) -> Result<(Vec<u8>, String), String> {
+    let (Ok(vendor_id), Ok(device_id)) = (
+        u16::from_str_radix(&gpu.vendor_id, 16),
+        u16::from_str_radix(&gpu.device_id, 16),
+    ) else {
+        return Err(format!("Invalid device ID {}:{}", gpu.vendor_id, gpu.device_id));
+    };
+
+    let entries = fs::read_dir(tables_dir)
+        .map_err(|e| format!("Cannot read {} (run as root): {}", tables_dir.display(), e))?;
+    let mut tables: Vec<PathBuf> = entries.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    // Tables loaded at runtime (e.g., NVIDIA's GPS/_ROM SSDTs on some models)
+    if let Ok(dynamic) = fs::read_dir(tables_dir.join("dynamic")) {
+        tables.extend(dynamic.filter_map(|e| e.ok()).map(|e| e.path()));
+    }
+    tables.retain(|path| path.is_file());
+    tables.sort();
+
+    for path in &tables {
+        let Ok(data) = fs::read(path) else { continue };
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let image = if data.starts_with(b"VFCT") {
+            parse_vfct(&data, vendor_id, device_id)
+        } else {
+            find_embedded_rom(&data, vendor_id, device_id)
+        };
+        if let Some(image) = image {
+            return Ok((image, name));
+        }
+    }
+    Err(format!(
+        "No VBIOS for {}:{} found in {} ACPI table(s) under {}",
+        gpu.vendor_id, gpu.device_id, tables.len(), tables_dir.display(),
+    ))
+}
+
+/// Returns where generate_hybrid_kit writes the fake battery SSDT and its ASL source
+pub fn hybrid_ssdt_paths() -> (PathBuf, PathBuf) {
+    let dir = Path::new(HYBRID_DIR);
+    (dir.join("SSDT1.aml"), dir.join("SSDT1.dsl"))
+}
+
+/// Returns the hybrid kit files generated earlier; nothing is written
+///
+/// A user-supplied ROM stands in for a VBIOS extracted from ACPI.
+pub fn find_hybrid_kit(gpu: &GpuDevice) -> HybridKitFiles {
+    let existing = |path: PathBuf| path.exists().then_some(path);
+    let (aml_path, asl_path) = hybrid_ssdt_paths();
+    HybridKitFiles {
+        vbios: existing(managed_rom_path(&gpu.vendor_id, &gpu.device_id))
+            .or_else(|| existing(user_rom_path(&gpu.vendor_id, &gpu.device_id))),
+        vbios_source: None,
+        ssdt: existing(aml_path),
+        ssdt_source: existing(asl_path),
+    }
+}
+
+/// Writes the hybrid-graphics kit for a muxless GPU
+///
+/// The VBIOS goes to the managed ROM path so the vendor handlers' ROM
+/// loading picks it up; the fake-battery SSDT is only needed for NVIDIA.
+pub fn generate_hybrid_kit(gpu: &GpuDevice, dry_run: bool) -> io::Result<HybridKitFiles> {
+    let mut files = HybridKitFiles::default();
+
+    let rom_path = managed_rom_path(&gpu.vendor_id, &gpu.device_id);
+    if rom_path.exists() {
+        files.vbios = Some(rom_path);
+    } else {
+        match extract_acpi_vbios(Path::new(ACPI_TABLES_DIR), gpu) {
+            Ok((image, table)) => {
+                save_rom(&image, &rom_path, dry_run)?;
+                println!("VBIOS of {} extracted from ACPI table {} to {} ({} bytes)", gpu.bdf, table, rom_path.display(), image.len());
+                files.vbios = Some(rom_path);
+                files.vbios_source = Some(table);
+            }
+            Err(e) => println!("Warning: {}", e),
+        }
+    }
+
+    if gpu.vendor == GpuVendor::NVIDIA {
+        let (aml_path, asl_path) = hybrid_ssdt_paths();
+        if dry_run {
+            println!("[DRY RUN] Would write fake battery SSDT to {} and {}", aml_path.display(), asl_path.display());
+        } else {
+            fs::create_dir_all(HYBRID_DIR)?;
+            fs::write(&aml_path, fake_battery_ssdt())?;
+            fs::write(&asl_path, FAKE_BATTERY_ASL)?;
+        }
+        files.ssdt = Some(aml_path);
+        files.ssdt_source = Some(asl_path);
+    }
+
+    Ok(files)
+}
+
+/// ASL source of the fake battery (for reference; the AML is built by fake_battery_ssdt)
+pub const FAKE_BATTERY_ASL: &str = r#"/*
+ * Fake battery for NVIDIA mobile GPUs in a VM (generated by Exliar VFIO).
+ * The mobile driver refuses to start (Code 43) on systems without a battery.
+ */
+DefinitionBlock ("", "SSDT", 1, "BOCHS ", "BXPCSSDT", 0x00000001)
+{
+    Scope (\_SB.PCI0)
+    {
+        Device (BAT0)
+        {
+            Name (_HID, EisaId ("PNP0C0A"))
+            Name (_UID, One)
+            Method (_STA, 0, NotSerialized)
+            {
+                Return (0x0F)
+            }
+        }
+    }
+}
+"#;
+
+/// Builds the compiled (AML) form of FAKE_BATTERY_ASL, including the ACPI header
+pub fn fake_battery_ssdt() -> Vec<u8> {
+    // Method (_STA) { Return (0x0F) }
+    let mut method = b"_STA".to_vec();
+    method.extend_from_slice(&[0x00, 0xa4, 0x0a, 0x0f]); // NotSerialized, ReturnOp, ByteConst 0x0F
+
+    let mut device = b"BAT0".to_vec();
+    device.extend_from_slice(&[0x08, b'_', b'H', b'I', b'D', 0x0c, 0x41, 0xd0, 0x0c, 0x0a]); // EisaId ("PNP0C0A")
+    device.extend_from_slice(&[0x08, b'_', b'U', b'I', b'D', 0x01]); // One
+    device.push(0x14); // MethodOp
+    device.extend(aml_package(method));
+
+    let mut scope = vec![b'\\', 0x2e]; // Root, DualNamePrefix
+    scope.extend_from_slice(b"_SB_PCI0");
+    scope.extend_from_slice(&[0x5b, 0x82]); // DeviceOp
+    scope.extend(aml_package(device));
+
+    let mut aml = vec![0x10]; // ScopeOp
+    aml.extend(aml_package(scope));
+
+    let mut table = Vec::with_capacity(ACPI_HEADER_LEN + aml.len());
+    table.extend_from_slice(b"SSDT");
+    table.extend_from_slice(&((ACPI_HEADER_LEN + aml.len()) as u32).to_le_bytes());
+    table.push(1); // Revision
+    table.push(0); // Checksum, set below
+    table.extend_from_slice(b"BOCHS ");
+    table.extend_from_slice(b"BXPCSSDT");
+    table.extend_from_slice(&1u32.to_le_bytes()); // OEM revision
+    table.extend_from_slice(b"EXLR");
+    table.extend_from_slice(&1u32.to_le_bytes()); // Creator revision
+    table.extend(aml);
+    table[9] = table.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)).wrapping_neg();
+    table
+}
+
+/// Prefixes an AML body with its PkgLength (which counts its own bytes)
+fn aml_package(body: Vec<u8>) -> Vec<u8> {
+    let mut package = if body.len() + 1 < 0x40 {
+        vec![(body.len() + 1) as u8]
+    } else {
+        // Two-byte encoding: low nibble in the lead byte, the rest in the next
+        let len = body.len() + 2;
+        vec![0x40 | (len & 0x0f) as u8, (len >> 4) as u8]
+    };
+    package.extend(body);
+    package
+}
+
+/// Finds the image for a device in an AMD VFCT table
+fn parse_vfct(data: &[u8], vendor_id: u16, device_id: u16) -> Option<Vec<u8>> {
+    // Image headers follow each other from VBIOSImageOffset
+    let mut offset = read_u32(data, ACPI_HEADER_LEN + 16)? as usize;
+    while offset + VFCT_IMAGE_HEADER_LEN <= data.len() {
+        let image_len = read_u32(data, offset + 24)? as usize;
+        let start = offset + VFCT_IMAGE_HEADER_LEN;
+        if image_len == 0 {
+            break;
+        }
+        if read_u16(data, offset + 12)? == vendor_id && read_u16(data, offset + 14)? == device_id {
+            return data.get(start..start + image_len).map(<[u8]>::to_vec);
+        }
+        offset = start + image_len;
+    }
+    None
+}
+
+/// Finds a ROM image chain for a device embedded in an AML table
+fn find_embedded_rom(data: &[u8], vendor_id: u16, device_id: u16) -> Option<Vec<u8>> {
+    (0..data.len().saturating_sub(2))
+        .filter(|offset| data[*offset..].starts_with(&[0x55, 0xaa]))
+        .find_map(|offset| {
+            let vbios = VbiosInfo::parse(&data[offset..]).ok().filter(|vbios| vbios.header_len == 0)?;
+            let first = vbios.images.first().filter(|image| image.matches(vendor_id, device_id))?;
+            let end = vbios.images.iter().map(|image| image.offset + image.length).max().unwrap_or(first.length);
+            data.get(offset..offset + end).map(<[u8]>::to_vec)
+        })
+}
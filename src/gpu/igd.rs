@@ -0,0 +1,249 @@
+// Intel IGD assignment support for Exliar VFIO Automation Framework
+//
+// This module checks whether an Intel integrated GPU can be assigned to a
+// VM in QEMU's legacy IGD mode: it must sit at 00:02.0, have stolen memory
+// pre-allocated by the BIOS and an OpRegion, and the host LPC bridge is
+// mirrored into the guest. OpRegion and VBT are dumped from i915 debugfs.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::gpu::config_space::{read_config_space, read_u16, read_u32};
+use crate::gpu::pci::{read_pci_device, PciDevice};
+use crate::gpu::rom::{managed_rom_path, read_sysfs_rom, save_rom, user_rom_path};
+use crate::gpu::GpuDevice;
+
+/// Address QEMU's legacy mode requires the IGD at, on host and guest
+pub const IGD_BDF: &str = "0000:00:02.0";
+
+/// Address of the host ISA/LPC bridge mirrored into the guest
+pub const LPC_BRIDGE_BDF: &str = "0000:00:1f.0";
+
+/// Directory dumped OpRegion and VBT images are stored in
+pub const IGD_DIR: &str = "/var/lib/exliar-vfio/igd";
+
+/// Largest x-igd-gms value QEMU accepts (0x10 * 32 MB = 512 MB)
+pub const MAX_IGD_GMS: u64 = 0x10;
+
+const GMCH_OFFSET: usize = 0x50; // Graphics control (GMS field)
+const ASLS_OFFSET: usize = 0xfc; // ASL storage: physical address of the OpRegion
+const OPREGION_SIGNATURE: &[u8] = b"IntelGraphicsMem";
+const VBT_SIGNATURE: &[u8] = b"$VBT";
+
+/// A firmware table dumped from i915 debugfs
+#[derive(Debug, Clone)]
+pub struct IgdTable {
+    pub source: PathBuf,     // debugfs file it was read from
+    pub data: Vec<u8>,
+    pub version: String,     // e.g., "2.1" for an OpRegion, "$VBT SKYLAKE v221" for a VBT
+}
+
+/// Result of the IGD legacy-mode analysis
+#[derive(Debug, Clone)]
+pub struct IgdInfo {
+    pub at_legacy_address: bool,        // The IGD is at 00:02.0
+    pub generation: Option<u8>,         // Graphics generation (e.g., 9 for Skylake-Comet Lake)
+    pub stolen_memory_mb: Option<u64>,  // BIOS pre-allocated (DVMT) stolen memory; None if unreadable
+    pub opregion_address: Option<u32>,  // ASLS register; Some(0) means no OpRegion
+    pub opregion: Option<IgdTable>,     // OpRegion from i915 debugfs
+    pub vbt: Option<IgdTable>,          // Video BIOS Table from i915 debugfs
+    pub lpc_bridge: Option<PciDevice>,  // Host LPC bridge at 00:1f.0
+}
+
+impl IgdInfo {
+    /// Returns the x-igd-gms value for the guest: the host's stolen memory in
+    /// 32 MB units, clamped to what QEMU supports
+    pub fn recommended_gms(&self) -> u64 {
+        self.stolen_memory_mb
+            .map_or(2, |mb| (mb / 32).clamp(1, MAX_IGD_GMS))
+    }
+
+    /// Returns the reasons legacy-mode assignment will not work
+    pub fn legacy_mode_blockers(&self) -> Vec<String> {
+        let mut blockers = Vec::new();
+        if !self.at_legacy_address {
+            blockers.push(format!("The IGD is not at {}", IGD_BDF));
+        }
+        if self.stolen_memory_mb == Some(0) {
+            blockers.push("No stolen memory: set DVMT pre-allocated memory (e.g., 64M) in the BIOS".to_string());
+        }
+        if self.opregion_address == Some(0) {
+            blockers.push("The BIOS provides no OpRegion (ASLS is 0)".to_string());
+        }
+        if self.lpc_bridge.as_ref().is_none_or(|bridge| bridge.base_class() != 0x06) {
+            blockers.push(format!("No host LPC bridge at {}", LPC_BRIDGE_BDF));
+        }
+        blockers
+    }
+
+    /// Returns the non-fatal issues of the BIOS configuration
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.stolen_memory_mb.is_none() {
+            warnings.push("Stolen memory size unreadable (run as root)".to_string());
+        }
+        if let Some(mb) = self.stolen_memory_mb.filter(|mb| *mb > MAX_IGD_GMS * 32) {
+            warnings.push(format!(
+                "BIOS pre-allocates {} MB of stolen memory; the guest gets at most {} MB via x-igd-gms",
+                mb, MAX_IGD_GMS * 32,
+            ));
+        }
+        if self.generation.is_some_and(|generation| generation > 9) {
+            warnings.push("Legacy mode is only well tested up to Gen9.5 (Comet Lake); newer IGDs need a recent QEMU".to_string());
+        }
+        warnings
+    }
+}
+
+/// Analyses an Intel integrated GPU for legacy-mode assignment
+pub fn analyze_igd(sysfs_root: &Path, gpu: &GpuDevice) -> IgdInfo {
+    let devices = sysfs_root.join("bus/pci/devices");
+    let config = read_config_space(&devices.join(&gpu.bdf)).unwrap_or_default();
+    let generation = igd_generation(gpu);
+
+    IgdInfo {
+        at_legacy_address: gpu.bdf == IGD_BDF,
+        generation,
+        stolen_memory_mb: read_u16(&config, GMCH_OFFSET)
+            .map(|gmch| decode_stolen_memory_mb(gmch, generation.unwrap_or(9))),
+        opregion_address: read_u32(&config, ASLS_OFFSET),
+        opregion: find_debugfs_table(sysfs_root, gpu, "i915_opregion").and_then(|(source, data)| {
+            parse_opregion_version(&data).map(|version| IgdTable { source, data, version })
+        }),
+        vbt: find_debugfs_table(sysfs_root, gpu, "i915_vbt").and_then(|(source, data)| {
+            parse_vbt_version(&data).map(|version| IgdTable { source, data, version })
+        }),
+        lpc_bridge: read_pci_device(&devices.join(LPC_BRIDGE_BDF), LPC_BRIDGE_BDF),
+    }
+}
+
+/// Files written by extract_igd_firmware
+#[derive(Debug, Clone, Default)]
+pub struct IgdFirmwareFiles {
+    pub opregion: Option<PathBuf>,
+    pub vbt: Option<PathBuf>,
+    pub rom: Option<PathBuf>,
+}
+
+/// Returns where extract_igd_firmware stores a table ("opregion" or "vbt") of an IGD
+pub fn igd_firmware_path(gpu: &GpuDevice, name: &str) -> PathBuf {
+    Path::new(IGD_DIR).join(format!("{}-{}.bin", gpu.bdf.replace(':', "_"), name))
+}
+
+/// Returns the IGD firmware files extracted earlier; nothing is read from the device
+///
+/// A user-supplied ROM is used if no VBIOS was dumped.
+pub fn find_igd_firmware(gpu: &GpuDevice) -> IgdFirmwareFiles {
+    let existing = |path: PathBuf| path.exists().then_some(path);
+    IgdFirmwareFiles {
+        opregion: existing(igd_firmware_path(gpu, "opregion")),
+        vbt: existing(igd_firmware_path(gpu, "vbt")),
+        rom: existing(managed_rom_path(&gpu.vendor_id, &gpu.device_id))
+            .or_else(|| existing(user_rom_path(&gpu.vendor_id, &gpu.device_id))),
+    }
+}
+
+/// Saves the OpRegion, VBT and option ROM of an IGD to the managed directories
+///
+/// The ROM is the legacy VBIOS shadowed by the system BIOS; it is only
+/// available if the host booted with CSM/legacy video enabled.
+pub fn extract_igd_firmware(gpu: &GpuDevice, info: &IgdInfo, dry_run: bool) -> io::Result<IgdFirmwareFiles> {
+    let mut files = IgdFirmwareFiles::default();
+    let dir = Path::new(IGD_DIR);
+
+    for (table, name, slot) in [(&info.opregion, "opregion", &mut files.opregion), (&info.vbt, "vbt", &mut files.vbt)] {
+        if let Some(table) = table {
+            let path = igd_firmware_path(gpu, name);
+            if dry_run {
+                println!("[DRY RUN] Would write {} ({} bytes) to {}", name, table.data.len(), path.display());
+            } else {
+                fs::create_dir_all(dir)?;
+                fs::write(&path, &table.data)?;
+            }
+            *slot = Some(path);
+        }
+    }
+
+    match prepare_igd_romfile(gpu, dry_run) {
+        Ok(path) => files.rom = Some(path),
+        Err(e) => println!("Warning: {}", e),
+    }
+    Ok(files)
+}
+
+/// Returns the managed romfile of an IGD, dumping the shadowed VBIOS if needed
+pub fn prepare_igd_romfile(gpu: &GpuDevice, dry_run: bool) -> Result<PathBuf, String> {
+    let path = managed_rom_path(&gpu.vendor_id, &gpu.device_id);
+    if path.exists() {
+        return Ok(path);
+    }
+    if dry_run {
+        println!("[DRY RUN] Would dump the IGD VBIOS of {} to {}", gpu.bdf, path.display());
+        return Ok(path);
+    }
+
+    let sysfs_path = Path::new("/sys/bus/pci/devices").join(&gpu.bdf);
+    let data = read_sysfs_rom(&sysfs_path).map_err(|e| format!(
+        "Cannot read the IGD VBIOS ({}); boot the host with CSM enabled or place a ROM at {}",
+        e, user_rom_path(&gpu.vendor_id, &gpu.device_id).display(),
+    ))?;
+    save_rom(&data, &path, false).map_err(|e| format!("Failed to save ROM to {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Decodes the GMS field of the graphics control register into MB
+fn decode_stolen_memory_mb(gmch: u16, generation: u8) -> u64 {
+    if generation < 8 {
+        // Sandy Bridge to Haswell: bits 7:3 in 32 MB units
+        ((gmch >> 3) & 0x1f) as u64 * 32
+    } else {
+        // Broadwell and later: bits 15:8, 32 MB units below 0xf0, 4 MB steps above
+        let gms = (gmch >> 8) as u64;
+        if gms < 0xf0 { gms * 32 } else { (gms - 0xf0 + 1) * 4 }
+    }
+}
+
+/// Determines the graphics generation from the database family or device ID
+fn igd_generation(gpu: &GpuDevice) -> Option<u8> {
+    if let Some(generation) = gpu.profile.as_ref()
+        .and_then(|p| p.family.as_deref())
+        .and_then(|family| family.strip_prefix("Gen"))
+        .and_then(|generation| generation.parse().ok()) {
+        return Some(generation);
+    }
+    // Sandy Bridge, Ivy Bridge, Haswell and Bay Trail predate the database families
+    match gpu.device_id.get(..2) {
+        Some("01") => Some(6),
+        Some("04" | "0a" | "0c" | "0d" | "0f") => Some(7),
+        _ => None,
+    }
+}
+
+/// Reads an i915 debugfs file, trying the DRM minor and PCI address directories
+fn find_debugfs_table(sysfs_root: &Path, gpu: &GpuDevice, file: &str) -> Option<(PathBuf, Vec<u8>)> {
+    let debugfs = sysfs_root.join("kernel/debug/dri");
+    gpu.drm_cards.iter()
+        .filter_map(|card| card.name.strip_prefix("card"))
+        .map(|minor| debugfs.join(minor).join(file))
+        .chain(std::iter::once(debugfs.join(&gpu.bdf).join(file)))
+        .find_map(|path| fs::read(&path).ok().filter(|data| !data.is_empty()).map(|data| (path, data)))
+}
+
+/// Returns the "major.minor" version of an OpRegion, if the signature is valid
+fn parse_opregion_version(data: &[u8]) -> Option<String> {
+    if !data.starts_with(OPREGION_SIGNATURE) {
+        return None;
+    }
+    let version = read_u32(data, 0x14)?;
+    Some(format!("{}.{}", version >> 24, (version >> 16) & 0xff))
+}
+
+/// Returns the identification string and version of a VBT, if the signature is valid
+fn parse_vbt_version(data: &[u8]) -> Option<String> {
+    if !data.starts_with(VBT_SIGNATURE) {
+        return None;
+    }
+    let id = String::from_utf8_lossy(data.get(..20)?).trim_end_matches(['\0', ' ']).to_string();
+    Some(format!("{} v{}", id, read_u16(data, 0x14)?))
+}
@@ -0,0 +1,119 @@
+// IOMMU group discovery for Exliar VFIO Automation Framework
+//
+// This module reads the kernel's IOMMU group layout from
+// /sys/kernel/iommu_groups and works out which devices would have to
+// accompany a GPU when it is handed to vfio-pci.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::gpu::pci::PciDevice;
+
+/// A single IOMMU group and the PCI addresses it contains
+#[derive(Debug, Clone)]
+pub struct IommuGroup {
+    pub id: u32,
+    pub devices: Vec<String>, // Full PCI addresses (e.g., "0000:01:00.0")
+}
+
+/// How a device sharing a GPU's IOMMU group must be handled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IommuPeerKind {
+    /// PCI/host bridge - can stay on its host driver (pcieport)
+    Bridge,
+    /// Another function of the same slot - passed through together with the GPU
+    CompanionFunction,
+    /// Endpoint device - must also be bound to vfio-pci
+    Endpoint,
+}
+
+/// Another device found in the same IOMMU group as a GPU
+#[derive(Debug, Clone)]
+pub struct IommuGroupPeer {
+    pub bdf: String,
+    pub vendor_id: String,
+    pub device_id: String,
+    pub description: String, // e.g., "Audio device: GA102 High Definition Audio Controller"
+    pub driver: Option<String>,
+    pub kind: IommuPeerKind,
+}
+
+/// Reads all IOMMU groups from `<sysfs_root>/kernel/iommu_groups`
+///
+/// Returns an empty list if the IOMMU is disabled (the directory exists
+/// but is empty) and an error if it cannot be read at all.
+pub fn read_iommu_groups(sysfs_root: &Path) -> io::Result<Vec<IommuGroup>> {
+    let groups_dir = sysfs_root.join("kernel/iommu_groups");
+    let mut groups = Vec::new();
+
+    for entry in fs::read_dir(&groups_dir)? {
+        let entry = entry?;
+        let Ok(id) = entry.file_name().to_string_lossy().parse::<u32>() else { continue };
+
+        let mut devices: Vec<String> = match fs::read_dir(entry.path().join("devices")) {
+            Ok(dir) => dir
+                .filter_map(|d| d.ok())
+                .map(|d| d.file_name().to_string_lossy().to_string())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        devices.sort();
+
+        groups.push(IommuGroup { id, devices });
+    }
+
+    groups.sort_by_key(|group| group.id);
+    Ok(groups)
+}
+
+/// Builds a map of PCI address -> IOMMU group number
+pub fn group_map(groups: &[IommuGroup]) -> HashMap<String, u32> {
+    groups.iter()
+        .flat_map(|group| group.devices.iter().map(move |bdf| (bdf.clone(), group.id)))
+        .collect()
+}
+
+/// Fills in the IOMMU group of every device from the group listing
+pub fn assign_iommu_groups(devices: &mut [PciDevice], groups: &[IommuGroup]) {
+    let map = group_map(groups);
+    for device in devices.iter_mut() {
+        if let Some(group) = map.get(&device.bdf) {
+            device.iommu_group = Some(*group);
+        }
+    }
+}
+
+/// Returns true if a device is a bridge that can be left on the host
+/// when the rest of its group is passed through
+pub fn is_bridge(device: &PciDevice) -> bool {
+    // Host bridge (0x0600), PCI-to-PCI bridge (0x0604), semi-transparent bridge (0x0609)
+    device.base_class() == 0x06 && matches!(device.subclass(), 0x00 | 0x04 | 0x09)
+}
+
+/// Lists every other device sharing `device`'s IOMMU group
+///
+/// `companions` are the GPU functions of the slot passed through with it;
+/// any other function of the slot is an endpoint like the rest.
+pub fn group_peers(device: &PciDevice, all_devices: &[PciDevice], companions: &[PciDevice]) -> Vec<IommuGroupPeer> {
+    let Some(group) = device.iommu_group else { return Vec::new() };
+
+    all_devices.iter()
+        .filter(|other| other.bdf != device.bdf && other.iommu_group == Some(group))
+        .map(|other| IommuGroupPeer {
+            bdf: other.bdf.clone(),
+            vendor_id: other.vendor_id.clone(),
+            device_id: other.device_id.clone(),
+            description: format!("{}: {}", other.class, other.device_name),
+            driver: other.driver.clone(),
+            kind: if is_bridge(other) {
+                IommuPeerKind::Bridge
+            } else if companions.iter().any(|companion| companion.bdf == other.bdf) {
+                IommuPeerKind::CompanionFunction
+            } else {
+                IommuPeerKind::Endpoint
+            },
+        })
+        .collect()
+}
@@ -0,0 +1,122 @@
+// PCIe link status for Exliar VFIO Automation Framework
+//
+// This module reads the negotiated and maximum link speed/width of a
+// device and of every bridge above it from sysfs, so that cards trained
+// at a lower generation or width than they support (x4 in an x16 slot,
+// Gen1 behind a bad riser) show up before they are blamed on passthrough.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::gpu::acs::BridgeAcs;
+
+/// Negotiated and maximum state of a PCIe link
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkStatus {
+    pub current_speed: Option<f32>, // GT/s (e.g., 16.0)
+    pub current_width: Option<u8>,  // Lanes (e.g., 16)
+    pub max_speed: Option<f32>,
+    pub max_width: Option<u8>,
+}
+
+impl LinkStatus {
+    /// Returns true if the link trained below its maximum speed
+    ///
+    /// GPUs lower the speed when idle to save power, so this alone is only
+    /// a hint; check it again under load.
+    pub fn speed_degraded(&self) -> bool {
+        matches!((self.current_speed, self.max_speed), (Some(current), Some(max)) if current < max)
+    }
+
+    /// Returns true if the link trained with fewer lanes than it supports
+    pub fn width_degraded(&self) -> bool {
+        matches!((self.current_width, self.max_width), (Some(current), Some(max)) if current < max)
+    }
+
+    /// Returns true if the link runs below its maximum speed or width
+    pub fn is_degraded(&self) -> bool {
+        self.speed_degraded() || self.width_degraded()
+    }
+}
+
+impl fmt::Display for LinkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let describe = |speed: Option<f32>, width: Option<u8>| format!(
+            "{} x{}",
+            speed.map_or("Gen?".to_string(), |gts| pcie_generation(gts).map_or(format!("{} GT/s", gts), |generation| format!("Gen{}", generation))),
+            width.map_or("?".to_string(), |lanes| lanes.to_string()),
+        );
+        write!(f, "{}", describe(self.current_speed, self.current_width))?;
+        if self.is_degraded() {
+            write!(f, " (max {})", describe(self.max_speed, self.max_width))?;
+        }
+        Ok(())
+    }
+}
+
+/// One bridge on the path from the root port to a GPU
+#[derive(Debug, Clone)]
+pub struct PcieHop {
+    pub bdf: String,
+    pub role: &'static str,        // e.g., "Root Port", "Switch Downstream Port"
+    pub link: Option<LinkStatus>,  // None for conventional PCI bridges
+}
+
+/// Reads a device's link status from sysfs
+///
+/// Returns None if the device has no PCIe link (root complex integrated
+/// endpoints, conventional PCI) or the attributes don't exist.
+pub fn read_link_status(sysfs_path: &Path) -> Option<LinkStatus> {
+    let read_attr = |attr: &str| fs::read_to_string(sysfs_path.join(attr)).ok();
+    let status = LinkStatus {
+        current_speed: read_attr("current_link_speed").as_deref().and_then(parse_link_speed),
+        current_width: read_attr("current_link_width").as_deref().and_then(parse_link_width),
+        max_speed: read_attr("max_link_speed").as_deref().and_then(parse_link_speed),
+        max_width: read_attr("max_link_width").as_deref().and_then(parse_link_width),
+    };
+    // Integrated devices report "Unknown" speeds and a width of 0
+    if status.current_speed.is_none() && status.current_width.is_none() {
+        return None;
+    }
+    Some(status)
+}
+
+/// Reads the links of a device's upstream bridges, root port first
+///
+/// `bridges` is the nearest-first path from the ACS analysis, which already
+/// knows each bridge's PCIe port type.
+pub fn upstream_links(sysfs_path: &Path, bridges: &[BridgeAcs]) -> Vec<PcieHop> {
+    let Some(devices_dir) = sysfs_path.parent() else { return Vec::new() };
+    bridges.iter()
+        .rev()
+        .map(|bridge| PcieHop {
+            bdf: bridge.bdf.clone(),
+            role: bridge.role(),
+            link: read_link_status(&devices_dir.join(&bridge.bdf)),
+        })
+        .collect()
+}
+
+/// Returns the PCIe generation of a transfer rate
+pub fn pcie_generation(gts: f32) -> Option<u8> {
+    match gts {
+        s if (s - 2.5).abs() < 0.1 => Some(1),
+        s if (s - 5.0).abs() < 0.1 => Some(2),
+        s if (s - 8.0).abs() < 0.1 => Some(3),
+        s if (s - 16.0).abs() < 0.1 => Some(4),
+        s if (s - 32.0).abs() < 0.1 => Some(5),
+        s if (s - 64.0).abs() < 0.1 => Some(6),
+        _ => None,
+    }
+}
+
+/// Parses a sysfs link speed such as "16.0 GT/s PCIe" or "8 GT/s"
+fn parse_link_speed(value: &str) -> Option<f32> {
+    value.split_whitespace().next()?.parse().ok().filter(|gts: &f32| *gts > 0.0)
+}
+
+/// Parses a sysfs link width, treating 0 as unknown
+fn parse_link_width(value: &str) -> Option<u8> {
+    value.trim().parse().ok().filter(|lanes: &u8| *lanes > 0)
+}
@@ -0,0 +1,222 @@
+// Mediated device (mdev) management for Exliar VFIO Automation Framework
+//
+// This module lists the mdev types a parent device offers (Intel GVT-g),
+// creates and removes mediated devices through sysfs and persists them as
+// mdevctl definitions so they are recreated at boot.
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+/// Directory mdevctl reads persistent definitions from
+pub const MDEVCTL_CONFIG_DIR: &str = "/etc/mdevctl.d";
+
+/// sysfs directory of all mediated devices
+const MDEV_BUS_DEVICES: &str = "/sys/bus/mdev/devices";
+
+/// A mediated device type offered by a parent device
+#[derive(Debug, Clone)]
+pub struct MdevType {
+    pub id: String,                  // Type directory name (e.g., "i915-GVTg_V5_4")
+    pub name: Option<String>,        // Short name, if the driver provides one
+    pub description: String,         // Driver-provided description
+    pub available_instances: u32,    // How many more devices of this type can be created
+    pub device_api: Option<String>,  // e.g., "vfio-pci"
+    pub memory_mb: Option<u64>,      // Guest-visible graphics memory (GVT-g low + high GM)
+    pub resolution: Option<String>,  // Maximum resolution (GVT-g)
+}
+
+/// An existing mediated device
+#[derive(Debug, Clone)]
+pub struct MdevDevice {
+    pub uuid: String,
+    pub type_id: String,
+    pub parent_bdf: String,
+}
+
+/// Persistent mdevctl definition (/etc/mdevctl.d/<parent>/<uuid>)
+#[derive(Debug, Serialize)]
+struct MdevctlDefinition<'a> {
+    mdev_type: &'a str,
+    start: &'a str, // "auto" or "manual"
+    attrs: Vec<serde_json::Value>,
+}
+
+/// Lists the mdev types of a parent device, sorted by memory size
+pub fn list_mdev_types(sysfs_path: &Path) -> Vec<MdevType> {
+    let Ok(entries) = fs::read_dir(sysfs_path.join("mdev_supported_types")) else { return Vec::new() };
+
+    let mut types: Vec<MdevType> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| {
+            let type_path = entry.path();
+            let read_attr = |attr: &str| fs::read_to_string(type_path.join(attr))
+                .map(|value| value.trim().to_string())
+                .ok();
+            let description = read_attr("description").unwrap_or_default();
+            MdevType {
+                id: entry.file_name().to_string_lossy().to_string(),
+                name: read_attr("name"),
+                available_instances: read_attr("available_instances").and_then(|v| v.parse().ok()).unwrap_or(0),
+                device_api: read_attr("device_api"),
+                memory_mb: parse_gvtg_memory(&description),
+                resolution: description_field(&description, "resolution").map(str::to_string),
+                description,
+            }
+        })
+        .collect();
+
+    types.sort_by(|a, b| a.memory_mb.cmp(&b.memory_mb).then_with(|| a.id.cmp(&b.id)));
+    types
+}
+
+/// Lists the mediated devices created on a parent device
+pub fn list_mdevs(sysfs_path: &Path, parent_bdf: &str) -> Vec<MdevDevice> {
+    let Ok(types) = fs::read_dir(sysfs_path.join("mdev_supported_types")) else { return Vec::new() };
+
+    let mut devices: Vec<MdevDevice> = types
+        .filter_map(|entry| entry.ok())
+        .flat_map(|type_entry| {
+            let type_id = type_entry.file_name().to_string_lossy().to_string();
+            fs::read_dir(type_entry.path().join("devices"))
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.ok())
+                .map(move |entry| MdevDevice {
+                    uuid: entry.file_name().to_string_lossy().to_string(),
+                    type_id: type_id.clone(),
+                    parent_bdf: parent_bdf.to_string(),
+                })
+        })
+        .collect();
+
+    devices.sort_by(|a, b| a.uuid.cmp(&b.uuid));
+    devices
+}
+
+/// Picks the smallest type with free instances that provides at least `memory_mb`
+///
+/// Types without a parseable memory size only match a request of 0.
+pub fn select_mdev_type(types: &[MdevType], memory_mb: u64) -> Option<&MdevType> {
+    types.iter()
+        .filter(|t| t.available_instances > 0)
+        .filter(|t| memory_mb == 0 || t.memory_mb.is_some_and(|mb| mb >= memory_mb))
+        .min_by_key(|t| t.memory_mb.unwrap_or(0))
+}
+
+/// Generates a random (version 4) UUID for a new mediated device
+pub fn generate_uuid() -> io::Result<String> {
+    if let Ok(uuid) = fs::read_to_string("/proc/sys/kernel/random/uuid") {
+        return Ok(uuid.trim().to_string());
+    }
+
+    let mut bytes = [0u8; 16];
+    fs::File::open("/dev/urandom")?.read_exact(&mut bytes)?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40; // Version 4
+    bytes[8] = (bytes[8] & 0x3f) | 0x80; // RFC 4122 variant
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!("{}-{}-{}-{}-{}", &hex[0..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..32]))
+}
+
+/// Creates a mediated device of the given type
+pub fn create_mdev(sysfs_path: &Path, type_id: &str, uuid: &str, dry_run: bool) -> io::Result<()> {
+    let create_path = sysfs_path.join("mdev_supported_types").join(type_id).join("create");
+    if dry_run {
+        println!("[DRY RUN] Would write {} to {}", uuid, create_path.display());
+        return Ok(());
+    }
+    let mut file = fs::OpenOptions::new().write(true).open(&create_path)?;
+    write!(file, "{}", uuid)
+}
+
+/// Removes a mediated device
+///
+/// Fails with a busy error while a VM still uses the device.
+pub fn remove_mdev(uuid: &str, dry_run: bool) -> io::Result<()> {
+    let remove_path = Path::new(MDEV_BUS_DEVICES).join(uuid).join("remove");
+    if dry_run {
+        println!("[DRY RUN] Would write 1 to {}", remove_path.display());
+        return Ok(());
+    }
+    if !remove_path.exists() {
+        println!("Mediated device {} does not exist, nothing to remove.", uuid);
+        return Ok(());
+    }
+    let mut file = fs::OpenOptions::new().write(true).open(&remove_path)?;
+    write!(file, "1")
+}
+
+/// Returns the path of the mdevctl definition for a device
+pub fn mdevctl_definition_path(parent_bdf: &str, uuid: &str) -> PathBuf {
+    Path::new(MDEVCTL_CONFIG_DIR).join(parent_bdf).join(uuid)
+}
+
+/// Writes an mdevctl definition so the device is recreated at boot
+///
+/// Returns the path of the definition file.
+pub fn persist_mdev(device: &MdevDevice, auto_start: bool, dry_run: bool) -> io::Result<PathBuf> {
+    let path = mdevctl_definition_path(&device.parent_bdf, &device.uuid);
+    let definition = MdevctlDefinition {
+        mdev_type: &device.type_id,
+        start: if auto_start { "auto" } else { "manual" },
+        attrs: Vec::new(),
+    };
+    let content = serde_json::to_string_pretty(&definition)
+        .map_err(|e| io::Error::other(format!("Failed to serialize mdevctl definition: {}", e)))?;
+
+    if dry_run {
+        println!("[DRY RUN] Would write {}:\n{}", path.display(), content);
+        return Ok(path);
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, content + "\n")?;
+    Ok(path)
+}
+
+/// Deletes an mdevctl definition, if present
+pub fn remove_persisted_mdev(parent_bdf: &str, uuid: &str, dry_run: bool) -> io::Result<()> {
+    let path = mdevctl_definition_path(parent_bdf, uuid);
+    if dry_run {
+        println!("[DRY RUN] Would remove {}", path.display());
+        return Ok(());
+    }
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Builds the libvirt hostdev element for a mediated device
+pub fn mdev_hostdev_xml(uuid: &str) -> String {
+    format!(r#"<devices>
+  <hostdev mode='subsystem' type='mdev' managed='no' model='vfio-pci'>
+    <source>
+      <address uuid='{}'/>
+    </source>
+  </hostdev>
+</devices>"#, uuid)
+}
+
+/// Parses the graphics memory of a GVT-g type description
+///
+/// i915 describes types as "low_gm_size: 128MB\nhigh_gm_size: 512MB\n...".
+fn parse_gvtg_memory(description: &str) -> Option<u64> {
+    let parse_mb = |field: &str| description_field(description, field)
+        .and_then(|value| value.trim_end_matches("MB").trim().parse::<u64>().ok());
+    match (parse_mb("low_gm_size"), parse_mb("high_gm_size")) {
+        (None, None) => None,
+        (low, high) => Some(low.unwrap_or(0) + high.unwrap_or(0)),
+    }
+}
+
+/// Returns the value of a "field: value" line of a type description
+fn description_field<'a>(description: &'a str, field: &str) -> Option<&'a str> {
+    description.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim() == field)
+        .map(|(_, value)| value.trim())
+}
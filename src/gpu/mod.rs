@@ -3,11 +3,43 @@
 // This module handles GPU device detection, identification, and
 // vendor-specific handling for VFIO passthrough
 
+pub mod acs;
+pub mod config_space;
+pub mod database;
 pub mod detection;
+pub mod display;
+pub mod hybrid;
+pub mod igd;
+pub mod iommu;
+pub mod link;
+pub mod mdev;
+pub mod numa;
+pub mod pci;
+pub mod pci_ids;
+pub mod rebar;
+pub mod reset;
+pub mod rom;
+pub mod sriov;
+pub mod vbios;
 pub mod vendor;
+pub mod vendor_reset;
 
 use std::fmt;
 
+use crate::gpu::acs::AcsAnalysis;
+use crate::gpu::config_space::PciCapabilities;
+use crate::gpu::database::GpuProfile;
+use crate::gpu::display::{DrmCard, DrmConnector};
+use crate::gpu::hybrid::HybridInfo;
+use crate::gpu::iommu::{IommuGroupPeer, IommuPeerKind};
+use crate::gpu::link::{LinkStatus, PcieHop};
+use crate::gpu::mdev::{MdevDevice, MdevType};
+use crate::gpu::numa::NumaLocality;
+use crate::gpu::pci::{vram_aperture, PciBar, PciDevice};
+use crate::gpu::rebar::RebarInfo;
+use crate::gpu::reset::{ResetMethod, ResetTestResult};
+use crate::gpu::sriov::SriovInfo;
+
 /// GPU vendors supported by the framework
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum GpuVendor {
@@ -36,15 +68,18 @@ pub struct GpuDriverCapabilities {
     pub requires_acs_override: bool, // Needs ACS override patch for IOMMU separation
     pub supports_fbc: bool,        // Supports frame buffer compression
     pub supports_gvt: bool,        // Supports Intel GVT-g virtualization
-    pub has_reset_bug: bool,       // Has the AMD reset bug
+    pub has_reset_bug: Option<bool>, // Has the AMD reset bug (None until the database or a reset test tells)
     pub needs_code_43_workaround: bool, // Needs NVIDIA Code 43 workaround
     pub supports_vbios_loading: bool, // Supports custom VBIOS loading
+    pub bar_config_breaks_passthrough: bool, // Current (Re)BAR sizes are known to break guests
+    pub reset_methods: Vec<ResetMethod>, // Kernel reset methods in order of use (empty if unknown)
+    pub reset_test: Option<ResetTestResult>, // Result of the last on-demand reset test
 }
 
 /// Represents a detected GPU device
 #[derive(Debug, Clone)]
 pub struct GpuDevice {
-    pub bdf: String,               // Bus:Device.Function address (e.g., "01:00.0")
+    pub bdf: String,               // Full PCI address (e.g., "0000:01:00.0")
     pub vendor_id: String,         // Vendor ID (e.g., "10de" for NVIDIA)
     pub device_id: String,         // Device ID
     pub vendor: GpuVendor,         // Vendor enum
@@ -53,6 +88,26 @@ pub struct GpuDevice {
     pub vram_size: Option<u64>,    // VRAM size in MB (if detected)
     pub driver: Option<String>,    // Current driver in use
     pub capabilities: GpuDriverCapabilities,
+    pub profile: Option<GpuProfile>, // Family/architecture from the compatibility database
+    pub iommu_group: Option<u32>,  // IOMMU group number (None if IOMMU is inactive)
+    pub iommu_group_peers: Vec<IommuGroupPeer>, // Other devices in the same IOMMU group
+    pub companion_functions: Vec<PciDevice>, // GPU functions of the same slot (audio, USB-C, UCSI)
+    pub shared_slot_functions: Vec<PciDevice>, // Other functions of the slot, left with the host (e.g., an APU's xHCI)
+    pub bars: Vec<PciBar>,         // Populated BARs with their sizes
+    pub rebar: Option<RebarInfo>,  // Resizable BAR capability (None if absent or unreadable)
+    pub above_4g_decoding: Option<bool>, // Inferred from BAR placement
+    pub pci_capabilities: Option<PciCapabilities>, // Parsed capability lists (None without root)
+    pub acs: AcsAnalysis,          // ACS state of the upstream PCIe path
+    pub pcie_link: Option<LinkStatus>, // Link to the GPU (None for integrated GPUs)
+    pub upstream_links: Vec<PcieHop>, // Bridges above the GPU, root port first
+    pub numa: NumaLocality,        // NUMA node, local CPUs and the host's node layout
+    pub boot_vga: bool,            // Firmware used this GPU for the boot console
+    pub drm_cards: Vec<DrmCard>,   // DRM cards and connectors (empty if not bound to a DRM driver)
+    pub sriov: Option<SriovInfo>,  // SR-IOV state (None if the GPU can't create VFs)
+    pub virtual_functions: Vec<PciDevice>, // Enabled VFs of this GPU
+    pub mdev_types: Vec<MdevType>, // Mediated device types offered (GVT-g)
+    pub mdevs: Vec<MdevDevice>,    // Mediated devices created on this GPU
+    pub hybrid: Option<HybridInfo>, // Hybrid-graphics topology (laptop dGPUs only)
 }
 
 impl GpuDevice {
@@ -70,4 +125,52 @@ impl GpuDevice {
     pub fn model_name(&self) -> &str {
         &self.model_name
     }
-}
\ No newline at end of file
+
+    /// Returns the BAR that maps the GPU's VRAM, if one can be identified
+    pub fn vram_aperture(&self) -> Option<&PciBar> {
+        vram_aperture(&self.bars)
+    }
+
+    /// Returns the addresses of every function in the GPU's slot, GPU first
+    pub fn slot_bdfs(&self) -> Vec<String> {
+        std::iter::once(self.bdf.clone())
+            .chain(self.companion_functions.iter().map(|f| f.bdf.clone()))
+            .collect()
+    }
+
+    /// Returns the vendor:device IDs of every function in the slot,
+    /// deduplicated, for use with vfio-pci ids=
+    pub fn passthrough_ids(&self) -> Vec<String> {
+        let mut ids = vec![format!("{}:{}", self.vendor_id, self.device_id)];
+        for function in &self.companion_functions {
+            let id = function.id_pair();
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        ids
+    }
+
+    /// Returns the group peers outside the GPU's slot that must also be bound to vfio-pci
+    pub fn endpoint_peers(&self) -> impl Iterator<Item = &IommuGroupPeer> {
+        self.iommu_group_peers.iter().filter(|peer| peer.kind == IommuPeerKind::Endpoint)
+    }
+
+    /// Returns the connectors with a display plugged in
+    pub fn connected_displays(&self) -> Vec<&DrmConnector> {
+        self.drm_cards.iter()
+            .flat_map(|card| card.connectors.iter())
+            .filter(|connector| connector.is_connected())
+            .collect()
+    }
+
+    /// Returns true if the GPU is driving at least one connected display
+    pub fn is_driving_display(&self) -> bool {
+        !self.connected_displays().is_empty()
+    }
+
+    /// Returns true if the GPU is in an IOMMU group with no other endpoints
+    pub fn is_iommu_isolated(&self) -> bool {
+        self.iommu_group.is_some() && self.endpoint_peers().next().is_none()
+    }
+}
@@ -0,0 +1,173 @@
+// NUMA locality for Exliar VFIO Automation Framework
+//
+// This module reads which NUMA node a device is attached to, which CPUs
+// are local to it and the host's node layout, and builds libvirt CPU
+// pinning that keeps a VM's vCPUs and memory on the GPU's node.
+
+use std::fs;
+use std::path::Path;
+
+/// A NUMA node of the host
+#[derive(Debug, Clone)]
+pub struct NumaNode {
+    pub id: u32,
+    pub cpus: Vec<u32>,         // Online CPUs of the node
+    pub memory_mb: Option<u64>, // MemTotal of the node
+}
+
+/// NUMA placement of a device
+#[derive(Debug, Clone, Default)]
+pub struct NumaLocality {
+    pub node: Option<u32>,        // None if the platform reports no node (-1)
+    pub local_cpus: Vec<u32>,     // CPUs on the device's node (local_cpulist)
+    pub host_nodes: Vec<NumaNode>,
+}
+
+impl NumaLocality {
+    /// Returns true if the host has more than one NUMA node
+    pub fn is_multi_node(&self) -> bool {
+        self.host_nodes.len() > 1
+    }
+
+    /// Returns the CPUs a VM using the device should run on: the device's
+    /// local CPUs, or every CPU if those are unknown
+    pub fn preferred_cpus(&self) -> Vec<u32> {
+        if !self.local_cpus.is_empty() {
+            return self.local_cpus.clone();
+        }
+        if let Some(node) = self.node.and_then(|id| self.host_nodes.iter().find(|n| n.id == id)) {
+            return node.cpus.clone();
+        }
+        let mut cpus: Vec<u32> = self.host_nodes.iter().flat_map(|n| n.cpus.iter().copied()).collect();
+        cpus.sort_unstable();
+        cpus
+    }
+
+    /// Returns a one-line description (e.g., "node 1 of 2, CPUs 16-31")
+    pub fn summary(&self) -> String {
+        let cpus = if self.local_cpus.is_empty() { "unknown".to_string() } else { format_cpulist(&self.local_cpus) };
+        match self.node {
+            Some(node) => format!("node {} of {}, CPUs {}", node, self.host_nodes.len().max(1), cpus),
+            None => format!("no NUMA node reported, CPUs {}", cpus),
+        }
+    }
+}
+
+/// CPU pinning for a VM, derived from a device's NUMA locality
+#[derive(Debug, Clone)]
+pub struct CpuPinning {
+    pub vcpu_pins: Vec<u32>,      // Host CPU of each vCPU, by vCPU index
+    pub emulator_cpus: Vec<u32>,  // CPUs for QEMU's emulator and I/O threads
+    pub memory_node: Option<u32>, // Node to allocate guest memory on (multi-node hosts)
+}
+
+impl CpuPinning {
+    /// Builds pinning on the device's local CPUs
+    ///
+    /// The first local CPU is left to the emulator thread (and the host);
+    /// `vcpus` is capped to the remaining local CPUs, 0 means all of them.
+    pub fn for_locality(locality: &NumaLocality, vcpus: usize) -> Option<Self> {
+        let cpus = locality.preferred_cpus();
+        let (emulator_cpus, vcpu_cpus) = match cpus.len() {
+            0 => return None,
+            1 => (cpus.clone(), cpus.clone()),
+            _ => (cpus[..1].to_vec(), cpus[1..].to_vec()),
+        };
+        let count = if vcpus == 0 { vcpu_cpus.len() } else { vcpus.min(vcpu_cpus.len()) };
+        Some(Self {
+            vcpu_pins: vcpu_cpus[..count].to_vec(),
+            emulator_cpus,
+            memory_node: locality.node.filter(|_| locality.is_multi_node()),
+        })
+    }
+
+    /// Builds the libvirt vcpu, cputune and numatune elements
+    pub fn to_libvirt_xml(&self) -> String {
+        let mut xml = format!("<vcpu placement='static'>{}</vcpu>\n<cputune>\n", self.vcpu_pins.len());
+        for (vcpu, cpu) in self.vcpu_pins.iter().enumerate() {
+            xml.push_str(&format!("  <vcpupin vcpu='{}' cpuset='{}'/>\n", vcpu, cpu));
+        }
+        xml.push_str(&format!("  <emulatorpin cpuset='{}'/>\n</cputune>", format_cpulist(&self.emulator_cpus)));
+        if let Some(node) = self.memory_node {
+            xml.push_str(&format!("\n<numatune>\n  <memory mode='strict' nodeset='{}'/>\n</numatune>", node));
+        }
+        xml
+    }
+}
+
+/// Reads a device's NUMA node and local CPUs, and the host's nodes
+pub fn read_numa_locality(sysfs_root: &Path, sysfs_path: &Path) -> NumaLocality {
+    let node = fs::read_to_string(sysfs_path.join("numa_node"))
+        .ok()
+        .and_then(|value| value.trim().parse::<i32>().ok())
+        .and_then(|node| u32::try_from(node).ok());
+    let local_cpus = fs::read_to_string(sysfs_path.join("local_cpulist"))
+        .map(|list| parse_cpulist(&list))
+        .unwrap_or_default();
+
+    NumaLocality { node, local_cpus, host_nodes: read_numa_nodes(sysfs_root) }
+}
+
+/// Reads the host's NUMA nodes from /sys/devices/system/node
+pub fn read_numa_nodes(sysfs_root: &Path) -> Vec<NumaNode> {
+    let node_dir = sysfs_root.join("devices/system/node");
+    let Ok(entries) = fs::read_dir(&node_dir) else { return Vec::new() };
+
+    let mut nodes: Vec<NumaNode> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let id = entry.file_name().to_string_lossy().strip_prefix("node")?.parse().ok()?;
+            let path = entry.path();
+            Some(NumaNode {
+                id,
+                cpus: fs::read_to_string(path.join("cpulist")).map(|list| parse_cpulist(&list)).unwrap_or_default(),
+                memory_mb: fs::read_to_string(path.join("meminfo")).ok().and_then(|info| parse_node_memtotal(&info)),
+            })
+        })
+        .collect();
+    nodes.sort_by_key(|node| node.id);
+    nodes
+}
+
+/// Parses a kernel CPU list such as "0-7,16-23"
+pub fn parse_cpulist(list: &str) -> Vec<u32> {
+    list.trim()
+        .split(',')
+        .filter(|range| !range.is_empty())
+        .flat_map(|range| {
+            let (start, end) = range.split_once('-').unwrap_or((range, range));
+            match (start.trim().parse::<u32>(), end.trim().parse::<u32>()) {
+                (Ok(start), Ok(end)) if start <= end => (start..=end).collect(),
+                _ => Vec::new(),
+            }
+        })
+        .collect()
+}
+
+/// Formats CPUs as a kernel/libvirt CPU list, collapsing runs into ranges
+pub fn format_cpulist(cpus: &[u32]) -> String {
+    let mut sorted = cpus.to_vec();
+    sorted.sort_unstable();
+    sorted.dedup();
+
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for cpu in sorted {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == cpu => *end = cpu,
+            _ => ranges.push((cpu, cpu)),
+        }
+    }
+    ranges.iter()
+        .map(|(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Parses "Node 0 MemTotal:  32768000 kB" from a node's meminfo
+fn parse_node_memtotal(meminfo: &str) -> Option<u64> {
+    meminfo.lines()
+        .find(|line| line.contains("MemTotal:"))
+        .and_then(|line| line.split_whitespace().rev().nth(1))
+        .and_then(|kb| kb.parse::<u64>().ok())
+        .map(|kb| kb / 1024)
+}
@@ -0,0 +1,349 @@
+// Native PCI enumeration for Exliar VFIO Automation Framework
+//
+// This module walks /sys/bus/pci/devices directly and reads device
+// attributes (IDs, class, driver, IOMMU group) without relying on lspci.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Default sysfs mount point
+pub const SYSFS_ROOT: &str = "/sys";
+
+/// Describes a detected PCI device
+#[derive(Debug, Clone)]
+pub struct PciDevice {
+    pub bdf: String,                 // Full PCI address (e.g., "0000:01:00.0")
+    pub vendor_id: String,           // Vendor ID (e.g., "10de" for NVIDIA)
+    pub device_id: String,           // Device ID (e.g., "2206")
+    pub subsystem_vendor_id: String, // Subsystem vendor ID (board partner)
+    pub subsystem_device_id: String, // Subsystem device ID
+    pub revision: String,            // Revision ID (e.g., "a1")
+    pub class_code: u32,             // Raw class code (e.g., 0x030000)
+    pub class: String,               // Device class (e.g., "VGA compatible controller")
+    pub vendor_name: String,         // Vendor name (e.g., "NVIDIA Corporation")
+    pub device_name: String,         // Device name (e.g., "GA102 [GeForce RTX 3080]")
+    pub subsystem_name: Option<String>, // Board name from pci.ids, if known
+    pub driver: Option<String>,      // Current driver
+    pub iommu_group: Option<u32>,    // IOMMU group number, if the IOMMU is active
+    pub bars: Vec<PciBar>,           // Populated base address registers
+    pub sysfs_path: PathBuf,         // Path to device in sysfs
+}
+
+impl PciDevice {
+    /// Returns the base class (e.g., 0x03 for display controllers)
+    pub fn base_class(&self) -> u8 {
+        (self.class_code >> 16) as u8
+    }
+
+    /// Returns the subclass (e.g., 0x00 for VGA compatible controllers)
+    pub fn subclass(&self) -> u8 {
+        (self.class_code >> 8) as u8
+    }
+
+    /// Returns the programming interface byte
+    pub fn prog_if(&self) -> u8 {
+        self.class_code as u8
+    }
+
+    /// Returns the "vendor:device" ID pair as used by vfio-pci ids=
+    pub fn id_pair(&self) -> String {
+        format!("{}:{}", self.vendor_id, self.device_id)
+    }
+}
+
+// Resource flags from include/linux/ioport.h as exposed in sysfs "resource"
+const IORESOURCE_IO: u64 = 0x0000_0100;
+const IORESOURCE_MEM: u64 = 0x0000_0200;
+const IORESOURCE_PREFETCH: u64 = 0x0000_2000;
+const IORESOURCE_MEM_64: u64 = 0x0010_0000;
+
+/// A populated base address register (BAR) of a PCI device
+#[derive(Debug, Clone)]
+pub struct PciBar {
+    pub index: u8,              // BAR number (0-5)
+    pub start: u64,             // Physical start address
+    pub size: u64,              // Size in bytes
+    pub is_io: bool,            // I/O port space rather than memory
+    pub is_prefetchable: bool,  // Prefetchable memory (VRAM apertures are)
+    pub is_64bit: bool,         // 64-bit memory BAR
+}
+
+impl PciBar {
+    /// Returns the BAR size in MiB
+    pub fn size_mb(&self) -> u64 {
+        self.size / (1024 * 1024)
+    }
+}
+
+impl std::fmt::Display for PciBar {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        // Pick a readable unit for anything from I/O ports to multi-GiB apertures
+        let size = match self.size {
+            s if s >= 1 << 30 => format!("{} GiB", s >> 30),
+            s if s >= 1 << 20 => format!("{} MiB", s >> 20),
+            s if s >= 1 << 10 => format!("{} KiB", s >> 10),
+            s => format!("{} B", s),
+        };
+        let kind = if self.is_io {
+            "I/O"
+        } else {
+            match (self.is_64bit, self.is_prefetchable) {
+                (true, true) => "64-bit, prefetchable",
+                (true, false) => "64-bit",
+                (false, true) => "32-bit, prefetchable",
+                (false, false) => "32-bit",
+            }
+        };
+        write!(f, "BAR{}: {} ({})", self.index, size, kind)
+    }
+}
+
+/// A parsed PCI address (domain:bus:device.function)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PciAddress {
+    pub domain: u16,
+    pub bus: u8,
+    pub device: u8,
+    pub function: u8,
+}
+
+impl PciAddress {
+    /// Parses an address in "dddd:bb:dd.f" or "bb:dd.f" form
+    pub fn parse(address: &str) -> Option<Self> {
+        let parts: Vec<&str> = address.trim().split(':').collect();
+        let (domain, bus, dev_fn) = match parts.as_slice() {
+            [domain, bus, dev_fn] => (u16::from_str_radix(domain, 16).ok()?, *bus, *dev_fn),
+            [bus, dev_fn] => (0, *bus, *dev_fn),
+            _ => return None,
+        };
+        let (device, function) = dev_fn.split_once('.')?;
+
+        Some(PciAddress {
+            domain,
+            bus: u8::from_str_radix(bus, 16).ok()?,
+            device: u8::from_str_radix(device, 16).ok()?,
+            function: u8::from_str_radix(function, 16).ok()?,
+        })
+    }
+
+    /// Returns true if both addresses refer to the same physical slot (bus:device)
+    pub fn same_slot(&self, other: &PciAddress) -> bool {
+        self.domain == other.domain && self.bus == other.bus && self.device == other.device
+    }
+}
+
+impl std::fmt::Display for PciAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:04x}:{:02x}:{:02x}.{:x}", self.domain, self.bus, self.device, self.function)
+    }
+}
+
+/// Enumerates all PCI devices below `<sysfs_root>/bus/pci/devices`
+///
+/// The sysfs root is injectable so that enumeration can run against
+/// a fixture tree instead of the live /sys.
+pub fn enumerate_pci_devices(sysfs_root: &Path) -> io::Result<Vec<PciDevice>> {
+    let devices_dir = sysfs_root.join("bus/pci/devices");
+    let mut devices = Vec::new();
+
+    for entry in fs::read_dir(&devices_dir)? {
+        let entry = entry?;
+        let bdf = entry.file_name().to_string_lossy().to_string();
+
+        // Skip anything that doesn't look like a PCI address
+        if PciAddress::parse(&bdf).is_none() {
+            continue;
+        }
+
+        if let Some(device) = read_pci_device(&entry.path(), &bdf) {
+            devices.push(device);
+        }
+    }
+
+    // Sort by address so the output is stable across runs
+    devices.sort_by_key(|dev| PciAddress::parse(&dev.bdf));
+    Ok(devices)
+}
+
+/// Reads a single PCI device from its sysfs directory
+pub fn read_pci_device(sysfs_path: &Path, bdf: &str) -> Option<PciDevice> {
+    // Vendor and device IDs are mandatory; everything else is best-effort
+    let vendor_id = read_hex_attr(sysfs_path, "vendor")?;
+    let device_id = read_hex_attr(sysfs_path, "device")?;
+    let subsystem_vendor_id = read_hex_attr(sysfs_path, "subsystem_vendor").unwrap_or_default();
+    let subsystem_device_id = read_hex_attr(sysfs_path, "subsystem_device").unwrap_or_default();
+    let revision = read_hex_attr(sysfs_path, "revision").unwrap_or_default();
+    let class_code = read_hex_attr(sysfs_path, "class")
+        .and_then(|class| u32::from_str_radix(&class, 16).ok())
+        .unwrap_or(0);
+
+    Some(PciDevice {
+        bdf: bdf.to_string(),
+        vendor_name: fallback_vendor_name(&vendor_id),
+        device_name: format!("Device {}", device_id),
+        subsystem_name: None,
+        class: fallback_class_name(class_code),
+        vendor_id,
+        device_id,
+        subsystem_vendor_id,
+        subsystem_device_id,
+        revision,
+        class_code,
+        driver: get_device_driver(sysfs_path),
+        iommu_group: get_iommu_group(sysfs_path),
+        bars: read_bars(sysfs_path),
+        sysfs_path: sysfs_path.to_path_buf(),
+    })
+}
+
+/// Reads a sysfs attribute containing a hex value (e.g., "0x10de")
+/// and returns it lowercase without the "0x" prefix
+pub fn read_hex_attr(sysfs_path: &Path, attr: &str) -> Option<String> {
+    let content = fs::read_to_string(sysfs_path.join(attr)).ok()?;
+    let value = content.trim();
+    let value = value.strip_prefix("0x").unwrap_or(value);
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(value.to_lowercase())
+}
+
+/// Parses the sysfs "resource" file into the device's populated BARs
+///
+/// Each line is "<start> <end> <flags>"; lines 0-5 are the standard BARs,
+/// line 6 is the expansion ROM and later lines are bridge windows.
+pub fn read_bars(sysfs_path: &Path) -> Vec<PciBar> {
+    let Ok(content) = fs::read_to_string(sysfs_path.join("resource")) else { return Vec::new() };
+
+    content.lines()
+        .take(6)
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let values: Vec<u64> = line.split_whitespace()
+                .filter_map(|v| u64::from_str_radix(v.trim_start_matches("0x"), 16).ok())
+                .collect();
+            let [start, end, flags] = values[..] else { return None };
+            if end == 0 || end < start {
+                return None; // Unused BAR
+            }
+            Some(PciBar {
+                index: index as u8,
+                start,
+                size: end - start + 1,
+                is_io: flags & IORESOURCE_IO != 0,
+                is_prefetchable: flags & IORESOURCE_MEM != 0 && flags & IORESOURCE_PREFETCH != 0,
+                is_64bit: flags & IORESOURCE_MEM_64 != 0,
+            })
+        })
+        .collect()
+}
+
+/// Identifies the VRAM aperture of a GPU: the largest prefetchable memory BAR
+/// (BAR1 on NVIDIA, BAR0 on AMD, BAR2 on Intel discrete)
+pub fn vram_aperture(bars: &[PciBar]) -> Option<&PciBar> {
+    bars.iter()
+        .filter(|bar| bar.is_prefetchable)
+        .max_by_key(|bar| bar.size)
+}
+
+/// Gets the current driver for a PCI device from sysfs
+pub fn get_device_driver(sysfs_path: &Path) -> Option<String> {
+    link_basename(&sysfs_path.join("driver"))
+}
+
+/// Gets the IOMMU group number for a PCI device from sysfs
+pub fn get_iommu_group(sysfs_path: &Path) -> Option<u32> {
+    link_basename(&sysfs_path.join("iommu_group"))
+        .and_then(|group| group.parse().ok())
+}
+
+/// Returns the final path component of a symlink's target
+fn link_basename(link: &Path) -> Option<String> {
+    fs::read_link(link).ok()?
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+}
+
+/// Minimal vendor names for the GPU vendors we care about
+/// (used when no pci.ids database is available)
+fn fallback_vendor_name(vendor_id: &str) -> String {
+    match vendor_id {
+        "1002" => "Advanced Micro Devices, Inc. [AMD/ATI]".to_string(),
+        "10de" => "NVIDIA Corporation".to_string(),
+        "8086" => "Intel Corporation".to_string(),
+        _ => format!("Vendor {}", vendor_id),
+    }
+}
+
+/// Minimal class names for display devices and their usual companions
+fn fallback_class_name(class_code: u32) -> String {
+    match class_code >> 8 {
+        0x0300 => "VGA compatible controller".to_string(),
+        0x0301 => "XGA compatible controller".to_string(),
+        0x0302 => "3D controller".to_string(),
+        0x0380 => "Display controller".to_string(),
+        0x0403 => "Audio device".to_string(),
+        0x0604 => "PCI bridge".to_string(),
+        0x0c03 => "USB controller".to_string(),
+        0x0c80 => "Serial bus controller".to_string(),
+        _ => format!("Class {:04x}", class_code >> 8),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    /// Writes a device directory below a fixture sysfs root
+    fn write_device(sysfs_root: &Path, bdf: &str, attrs: &[(&str, &str)]) -> PathBuf {
+        let dir = sysfs_root.join("bus/pci/devices").join(bdf);
+        fs::create_dir_all(&dir).unwrap();
+        for (attr, value) in attrs {
+            fs::write(dir.join(attr), format!("{}\n", value)).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn enumerate_pci_devices_reads_fixture_tree() {
+        let sysfs_root = std::env::temp_dir().join(format!("exliar-vfio-pci-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&sysfs_root);
+
+        let gpu = write_device(&sysfs_root, "0000:01:00.0", &[
+            ("vendor", "0x10DE"), ("device", "0x2206"), ("subsystem_vendor", "0x1462"),
+            ("subsystem_device", "0x3892"), ("revision", "0xa1"), ("class", "0x030000"),
+        ]);
+        symlink("../../../bus/pci/drivers/vfio-pci", gpu.join("driver")).unwrap();
+        symlink("../../../kernel/iommu_groups/14", gpu.join("iommu_group")).unwrap();
+        // Unbound, no IOMMU, sorts before the GPU
+        write_device(&sysfs_root, "0000:00:1f.3", &[("vendor", "0x8086"), ("device", "0xa348"), ("class", "0x040300")]);
+        // Not a PCI address, and a device without IDs
+        fs::create_dir_all(sysfs_root.join("bus/pci/devices/pci0000:00")).unwrap();
+        write_device(&sysfs_root, "0000:02:00.0", &[("class", "0x010802")]);
+
+        let devices = enumerate_pci_devices(&sysfs_root).unwrap();
+        fs::remove_dir_all(&sysfs_root).unwrap();
+
+        let bdfs: Vec<&str> = devices.iter().map(|dev| dev.bdf.as_str()).collect();
+        assert_eq!(bdfs, ["0000:00:1f.3", "0000:01:00.0"]);
+
+        let audio = &devices[0];
+        assert_eq!(audio.class, "Audio device");
+        assert_eq!(audio.driver, None);
+        assert_eq!(audio.iommu_group, None);
+
+        let gpu = &devices[1];
+        assert_eq!(gpu.id_pair(), "10de:2206");
+        assert_eq!((gpu.subsystem_vendor_id.as_str(), gpu.subsystem_device_id.as_str()), ("1462", "3892"));
+        assert_eq!(gpu.revision, "a1");
+        assert_eq!(gpu.class_code, 0x030000);
+        assert_eq!((gpu.base_class(), gpu.subclass()), (0x03, 0x00));
+        assert_eq!(gpu.class, "VGA compatible controller");
+        assert_eq!(gpu.vendor_name, "NVIDIA Corporation");
+        assert_eq!(gpu.driver.as_deref(), Some("vfio-pci"));
+        assert_eq!(gpu.iommu_group, Some(14));
+        assert!(gpu.bars.is_empty());
+    }
+}
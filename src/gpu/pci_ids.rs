@@ -0,0 +1,299 @@
+// PCI ID database for Exliar VFIO Automation Framework
+//
+// This module parses the standard pci.ids format (as shipped by hwdata /
+// pciutils) to resolve human-readable vendor, device, subsystem and class
+// names without shelling out to lspci.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::gpu::pci::PciDevice;
+
+/// Locations where distributions commonly install pci.ids
+pub const PCI_IDS_SEARCH_PATHS: &[&str] = &[
+    "/usr/share/hwdata/pci.ids",
+    "/usr/share/misc/pci.ids",
+    "/usr/share/pci.ids",
+    "/usr/share/pciids/pci.ids",
+    "/usr/local/share/pci.ids",
+];
+
+/// Copy of data/pci.ids embedded at build time (`bundled-pci-ids` feature)
+#[cfg(feature = "bundled-pci-ids")]
+const BUNDLED_PCI_IDS: Option<&str> = Some(include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/data/pci.ids")));
+#[cfg(not(feature = "bundled-pci-ids"))]
+const BUNDLED_PCI_IDS: Option<&str> = None;
+
+/// A vendor entry with its devices
+#[derive(Debug, Clone, Default)]
+pub struct PciIdsVendor {
+    pub name: String,
+    pub devices: HashMap<String, PciIdsDevice>,
+}
+
+/// A device entry with its subsystems, keyed by "subvendor:subdevice"
+#[derive(Debug, Clone, Default)]
+pub struct PciIdsDevice {
+    pub name: String,
+    pub subsystems: HashMap<String, String>,
+}
+
+/// A device class entry with its subclasses
+#[derive(Debug, Clone, Default)]
+pub struct PciIdsClass {
+    pub name: String,
+    pub subclasses: HashMap<String, PciIdsSubclass>,
+}
+
+/// A subclass entry with its programming interfaces
+#[derive(Debug, Clone, Default)]
+pub struct PciIdsSubclass {
+    pub name: String,
+    pub prog_ifs: HashMap<String, String>,
+}
+
+/// Parsed pci.ids database
+#[derive(Debug, Clone, Default)]
+pub struct PciIds {
+    pub vendors: HashMap<String, PciIdsVendor>,
+    pub classes: HashMap<String, PciIdsClass>,
+    pub source: Option<PathBuf>, // File the database was loaded from (None if bundled or parsed from a string)
+}
+
+impl PciIds {
+    /// Loads the database from the first system path found, falling back
+    /// to the bundled copy if one was compiled in
+    pub fn load() -> Option<Self> {
+        for path in PCI_IDS_SEARCH_PATHS {
+            let path = Path::new(path);
+            if !path.exists() {
+                continue;
+            }
+            match Self::load_from(path) {
+                Ok(ids) => return Some(ids),
+                Err(e) => println!("Warning: Failed to read {}: {}", path.display(), e),
+            }
+        }
+
+        BUNDLED_PCI_IDS.map(Self::parse)
+    }
+
+    /// Loads the database from a specific file
+    pub fn load_from(path: &Path) -> io::Result<Self> {
+        // pci.ids is mostly ASCII but may contain stray Latin-1 bytes
+        let bytes = fs::read(path)?;
+        let mut ids = Self::parse(&String::from_utf8_lossy(&bytes));
+        ids.source = Some(path.to_path_buf());
+        Ok(ids)
+    }
+
+    /// Parses the contents of a pci.ids file
+    pub fn parse(content: &str) -> Self {
+        let mut ids = PciIds::default();
+
+        // Track the current position in the tree
+        let mut current_vendor: Option<String> = None;
+        let mut current_device: Option<String> = None;
+        let mut current_class: Option<String> = None;
+        let mut current_subclass: Option<String> = None;
+
+        for line in content.lines() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let depth = line.chars().take_while(|c| *c == '\t').count();
+            let body = line.trim_start_matches('\t');
+
+            match depth {
+                0 => {
+                    current_device = None;
+                    current_subclass = None;
+                    if let Some(class_line) = body.strip_prefix("C ") {
+                        // Class section: "C 03  Display controller"
+                        if let Some((id, name)) = split_id_name(class_line) {
+                            ids.classes.insert(id.clone(), PciIdsClass { name, ..Default::default() });
+                            current_class = Some(id);
+                        }
+                        current_vendor = None;
+                    } else if let Some((id, name)) = split_id_name(body) {
+                        // Vendor line: "10de  NVIDIA Corporation"
+                        ids.vendors.insert(id.clone(), PciIdsVendor { name, ..Default::default() });
+                        current_vendor = Some(id);
+                        current_class = None;
+                    } else {
+                        // Other top-level sections (e.g., "L" for languages) we don't use
+                        current_vendor = None;
+                        current_class = None;
+                    }
+                }
+                1 => {
+                    let Some((id, name)) = split_id_name(body) else { continue };
+                    if let Some(vendor) = current_vendor.as_ref().and_then(|v| ids.vendors.get_mut(v)) {
+                        vendor.devices.insert(id.clone(), PciIdsDevice { name, ..Default::default() });
+                        current_device = Some(id);
+                    } else if let Some(class) = current_class.as_ref().and_then(|c| ids.classes.get_mut(c)) {
+                        class.subclasses.insert(id.clone(), PciIdsSubclass { name, ..Default::default() });
+                        current_subclass = Some(id);
+                    }
+                }
+                2 => {
+                    if let (Some(vendor_id), Some(device_id)) = (&current_vendor, &current_device) {
+                        // Subsystem line: "1043 87c0  TUF Gaming GeForce RTX 3080"
+                        let mut parts = body.splitn(3, ' ');
+                        let (Some(sub_vendor), Some(sub_device)) = (parts.next(), parts.next()) else { continue };
+                        let name = parts.next().unwrap_or_default().trim().to_string();
+                        if let Some(device) = ids.vendors.get_mut(vendor_id).and_then(|v| v.devices.get_mut(device_id)) {
+                            device.subsystems.insert(
+                                format!("{}:{}", sub_vendor.to_lowercase(), sub_device.to_lowercase()),
+                                name,
+                            );
+                        }
+                    } else if let (Some(class_id), Some(subclass_id)) = (&current_class, &current_subclass) {
+                        // Programming interface line: "30  XHCI"
+                        let Some((id, name)) = split_id_name(body) else { continue };
+                        if let Some(subclass) = ids.classes.get_mut(class_id).and_then(|c| c.subclasses.get_mut(subclass_id)) {
+                            subclass.prog_ifs.insert(id, name);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        ids
+    }
+
+    /// Resolves a vendor name (e.g., "10de" -> "NVIDIA Corporation")
+    pub fn vendor_name(&self, vendor_id: &str) -> Option<&str> {
+        self.vendors.get(&vendor_id.to_lowercase()).map(|v| v.name.as_str())
+    }
+
+    /// Resolves a device name (e.g., "10de", "2206" -> "GA102 [GeForce RTX 3080]")
+    pub fn device_name(&self, vendor_id: &str, device_id: &str) -> Option<&str> {
+        self.vendors.get(&vendor_id.to_lowercase())?
+            .devices.get(&device_id.to_lowercase())
+            .map(|d| d.name.as_str())
+    }
+
+    /// Resolves a subsystem (board) name
+    pub fn subsystem_name(&self, vendor_id: &str, device_id: &str, sub_vendor_id: &str, sub_device_id: &str) -> Option<&str> {
+        self.vendors.get(&vendor_id.to_lowercase())?
+            .devices.get(&device_id.to_lowercase())?
+            .subsystems.get(&format!("{}:{}", sub_vendor_id.to_lowercase(), sub_device_id.to_lowercase()))
+            .map(|s| s.as_str())
+    }
+
+    /// Resolves the most specific class name for a 24-bit class code
+    /// (subclass name if known, otherwise the base class name)
+    pub fn class_name(&self, class_code: u32) -> Option<&str> {
+        let class = self.classes.get(&format!("{:02x}", (class_code >> 16) as u8))?;
+        match class.subclasses.get(&format!("{:02x}", (class_code >> 8) as u8)) {
+            Some(subclass) => Some(subclass.name.as_str()),
+            None => Some(class.name.as_str()),
+        }
+    }
+
+    /// Resolves the programming interface name for a 24-bit class code
+    /// (e.g., 0x0c0330 -> "XHCI")
+    pub fn prog_if_name(&self, class_code: u32) -> Option<&str> {
+        self.classes.get(&format!("{:02x}", (class_code >> 16) as u8))?
+            .subclasses.get(&format!("{:02x}", (class_code >> 8) as u8))?
+            .prog_ifs.get(&format!("{:02x}", class_code as u8))
+            .map(|p| p.as_str())
+    }
+
+    /// Fills in the human-readable names of a PCI device
+    pub fn annotate(&self, device: &mut PciDevice) {
+        if let Some(name) = self.vendor_name(&device.vendor_id) {
+            device.vendor_name = name.to_string();
+        }
+        if let Some(name) = self.device_name(&device.vendor_id, &device.device_id) {
+            device.device_name = name.to_string();
+        }
+        device.subsystem_name = self
+            .subsystem_name(&device.vendor_id, &device.device_id, &device.subsystem_vendor_id, &device.subsystem_device_id)
+            .map(|name| name.to_string());
+        if let Some(name) = self.class_name(device.class_code) {
+            // Only mention the programming interface when it carries information
+            device.class = match self.prog_if_name(device.class_code) {
+                Some(prog_if) if device.prog_if() != 0 => format!("{} ({})", name, prog_if),
+                _ => name.to_string(),
+            };
+        }
+    }
+}
+
+/// Splits "<hex id>  <name>" into its parts, lowercasing the ID
+fn split_id_name(body: &str) -> Option<(String, String)> {
+    let (id, name) = body.split_once(' ')?;
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some((id.to_lowercase(), name.trim().to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = "\
+# Comment lines and blank lines are skipped
+
+10de  NVIDIA Corporation
+\t2206  GA102 [GeForce RTX 3080]
+\t\t1043 87C0  TUF Gaming GeForce RTX 3080
+\t\t1462 3892  RTX 3080 Gaming X Trio
+\t1aef  GA102 High Definition Audio Controller
+1002  Advanced Micro Devices, Inc. [AMD/ATI]
+\t73BF  Navi 21 [Radeon RX 6800/6800 XT / 6900 XT]
+
+C 03  Display controller
+\t00  VGA compatible controller
+\t\t00  VGA controller
+\t02  3D controller
+C 0c  Serial bus controller
+\t03  USB controller
+\t\t30  XHCI
+";
+
+    #[test]
+    fn parse_resolves_vendors_devices_and_subsystems() {
+        let ids = PciIds::parse(SAMPLE);
+        assert_eq!(ids.vendor_name("10DE"), Some("NVIDIA Corporation"));
+        assert_eq!(ids.device_name("10de", "2206"), Some("GA102 [GeForce RTX 3080]"));
+        assert_eq!(ids.device_name("10de", "1aef"), Some("GA102 High Definition Audio Controller"));
+        assert_eq!(ids.subsystem_name("10de", "2206", "1043", "87c0"), Some("TUF Gaming GeForce RTX 3080"));
+        assert_eq!(ids.subsystem_name("10de", "2206", "1462", "3892"), Some("RTX 3080 Gaming X Trio"));
+        assert_eq!(ids.subsystem_name("10de", "1aef", "1043", "87c0"), None);
+        // IDs are stored lowercase whatever case the file uses
+        assert_eq!(ids.device_name("1002", "73bf"), Some("Navi 21 [Radeon RX 6800/6800 XT / 6900 XT]"));
+        assert_eq!(ids.device_name("1002", "2206"), None);
+        assert!(ids.source.is_none());
+    }
+
+    #[test]
+    fn parse_resolves_classes_subclasses_and_prog_ifs() {
+        let ids = PciIds::parse(SAMPLE);
+        assert_eq!(ids.class_name(0x030000), Some("VGA compatible controller"));
+        assert_eq!(ids.prog_if_name(0x030000), Some("VGA controller"));
+        assert_eq!(ids.class_name(0x030200), Some("3D controller"));
+        // Unknown subclass falls back to the base class
+        assert_eq!(ids.class_name(0x038000), Some("Display controller"));
+        assert_eq!(ids.class_name(0x0c0330), Some("USB controller"));
+        assert_eq!(ids.prog_if_name(0x0c0330), Some("XHCI"));
+        assert_eq!(ids.prog_if_name(0x0c0320), None);
+        assert_eq!(ids.class_name(0x040300), None);
+        // Class sections don't leak into the vendor table
+        assert!(!ids.vendors.contains_key("03"));
+    }
+
+    #[test]
+    fn bundled_copy_parses() {
+        let ids = PciIds::load_from(&Path::new(env!("CARGO_MANIFEST_DIR")).join("data/pci.ids")).unwrap();
+        assert_eq!(ids.device_name("10de", "2206"), Some("GA102 [GeForce RTX 3080]"));
+        assert_eq!(ids.prog_if_name(0x0c0330), Some("XHCI"));
+    }
+}
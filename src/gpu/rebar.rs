@@ -0,0 +1,200 @@
+// Resizable BAR detection for Exliar VFIO Automation Framework
+//
+// This module parses the PCIe Resizable BAR extended capability, infers
+// whether above-4G decoding is enabled, and exposes the kernel's
+// resourceN_resize knobs where available.
+
+use std::path::Path;
+
+use crate::gpu::config_space::{find_extended_capability, read_config_space, read_u32, EXT_CAP_ID_REBAR};
+use crate::gpu::pci::PciBar;
+
+/// BAR size above which guests commonly fail without extra configuration
+pub const REBAR_SAFE_SIZE: u64 = 256 * 1024 * 1024;
+
+/// Default 64-bit MMIO window of OVMF (32 GiB)
+pub const OVMF_DEFAULT_MMIO64: u64 = 32 * 1024 * 1024 * 1024;
+
+/// A single resizable BAR
+#[derive(Debug, Clone)]
+pub struct ResizableBar {
+    pub index: u8,                 // BAR number the entry controls
+    pub supported_sizes: Vec<u64>, // Supported sizes in bytes, ascending
+    pub current_size: u64,         // Currently programmed size in bytes
+    pub sysfs_resize: bool,        // Kernel exposes resourceN_resize for this BAR
+}
+
+/// Resizable BAR state of a device
+#[derive(Debug, Clone, Default)]
+pub struct RebarInfo {
+    pub bars: Vec<ResizableBar>,
+}
+
+impl RebarInfo {
+    /// Returns true if any resizable BAR is currently larger than the classic 256 MiB
+    pub fn is_enlarged(&self) -> bool {
+        self.bars.iter().any(|bar| bar.current_size > REBAR_SAFE_SIZE)
+    }
+}
+
+/// Reads the Resizable BAR capability of a device
+///
+/// Returns None if the capability is absent or the extended config space
+/// isn't readable (which requires root).
+pub fn read_rebar_info(sysfs_path: &Path) -> Option<RebarInfo> {
+    let config = read_config_space(sysfs_path).ok()?;
+    let mut info = parse_rebar_capability(&config)?;
+    detect_resize_knobs(&mut info, sysfs_path);
+    Some(info)
+}
+
+/// Marks which resizable BARs the kernel exposes a resourceN_resize knob for
+pub fn detect_resize_knobs(info: &mut RebarInfo, sysfs_path: &Path) {
+    // resourceN_resize appeared in Linux 6.1 for drivers that allow it
+    for bar in info.bars.iter_mut() {
+        bar.sysfs_resize = sysfs_path.join(format!("resource{}_resize", bar.index)).exists();
+    }
+}
+
+/// Parses the Resizable BAR extended capability from raw config space
+pub fn parse_rebar_capability(config: &[u8]) -> Option<RebarInfo> {
+    let offset = find_extended_capability(config, EXT_CAP_ID_REBAR)?;
+
+    // The first control register holds the number of resizable BARs
+    let first_control = read_u32(config, offset + 8)?;
+    let count = ((first_control >> 5) & 0x7).clamp(1, 6) as usize;
+
+    let mut bars = Vec::new();
+    for i in 0..count {
+        let capability = read_u32(config, offset + 4 + i * 8)?;
+        let control = read_u32(config, offset + 8 + i * 8)?;
+
+        // Capability bits 4..31 encode 1 MiB..128 TiB; control bits 16..31 extend beyond that
+        let mut supported_sizes: Vec<u64> = (4..32)
+            .filter(|bit| capability & (1 << bit) != 0)
+            .map(|bit| 1u64 << (bit - 4 + 20))
+            .collect();
+        supported_sizes.extend((16..32)
+            .filter(|bit| control & (1 << bit) != 0)
+            .map(|bit| 1u64 << (bit - 16 + 48)));
+
+        // Control bits 8..13 hold log2(size in MiB); encodings beyond 8 EiB are reserved
+        let Some(current_size) = 1u64.checked_shl(((control >> 8) & 0x3f) + 20) else { continue };
+
+        bars.push(ResizableBar {
+            index: (control & 0x7) as u8,
+            supported_sizes,
+            current_size,
+            sysfs_resize: false,
+        });
+    }
+
+    Some(RebarInfo { bars })
+}
+
+/// Infers whether above-4G decoding is enabled from BAR placement
+///
+/// Returns Some(true) if any memory BAR is mapped above 4 GiB, Some(false)
+/// if 64-bit BARs exist but all sit below 4 GiB, and None if unknown.
+pub fn infer_above_4g_decoding(bars: &[PciBar]) -> Option<bool> {
+    if bars.iter().any(|bar| !bar.is_io && bar.start >= 1 << 32) {
+        Some(true)
+    } else if bars.iter().any(|bar| bar.is_64bit) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Returns true if the current BAR configuration is known to break passthrough
+///
+/// Enlarged resizable BARs and apertures beyond the guest firmware's default
+/// MMIO window commonly leave the guest unable to map the device.
+pub fn bar_config_breaks_passthrough(rebar: Option<&RebarInfo>, bars: &[PciBar]) -> bool {
+    let total_prefetchable: u64 = bars.iter()
+        .filter(|bar| bar.is_prefetchable)
+        .map(|bar| bar.size)
+        .sum();
+
+    rebar.is_some_and(|r| r.is_enlarged()) || total_prefetchable > OVMF_DEFAULT_MMIO64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+    const GIB: u64 = 1024 * MIB;
+
+    /// Builds a 4 KiB config space with a ReBAR capability at 0x100 holding (capability, control) pairs
+    fn config_with_rebar(entries: &[(u32, u32)]) -> Vec<u8> {
+        let mut config = vec![0u8; 4096];
+        let header = EXT_CAP_ID_REBAR as u32 | 1 << 16;
+        config[0x100..0x104].copy_from_slice(&header.to_le_bytes());
+        for (i, (capability, control)) in entries.iter().enumerate() {
+            let offset = 0x104 + i * 8;
+            config[offset..offset + 4].copy_from_slice(&capability.to_le_bytes());
+            config[offset + 4..offset + 8].copy_from_slice(&control.to_le_bytes());
+        }
+        config
+    }
+
+    fn bar(index: u8, start: u64, size: u64, is_64bit: bool) -> PciBar {
+        PciBar { index, start, size, is_io: false, is_prefetchable: is_64bit, is_64bit }
+    }
+
+    #[test]
+    fn decodes_supported_and_current_sizes() {
+        // BAR 0: 256 MiB..8 GiB supported, 8 GiB programmed; BAR 2: 256 MiB only plus 256 TiB from control bit 16
+        let config = config_with_rebar(&[
+            (0x3f << 12, 2 << 5 | 13 << 8),
+            (1 << 12, 1 << 16 | 8 << 8 | 2),
+        ]);
+
+        let info = parse_rebar_capability(&config).unwrap();
+        assert_eq!(info.bars.len(), 2);
+        assert_eq!(info.bars[0].index, 0);
+        assert_eq!(info.bars[0].supported_sizes, vec![256 * MIB, 512 * MIB, GIB, 2 * GIB, 4 * GIB, 8 * GIB]);
+        assert_eq!(info.bars[0].current_size, 8 * GIB);
+        assert_eq!(info.bars[1].index, 2);
+        assert_eq!(info.bars[1].supported_sizes, vec![256 * MIB, 1 << 48]);
+        assert_eq!(info.bars[1].current_size, 256 * MIB);
+        assert!(info.is_enlarged());
+    }
+
+    #[test]
+    fn skips_reserved_size_encodings() {
+        let config = config_with_rebar(&[(1 << 12, 1 << 5 | 0x3f << 8)]);
+        assert!(parse_rebar_capability(&config).unwrap().bars.is_empty());
+    }
+
+    #[test]
+    fn missing_capability_or_standard_only_read_yields_none() {
+        assert!(parse_rebar_capability(&vec![0u8; 4096]).is_none());
+        assert!(parse_rebar_capability(&config_with_rebar(&[(1 << 12, 1 << 5 | 8 << 8)])[..0x100]).is_none());
+    }
+
+    #[test]
+    fn large_bars_break_passthrough() {
+        let classic = RebarInfo {
+            bars: vec![ResizableBar { index: 0, supported_sizes: vec![256 * MIB], current_size: 256 * MIB, sysfs_resize: false }],
+        };
+        let enlarged = RebarInfo {
+            bars: vec![ResizableBar { index: 0, supported_sizes: vec![16 * GIB], current_size: 16 * GIB, sysfs_resize: true }],
+        };
+        let small_bars = [bar(0, 0xe000_0000, 256 * MIB, true), bar(2, 0xf000_0000, 32 * MIB, true)];
+        let huge_bars = [bar(0, 0x40_0000_0000, 64 * GIB, true)];
+
+        assert!(!bar_config_breaks_passthrough(None, &small_bars));
+        assert!(!bar_config_breaks_passthrough(Some(&classic), &small_bars));
+        assert!(bar_config_breaks_passthrough(Some(&enlarged), &small_bars));
+        assert!(bar_config_breaks_passthrough(None, &huge_bars));
+    }
+
+    #[test]
+    fn infers_above_4g_decoding_from_bar_placement() {
+        assert_eq!(infer_above_4g_decoding(&[bar(0, 0x40_0000_0000, 16 * GIB, true)]), Some(true));
+        assert_eq!(infer_above_4g_decoding(&[bar(0, 0xe000_0000, 256 * MIB, true)]), Some(false));
+        assert_eq!(infer_above_4g_decoding(&[bar(0, 0xe000_0000, 256 * MIB, false)]), None);
+    }
+}
@@ -0,0 +1,186 @@
+// Reset method detection for Exliar VFIO Automation Framework
+//
+// This module reads which reset methods the kernel will use for a device
+// (the reset_method sysfs attribute, Linux 5.15+), triggers resets through
+// sysfs and checks that a device still responds sanely afterwards.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::gpu::detection::PciDevice;
+use crate::gpu::config_space::{read_config_space, read_u16, read_u8, standard_capabilities};
+
+/// A reset method as named by the kernel's reset_method attribute
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResetMethod {
+    DeviceSpecific, // Driver quirk or vendor-reset module
+    Acpi,           // ACPI _RST method
+    Flr,            // PCIe Function Level Reset
+    AfFlr,          // FLR via the Advanced Features capability
+    Pm,             // D3hot -> D0 power management reset
+    Bus,            // Secondary bus reset (resets every device on the bus)
+    CxlBus,         // CXL bus reset
+    Other(String),
+}
+
+impl ResetMethod {
+    /// Parses a kernel method name
+    pub fn parse(name: &str) -> Self {
+        match name {
+            "device_specific" => ResetMethod::DeviceSpecific,
+            "acpi" => ResetMethod::Acpi,
+            "flr" => ResetMethod::Flr,
+            "af_flr" => ResetMethod::AfFlr,
+            "pm" => ResetMethod::Pm,
+            "bus" => ResetMethod::Bus,
+            "cxl_bus" => ResetMethod::CxlBus,
+            other => ResetMethod::Other(other.to_string()),
+        }
+    }
+
+    /// Returns true if the method resets only this function
+    pub fn is_function_level(&self) -> bool {
+        matches!(self, ResetMethod::DeviceSpecific | ResetMethod::Acpi | ResetMethod::Flr | ResetMethod::AfFlr | ResetMethod::Pm)
+    }
+}
+
+impl fmt::Display for ResetMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ResetMethod::DeviceSpecific => write!(f, "device_specific"),
+            ResetMethod::Acpi => write!(f, "acpi"),
+            ResetMethod::Flr => write!(f, "flr"),
+            ResetMethod::AfFlr => write!(f, "af_flr"),
+            ResetMethod::Pm => write!(f, "pm"),
+            ResetMethod::Bus => write!(f, "bus"),
+            ResetMethod::CxlBus => write!(f, "cxl_bus"),
+            ResetMethod::Other(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Outcome of an on-demand reset test
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResetTestOutcome {
+    Passed,
+    Failed,
+    NotRun, // Dry run, or the device has no usable reset method
+}
+
+/// Result of an on-demand reset test
+#[derive(Debug, Clone)]
+pub struct ResetTestResult {
+    pub outcome: ResetTestOutcome,
+    pub method: Option<ResetMethod>, // Method the kernel tried first
+    pub detail: String,
+    pub tested_at: String, // Local time of the test
+}
+
+impl ResetTestResult {
+    /// Creates a result stamped with the current time
+    pub fn new(outcome: ResetTestOutcome, method: Option<ResetMethod>, detail: impl Into<String>) -> Self {
+        Self {
+            outcome,
+            method,
+            detail: detail.into(),
+            tested_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
+}
+
+/// A reset test that couldn't prepare or restore the device
+#[derive(Debug)]
+pub struct ResetTestError {
+    pub error: io::Error,
+    pub left_on_vfio: Vec<PciDevice>, // Functions moved to vfio-pci and not handed back, with their original driver
+}
+
+impl ResetTestError {
+    /// Creates an error for a test that left no function on vfio-pci
+    pub fn new(error: io::Error) -> Self {
+        Self { error, left_on_vfio: Vec::new() }
+    }
+}
+
+impl fmt::Display for ResetTestError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.error)
+    }
+}
+
+/// Reads the reset methods the kernel will try, in order
+///
+/// Returns None if the attribute doesn't exist (kernel older than 5.15) and
+/// an empty list if resets are disabled for the device.
+pub fn read_reset_methods(sysfs_path: &Path) -> Option<Vec<ResetMethod>> {
+    let content = fs::read_to_string(sysfs_path.join("reset_method")).ok()?;
+    Some(content.split_whitespace().map(ResetMethod::parse).collect())
+}
+
+/// Triggers a reset through the sysfs "reset" attribute
+///
+/// The kernel uses the first working method from reset_method. The
+/// attribute only exists if the device has at least one method.
+pub fn trigger_reset(sysfs_path: &Path, dry_run: bool) -> io::Result<()> {
+    let reset_path = sysfs_path.join("reset");
+    if dry_run {
+        println!("[DRY RUN] Would write 1 to {}", reset_path.display());
+        return Ok(());
+    }
+    if !reset_path.exists() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "Device has no reset attribute (no usable reset method)"));
+    }
+    let mut file = fs::OpenOptions::new().write(true).open(&reset_path)?;
+    write!(file, "1")?;
+    Ok(())
+}
+
+/// Time a device gets to answer config reads again after FLR or a PM reset
+pub const RESET_SETTLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Time a device gets after a secondary bus reset
+pub const RESET_SETTLE_TIMEOUT_BUS: Duration = Duration::from_secs(5);
+
+/// Reads config space until the device answers again after a reset
+///
+/// A device still in reset reads back vendor ID 0xffff. Returns the last
+/// read once the vendor ID is valid or the timeout has passed.
+pub fn wait_for_config_space(sysfs_path: &Path, timeout: Duration) -> io::Result<Vec<u8>> {
+    let start = Instant::now();
+    loop {
+        let config = read_config_space(sysfs_path)?;
+        if read_u16(&config, 0x00).is_some_and(|vendor| vendor != 0xffff) || start.elapsed() >= timeout {
+            return Ok(config);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Checks that config space read after a reset looks like the same, working device
+///
+/// `before` is the config space read just before the reset.
+pub fn check_config_after_reset(before: &[u8], after: &[u8]) -> Result<(), String> {
+    let (Some(vendor), Some(device)) = (read_u16(after, 0x00), read_u16(after, 0x02)) else {
+        return Err("Config space could not be read".to_string());
+    };
+    if vendor == 0xffff || after.iter().take(64).all(|b| *b == 0xff) {
+        return Err("Device no longer responds (config space reads back all 1s)".to_string());
+    }
+    if Some(vendor) != read_u16(before, 0x00) || Some(device) != read_u16(before, 0x02) {
+        return Err(format!("Device IDs changed to {:04x}:{:04x}", vendor, device));
+    }
+    // Class code and header type must be unchanged
+    if after.get(0x09..0x0c) != before.get(0x09..0x0c) || read_u8(after, 0x0e) != read_u8(before, 0x0e) {
+        return Err("Class code or header type changed".to_string());
+    }
+    // The capability list must still be walkable and unchanged
+    let caps_before: Vec<u8> = standard_capabilities(before).into_iter().map(|(id, _)| id).collect();
+    let caps_after: Vec<u8> = standard_capabilities(after).into_iter().map(|(id, _)| id).collect();
+    if caps_before != caps_after {
+        return Err(format!("Capability list changed ({:02x?} -> {:02x?})", caps_before, caps_after));
+    }
+    Ok(())
+}
@@ -0,0 +1,93 @@
+// Option ROM extraction for Exliar VFIO Automation Framework
+//
+// This module dumps a device's option ROM through the sysfs "rom" attribute,
+// strips dump headers (NVFlash) and stores the image in a managed location
+// for use as a VM's romfile.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Directory extracted ROMs are stored in
+pub const ROM_DIR: &str = "/var/lib/exliar-vfio/roms";
+
+/// Directory users can place ROM dumps in (e.g., from TechPowerUp or nvflash)
+pub const USER_ROM_DIR: &str = "/etc/exliar-vfio/roms";
+
+/// Returns the managed path of a device's ROM file
+pub fn managed_rom_path(vendor_id: &str, device_id: &str) -> PathBuf {
+    Path::new(ROM_DIR).join(format!("{}-{}.rom", vendor_id, device_id))
+}
+
+/// Returns where a user-supplied ROM for a device is looked up
+pub fn user_rom_path(vendor_id: &str, device_id: &str) -> PathBuf {
+    Path::new(USER_ROM_DIR).join(format!("{}-{}.rom", vendor_id, device_id))
+}
+
+/// Returns true if the data starts with the PCI expansion ROM signature (0x55 0xAA)
+pub fn has_rom_signature(data: &[u8]) -> bool {
+    data.starts_with(&[0x55, 0xaa])
+}
+
+/// Finds the start of the first ROM image: a 0x55AA signature whose PCI
+/// data structure pointer leads to a "PCIR" signature
+pub fn find_rom_image_start(data: &[u8]) -> Option<usize> {
+    (0..data.len().saturating_sub(0x1a))
+        .filter(|offset| data[*offset..].starts_with(&[0x55, 0xaa]))
+        .find(|offset| {
+            let pcir = u16::from_le_bytes([data[offset + 0x18], data[offset + 0x19]]) as usize;
+            data.get(offset + pcir..offset + pcir + 4) == Some(b"PCIR".as_slice())
+        })
+}
+
+/// Strips the header tools like NVFlash put in front of the ROM image
+///
+/// Returns the image and the number of bytes removed (0 if the data already
+/// starts with a valid image).
+pub fn strip_rom_header(data: &[u8]) -> Result<(Vec<u8>, usize), String> {
+    let start = find_rom_image_start(data)
+        .ok_or_else(|| "No PCI expansion ROM image (0x55AA signature with PCIR structure) found".to_string())?;
+    Ok((data[start..].to_vec(), start))
+}
+
+/// Reads a device's option ROM through sysfs
+///
+/// The rom attribute must be enabled before it can be read and is disabled
+/// again afterwards, even if reading fails. For the boot VGA device the
+/// kernel returns the shadow copy at 0xC0000 instead of the ROM BAR.
+pub fn read_sysfs_rom(sysfs_path: &Path) -> io::Result<Vec<u8>> {
+    let rom_path = sysfs_path.join("rom");
+    if !rom_path.exists() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "Device exposes no option ROM"));
+    }
+
+    set_rom_enabled(&rom_path, true)?;
+    let data = fs::read(&rom_path);
+    if let Err(e) = set_rom_enabled(&rom_path, false) {
+        println!("Warning: Failed to disable ROM access at {}: {}", rom_path.display(), e);
+    }
+
+    let data = data?;
+    if !has_rom_signature(&data) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "ROM image has no 0x55AA signature"));
+    }
+    Ok(data)
+}
+
+/// Writes a ROM image to the given path, creating the directory if needed
+pub fn save_rom(data: &[u8], path: &Path, dry_run: bool) -> io::Result<()> {
+    if dry_run {
+        println!("[DRY RUN] Would write {} byte ROM to {}", data.len(), path.display());
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, data)
+}
+
+/// Enables or disables reading of the sysfs rom attribute
+fn set_rom_enabled(rom_path: &Path, enabled: bool) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new().write(true).open(rom_path)?;
+    write!(file, "{}", if enabled { 1 } else { 0 })
+}
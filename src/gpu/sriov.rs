@@ -0,0 +1,127 @@
+// SR-IOV support for Exliar VFIO Automation Framework
+//
+// This module reads the SR-IOV state of physical functions (Intel Flex/Arc,
+// SR-IOV capable Intel iGPUs, AMD MxGPU), creates and destroys virtual
+// functions through sysfs and lists the resulting VF devices.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::gpu::pci::{read_hex_attr, read_pci_device, PciAddress, PciDevice};
+
+/// SR-IOV state of a physical function
+#[derive(Debug, Clone)]
+pub struct SriovInfo {
+    pub total_vfs: u16,                // Maximum number of VFs (sriov_totalvfs)
+    pub num_vfs: u16,                  // Currently enabled VFs (sriov_numvfs)
+    pub vf_device_id: Option<String>,  // Device ID the VFs will use (sriov_vf_device)
+    pub drivers_autoprobe: bool,       // New VFs are probed by host drivers automatically
+    pub vf_bdfs: Vec<String>,          // Addresses of the enabled VFs, in VF index order
+}
+
+/// Reads the SR-IOV state of a device
+///
+/// Returns None if the device has no SR-IOV capability (or it supports no VFs).
+pub fn read_sriov_info(sysfs_path: &Path) -> Option<SriovInfo> {
+    let total_vfs = read_u16_attr(sysfs_path, "sriov_totalvfs")?;
+    if total_vfs == 0 {
+        return None;
+    }
+
+    Some(SriovInfo {
+        total_vfs,
+        num_vfs: read_u16_attr(sysfs_path, "sriov_numvfs").unwrap_or(0),
+        vf_device_id: read_hex_attr(sysfs_path, "sriov_vf_device"),
+        drivers_autoprobe: read_u16_attr(sysfs_path, "sriov_drivers_autoprobe").is_none_or(|value| value != 0),
+        vf_bdfs: read_vf_links(sysfs_path).into_iter().map(|(_, bdf)| bdf).collect(),
+    })
+}
+
+/// Returns true if the device is a virtual function
+pub fn is_virtual_function(sysfs_path: &Path) -> bool {
+    sysfs_path.join("physfn").exists()
+}
+
+/// Returns the address of a VF's physical function
+pub fn physical_function(sysfs_path: &Path) -> Option<String> {
+    let target = fs::read_link(sysfs_path.join("physfn")).ok()?;
+    target.file_name().map(|name| name.to_string_lossy().to_string())
+}
+
+/// Lists the enabled VFs of a physical function as PCI devices
+pub fn list_virtual_functions(sysfs_path: &Path) -> Vec<PciDevice> {
+    read_vf_links(sysfs_path).iter()
+        .filter_map(|(index, bdf)| read_pci_device(&sysfs_path.join(format!("virtfn{}", index)), bdf))
+        .collect()
+}
+
+/// Sets the number of enabled VFs
+///
+/// The kernel only accepts a new non-zero count while no VFs exist, so any
+/// existing VFs are destroyed first. Creation requires a PF driver with
+/// SR-IOV support (e.g., xe, i915 with SR-IOV, amdgpu MxGPU or vfio-pci
+/// with enable_sriov=1).
+pub fn set_num_vfs(sysfs_path: &Path, num_vfs: u16, dry_run: bool) -> io::Result<()> {
+    let numvfs_path = sysfs_path.join("sriov_numvfs");
+    let info = read_sriov_info(sysfs_path).ok_or_else(|| {
+        io::Error::new(io::ErrorKind::Unsupported, "Device does not support SR-IOV")
+    })?;
+    if num_vfs > info.total_vfs {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Requested {} VFs, but the device supports at most {}", num_vfs, info.total_vfs),
+        ));
+    }
+
+    if dry_run {
+        if info.num_vfs != 0 && num_vfs != 0 {
+            println!("[DRY RUN] Would write 0 to {} (remove {} existing VFs)", numvfs_path.display(), info.num_vfs);
+        }
+        println!("[DRY RUN] Would write {} to {}", num_vfs, numvfs_path.display());
+        return Ok(());
+    }
+
+    if info.num_vfs == num_vfs {
+        return Ok(());
+    }
+    if info.num_vfs != 0 && num_vfs != 0 {
+        write_numvfs(&numvfs_path, 0)?;
+    }
+    write_numvfs(&numvfs_path, num_vfs).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(
+            e.kind(),
+            "The bound driver cannot create VFs (no sriov_configure support)",
+        ),
+        _ => e,
+    })
+}
+
+/// Writes a VF count to sriov_numvfs
+fn write_numvfs(numvfs_path: &Path, num_vfs: u16) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new().write(true).open(numvfs_path)?;
+    write!(file, "{}", num_vfs)
+}
+
+/// Reads the virtfnN links of a physical function as (N, address), sorted by N
+fn read_vf_links(sysfs_path: &Path) -> Vec<(u32, String)> {
+    let Ok(entries) = fs::read_dir(sysfs_path) else { return Vec::new() };
+
+    let mut vfs: Vec<(u32, String)> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let index = entry.file_name().to_string_lossy().strip_prefix("virtfn")?.parse().ok()?;
+            let target = fs::read_link(entry.path()).ok()?;
+            let bdf = target.file_name()?.to_string_lossy().to_string();
+            PciAddress::parse(&bdf).map(|_| (index, bdf))
+        })
+        .collect();
+
+    vfs.sort();
+    vfs
+}
+
+/// Reads a decimal sysfs attribute
+fn read_u16_attr(sysfs_path: &Path, attr: &str) -> Option<u16> {
+    fs::read_to_string(sysfs_path.join(attr)).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,344 @@
+// Option ROM (VBIOS) parsing for Exliar VFIO Automation Framework
+//
+// This module walks the chain of images in a PCI expansion ROM (legacy x86
+// VBIOS, UEFI GOP driver, Open Firmware), reads their PCI data structures
+// and checks whether a ROM suits a GPU and can boot under OVMF.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::gpu::config_space::{read_u16, read_u32};
+use crate::gpu::rom::{find_rom_image_start, has_rom_signature};
+use crate::gpu::GpuDevice;
+
+const ROM_BLOCK_SIZE: usize = 512;      // Image lengths are given in 512-byte units
+const PCIR_POINTER_OFFSET: usize = 0x18; // Pointer to the PCI data structure
+const EFI_SIGNATURE: u32 = 0x0ef1;
+const EFI_SUBSYSTEM_BOOT_SERVICE_DRIVER: u16 = 0x0b;
+const EFI_MACHINE_X64: u16 = 0x8664;
+const EFI_MACHINE_EBC: u16 = 0x0ebc;
+
+/// Code type of a ROM image, from its PCI data structure
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CodeType {
+    X86,          // Legacy (CSM) VBIOS
+    OpenFirmware,
+    PaRisc,
+    Efi,          // UEFI driver (GOP)
+    Other(u8),
+}
+
+impl CodeType {
+    fn from_byte(code_type: u8) -> Self {
+        match code_type {
+            0x00 => CodeType::X86,
+            0x01 => CodeType::OpenFirmware,
+            0x02 => CodeType::PaRisc,
+            0x03 => CodeType::Efi,
+            other => CodeType::Other(other),
+        }
+    }
+}
+
+impl fmt::Display for CodeType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CodeType::X86 => write!(f, "x86 (legacy)"),
+            CodeType::OpenFirmware => write!(f, "Open Firmware"),
+            CodeType::PaRisc => write!(f, "PA-RISC"),
+            CodeType::Efi => write!(f, "UEFI"),
+            CodeType::Other(code) => write!(f, "Unknown (0x{:02x})", code),
+        }
+    }
+}
+
+/// Header fields of a UEFI ROM image
+#[derive(Debug, Clone)]
+pub struct EfiImageInfo {
+    pub subsystem: u16,    // 0x0b = boot service driver (GOP)
+    pub machine_type: u16, // 0x8664 = x64, 0x0ebc = EFI byte code
+    pub compressed: bool,
+}
+
+impl EfiImageInfo {
+    /// Returns true if OVMF (x64) can load this driver
+    pub fn runs_on_ovmf(&self) -> bool {
+        self.subsystem == EFI_SUBSYSTEM_BOOT_SERVICE_DRIVER
+            && matches!(self.machine_type, EFI_MACHINE_X64 | EFI_MACHINE_EBC)
+    }
+}
+
+/// One image of the ROM chain
+#[derive(Debug, Clone)]
+pub struct RomImage {
+    pub offset: usize,         // Offset from the start of the (header-stripped) ROM
+    pub length: usize,         // Image length in bytes
+    pub vendor_id: u16,
+    pub device_id: u16,
+    pub device_list: Vec<u16>, // Additional device IDs (PCI 3.0 device list)
+    pub class_code: u32,
+    pub code_type: CodeType,
+    pub last_image: bool,
+    pub efi: Option<EfiImageInfo>,
+}
+
+impl RomImage {
+    /// Returns true if this image is for the given vendor/device ID
+    pub fn matches(&self, vendor_id: u16, device_id: u16) -> bool {
+        self.vendor_id == vendor_id && (self.device_id == device_id || self.device_list.contains(&device_id))
+    }
+}
+
+/// A parsed option ROM
+#[derive(Debug, Clone)]
+pub struct VbiosInfo {
+    pub images: Vec<RomImage>,
+    pub header_len: usize, // Bytes in front of the first image (e.g., an NVFlash header)
+    pub size: usize,       // Size of the ROM without that header
+}
+
+impl VbiosInfo {
+    /// Parses the image chain of a ROM
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let header_len = find_rom_image_start(data)
+            .ok_or_else(|| "No PCI expansion ROM image (0x55AA signature with PCIR structure) found".to_string())?;
+        let rom = &data[header_len..];
+
+        let mut images = Vec::new();
+        let mut offset = 0;
+        while let Some(image) = parse_image(rom, offset) {
+            let next = offset + image.length.max(ROM_BLOCK_SIZE);
+            let last_image = image.last_image;
+            images.push(image);
+            if last_image {
+                break;
+            }
+            // Some vendors (NVIDIA) put images whose length isn't in the PCIR
+            // structure; continue at the next block-aligned image signature
+            match find_rom_image_start(rom.get(next..).unwrap_or_default()) {
+                Some(skip) if skip % ROM_BLOCK_SIZE == 0 => offset = next + skip,
+                _ => break,
+            }
+        }
+
+        Ok(Self { images, header_len, size: rom.len() })
+    }
+
+    /// Returns true if the ROM contains a UEFI GOP driver OVMF can run
+    pub fn has_uefi_gop(&self) -> bool {
+        self.images.iter().any(|image| image.efi.as_ref().is_some_and(|efi| efi.runs_on_ovmf()))
+    }
+
+    /// Returns true if the ROM contains a legacy x86 VBIOS
+    pub fn has_legacy_image(&self) -> bool {
+        self.images.iter().any(|image| image.code_type == CodeType::X86)
+    }
+
+    /// Returns true if the first image is for the GPU's vendor/device ID
+    pub fn matches_device(&self, gpu: &GpuDevice) -> bool {
+        let (Ok(vendor_id), Ok(device_id)) = (
+            u16::from_str_radix(&gpu.vendor_id, 16),
+            u16::from_str_radix(&gpu.device_id, 16),
+        ) else {
+            return false;
+        };
+        self.images.first().is_some_and(|image| image.matches(vendor_id, device_id))
+    }
+
+    /// Returns the reasons the ROM won't boot the GPU in an OVMF (UEFI) guest
+    pub fn ovmf_issues(&self, gpu: &GpuDevice) -> Vec<String> {
+        let mut issues = Vec::new();
+        if !self.matches_device(gpu) {
+            let ids = self.images.first()
+                .map_or("none".to_string(), |image| format!("{:04x}:{:04x}", image.vendor_id, image.device_id));
+            issues.push(format!("ROM is for {}, not {}:{}", ids, gpu.vendor_id, gpu.device_id));
+        }
+        if !self.has_uefi_gop() {
+            issues.push(match self.images.iter().find_map(|image| image.efi.as_ref()) {
+                Some(efi) => format!("UEFI image is not an x64 GOP driver (subsystem 0x{:x}, machine 0x{:x})", efi.subsystem, efi.machine_type),
+                None => "ROM has no UEFI GOP image (legacy VBIOS only); OVMF will show no display".to_string(),
+            });
+        }
+        issues
+    }
+
+    /// Returns a one-line summary of the image chain
+    pub fn summary(&self) -> String {
+        let images: Vec<String> = self.images.iter()
+            .map(|image| format!("{} {:04x}:{:04x} ({} KB)", image.code_type, image.vendor_id, image.device_id, image.length / 1024))
+            .collect();
+        format!("{} KB ROM: {}", self.size / 1024, images.join(", "))
+    }
+}
+
+/// Reads and parses a ROM file
+pub fn read_vbios_file(path: &Path) -> Result<VbiosInfo, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    VbiosInfo::parse(&data)
+}
+
+/// Parses the image starting at `offset`
+fn parse_image(rom: &[u8], offset: usize) -> Option<RomImage> {
+    let image = rom.get(offset..)?;
+    if !has_rom_signature(image) {
+        return None;
+    }
+    let pcir = read_u16(image, PCIR_POINTER_OFFSET)? as usize;
+    if image.get(pcir..pcir + 4)? != b"PCIR" {
+        return None;
+    }
+
+    let revision = *image.get(pcir + 0x0c)?;
+    let device_list_pointer = read_u16(image, pcir + 0x08)? as usize;
+    let class_bytes = image.get(pcir + 0x0d..pcir + 0x10)?;
+    let code_type = CodeType::from_byte(*image.get(pcir + 0x14)?);
+
+    Some(RomImage {
+        offset,
+        length: read_u16(image, pcir + 0x10)? as usize * ROM_BLOCK_SIZE,
+        vendor_id: read_u16(image, pcir + 0x04)?,
+        device_id: read_u16(image, pcir + 0x06)?,
+        device_list: if revision >= 3 && device_list_pointer != 0 {
+            read_device_list(image, pcir + device_list_pointer)
+        } else {
+            Vec::new()
+        },
+        class_code: u32::from_le_bytes([class_bytes[0], class_bytes[1], class_bytes[2], 0]),
+        code_type,
+        last_image: image.get(pcir + 0x15)? & 0x80 != 0,
+        efi: if code_type == CodeType::Efi { parse_efi_header(image) } else { None },
+    })
+}
+
+/// Reads the zero-terminated device ID list of a PCI 3.0 data structure
+fn read_device_list(image: &[u8], offset: usize) -> Vec<u16> {
+    (0..)
+        .map_while(|index| read_u16(image, offset + index * 2))
+        .take_while(|id| *id != 0)
+        .take(64)
+        .collect()
+}
+
+/// Reads the EFI-specific fields of a UEFI ROM image header
+fn parse_efi_header(image: &[u8]) -> Option<EfiImageInfo> {
+    if read_u32(image, 0x04)? != EFI_SIGNATURE {
+        return None;
+    }
+    Some(EfiImageInfo {
+        subsystem: read_u16(image, 0x08)?,
+        machine_type: read_u16(image, 0x0a)?,
+        compressed: read_u16(image, 0x0c)? == 1,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PCIR_OFFSET: usize = 0x40;
+
+    /// Builds a ROM image of `blocks` 512-byte blocks with a PCI 3.0 data structure
+    fn image(vendor_id: u16, device_id: u16, code_type: u8, blocks: u16, last: bool) -> Vec<u8> {
+        let mut image = vec![0u8; blocks as usize * ROM_BLOCK_SIZE];
+        image[..2].copy_from_slice(&[0x55, 0xaa]);
+        image[PCIR_POINTER_OFFSET..PCIR_POINTER_OFFSET + 2].copy_from_slice(&(PCIR_OFFSET as u16).to_le_bytes());
+
+        let pcir = &mut image[PCIR_OFFSET..PCIR_OFFSET + 0x18];
+        pcir[..4].copy_from_slice(b"PCIR");
+        pcir[0x04..0x06].copy_from_slice(&vendor_id.to_le_bytes());
+        pcir[0x06..0x08].copy_from_slice(&device_id.to_le_bytes());
+        pcir[0x0a..0x0c].copy_from_slice(&0x18u16.to_le_bytes());
+        pcir[0x0c] = 3;
+        pcir[0x0f] = 0x03; // Display controller
+        pcir[0x10..0x12].copy_from_slice(&blocks.to_le_bytes());
+        pcir[0x14] = code_type;
+        pcir[0x15] = if last { 0x80 } else { 0 };
+        image
+    }
+
+    /// Builds a UEFI image for the given machine type
+    fn efi_image(vendor_id: u16, device_id: u16, machine_type: u16, blocks: u16) -> Vec<u8> {
+        let mut image = image(vendor_id, device_id, 0x03, blocks, true);
+        image[0x04..0x08].copy_from_slice(&EFI_SIGNATURE.to_le_bytes());
+        image[0x08..0x0a].copy_from_slice(&EFI_SUBSYSTEM_BOOT_SERVICE_DRIVER.to_le_bytes());
+        image[0x0a..0x0c].copy_from_slice(&machine_type.to_le_bytes());
+        image[0x0c..0x0e].copy_from_slice(&1u16.to_le_bytes());
+        image
+    }
+
+    #[test]
+    fn parses_legacy_only_rom() {
+        let rom = image(0x1002, 0x73bf, 0x00, 2, true);
+
+        let info = VbiosInfo::parse(&rom).unwrap();
+        assert_eq!(info.images.len(), 1);
+        assert_eq!((info.header_len, info.size), (0, 1024));
+        assert_eq!(info.images[0].code_type, CodeType::X86);
+        assert_eq!(info.images[0].class_code, 0x030000);
+        assert!(info.images[0].matches(0x1002, 0x73bf));
+        assert!(info.has_legacy_image());
+        assert!(!info.has_uefi_gop());
+    }
+
+    #[test]
+    fn parses_legacy_and_efi_x64_rom() {
+        let mut rom = image(0x1002, 0x73bf, 0x00, 2, false);
+        rom.extend(efi_image(0x1002, 0x73bf, EFI_MACHINE_X64, 4));
+
+        let info = VbiosInfo::parse(&rom).unwrap();
+        assert_eq!(info.images.len(), 2);
+        assert_eq!(info.images[1].offset, 1024);
+        assert_eq!(info.images[1].length, 2048);
+        let efi = info.images[1].efi.as_ref().unwrap();
+        assert_eq!(efi.machine_type, EFI_MACHINE_X64);
+        assert!(efi.compressed);
+        assert!(info.has_legacy_image());
+        assert!(info.has_uefi_gop());
+    }
+
+    #[test]
+    fn rejects_efi_image_ovmf_cannot_run() {
+        let mut rom = image(0x10de, 0x2684, 0x00, 2, false);
+        rom.extend(efi_image(0x10de, 0x2684, 0xaa64, 2));
+
+        let info = VbiosInfo::parse(&rom).unwrap();
+        assert_eq!(info.images.len(), 2);
+        assert!(!info.has_uefi_gop());
+    }
+
+    #[test]
+    fn skips_nvflash_header() {
+        let mut rom = b"NVGI".to_vec();
+        rom.resize(0x400, 0xff);
+        rom.extend(image(0x10de, 0x2684, 0x00, 2, false));
+        rom.extend(efi_image(0x10de, 0x2684, EFI_MACHINE_X64, 2));
+
+        let info = VbiosInfo::parse(&rom).unwrap();
+        assert_eq!(info.header_len, 0x400);
+        assert_eq!(info.size, 2048);
+        assert_eq!(info.images.len(), 2);
+        assert_eq!(info.images[0].offset, 0);
+        assert_eq!(info.images[1].offset, 1024);
+        assert!(info.images[0].matches(0x10de, 0x2684));
+        assert!(info.has_uefi_gop());
+    }
+
+    #[test]
+    fn truncated_pcir_pointer_ends_the_chain() {
+        // A pointer past the end leaves no valid image at all
+        let mut rom = image(0x1002, 0x73bf, 0x00, 1, true);
+        rom[PCIR_POINTER_OFFSET..PCIR_POINTER_OFFSET + 2].copy_from_slice(&0xfff0u16.to_le_bytes());
+        assert!(VbiosInfo::parse(&rom).is_err());
+
+        // In a later image it stops the walk after the images parsed so far
+        let mut rom = image(0x1002, 0x73bf, 0x00, 2, false);
+        let mut efi = efi_image(0x1002, 0x73bf, EFI_MACHINE_X64, 1);
+        efi[PCIR_POINTER_OFFSET..PCIR_POINTER_OFFSET + 2].copy_from_slice(&0x01feu16.to_le_bytes());
+        rom.extend(efi);
+
+        let info = VbiosInfo::parse(&rom).unwrap();
+        assert_eq!(info.images.len(), 1);
+        assert!(!info.has_uefi_gop());
+    }
+}
@@ -0,0 +1,173 @@
+// vendor-reset integration for Exliar VFIO Automation Framework
+//
+// AMD GPUs with the reset bug (Polaris, Vega, Navi 1x) can't be reset by
+// the kernel between VM runs. The out-of-tree vendor-reset module (usually
+// installed through DKMS) adds device-specific resets for them, which the
+// kernel only uses once reset_method is set to device_specific.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Name of the kernel module
+pub const VENDOR_RESET_MODULE: &str = "vendor_reset";
+
+/// modules-load.d file that loads vendor-reset at boot
+pub const VENDOR_RESET_MODULES_LOAD_PATH: &str = "/etc/modules-load.d/vendor-reset.conf";
+
+/// udev rule that selects the device-specific reset for supported GPUs
+pub const VENDOR_RESET_UDEV_RULE_PATH: &str = "/etc/udev/rules.d/99-exliar-vendor-reset.rules";
+
+/// Directory DKMS keeps registered module sources in
+const DKMS_MODULE_DIR: &str = "/var/lib/dkms/vendor-reset";
+
+/// AMD device IDs vendor-reset implements a reset for, by family
+/// (from the module's device database)
+const SUPPORTED_DEVICES: &[(&str, &[&str])] = &[
+    ("Polaris 10", &["67c0", "67c1", "67c2", "67c4", "67c7", "67c8", "67c9", "67ca", "67cc", "67cf", "67d0", "67df", "6fdf"]),
+    ("Polaris 11", &["67e0", "67e1", "67e3", "67e7", "67e8", "67e9", "67eb", "67ef", "67ff"]),
+    ("Polaris 12", &["6980", "6981", "6985", "6986", "6987", "6995", "6997", "699f"]),
+    ("Vega 10", &["6860", "6861", "6862", "6863", "6864", "6867", "6868", "6869", "686a", "686b", "686c", "686d", "686e", "686f", "687f"]),
+    ("Vega 20", &["66a0", "66a1", "66a2", "66a3", "66a4", "66a7", "66af"]),
+    ("Navi 10", &["7310", "7312", "7318", "7319", "731a", "731b", "731e", "731f"]),
+    ("Navi 12", &["7360", "7362"]),
+    ("Navi 14", &["7340", "7341", "7347", "734f"]),
+];
+
+/// Installation state of the vendor-reset module
+#[derive(Debug, Clone, Default)]
+pub struct VendorResetStatus {
+    pub installed: bool,               // modinfo finds the module for the running kernel
+    pub loaded: bool,                  // /sys/module/vendor_reset exists
+    pub dkms: bool,                    // Registered with DKMS (rebuilt on kernel updates)
+    pub module_path: Option<PathBuf>,
+    pub version: Option<String>,
+    pub signer: Option<String>,        // Signing key name; None if the module is unsigned
+}
+
+impl VendorResetStatus {
+    /// Detects whether vendor-reset is installed, loaded and signed
+    pub fn detect() -> Self {
+        let modinfo = |field: &str| Command::new("modinfo")
+            .args(["-F", field, VENDOR_RESET_MODULE])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|value| !value.is_empty());
+
+        let module_path = modinfo("filename").map(PathBuf::from);
+        Self {
+            installed: module_path.is_some(),
+            loaded: Path::new("/sys/module").join(VENDOR_RESET_MODULE).exists(),
+            dkms: Path::new(DKMS_MODULE_DIR).exists(),
+            version: modinfo("version"),
+            signer: module_path.as_ref().and_then(|_| modinfo("signer")),
+            module_path,
+        }
+    }
+
+    /// Checks that the module can be loaded with the given Secure Boot state
+    ///
+    /// With Secure Boot on, the kernel refuses unsigned modules, so DKMS
+    /// must have signed the module with an enrolled MOK.
+    pub fn check_signature(&self, secure_boot_enabled: Option<bool>) -> Result<(), String> {
+        if secure_boot_enabled != Some(true) || self.loaded || self.signer.is_some() {
+            return Ok(());
+        }
+        Err(format!(
+            "{} is not signed and Secure Boot is enabled, so the kernel will refuse to load it. \
+             Sign it with an enrolled MOK (e.g., DKMS's /var/lib/dkms/mok.key after 'mokutil --import /var/lib/dkms/mok.pub') and rebuild",
+            self.module_path.as_ref().map_or(VENDOR_RESET_MODULE.to_string(), |path| path.display().to_string()),
+        ))
+    }
+
+    /// Returns a one-line description of the state
+    pub fn summary(&self) -> String {
+        if !self.installed {
+            return "vendor-reset not installed".to_string();
+        }
+        format!(
+            "vendor-reset {}{}, {}{}",
+            self.version.as_deref().unwrap_or("(unknown version)"),
+            if self.dkms { " (DKMS)" } else { "" },
+            if self.loaded { "loaded" } else { "not loaded" },
+            match &self.signer {
+                Some(signer) => format!(", signed by {}", signer),
+                None => ", unsigned".to_string(),
+            },
+        )
+    }
+}
+
+/// Returns the GPU family if vendor-reset supports the AMD device ID
+pub fn vendor_reset_family(device_id: &str) -> Option<&'static str> {
+    let device_id = device_id.to_lowercase();
+    SUPPORTED_DEVICES.iter()
+        .find(|(_, ids)| ids.contains(&device_id.as_str()))
+        .map(|(family, _)| *family)
+}
+
+/// Returns true if vendor-reset implements a reset for the AMD device ID
+pub fn is_vendor_reset_supported(device_id: &str) -> bool {
+    vendor_reset_family(device_id).is_some()
+}
+
+/// Loads the module now with modprobe
+pub fn load_vendor_reset(dry_run: bool) -> io::Result<()> {
+    if dry_run {
+        println!("[DRY RUN] Would run: modprobe {}", VENDOR_RESET_MODULE);
+        return Ok(());
+    }
+    let status = Command::new("modprobe").arg(VENDOR_RESET_MODULE).status()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("modprobe {} failed with status: {}", VENDOR_RESET_MODULE, status)));
+    }
+    Ok(())
+}
+
+/// Builds the udev rule that sets reset_method to device_specific for the given AMD device IDs
+pub fn vendor_reset_udev_rule(device_ids: &[String]) -> String {
+    let mut rule = String::from("# Generated by Exliar VFIO: use vendor-reset for AMD GPUs with the reset bug\n");
+    for device_id in device_ids {
+        rule.push_str(&format!(
+            "ACTION==\"add\", SUBSYSTEM==\"pci\", ATTR{{vendor}}==\"0x1002\", ATTR{{device}}==\"0x{}\", ATTR{{reset_method}}=\"device_specific\"\n",
+            device_id.to_lowercase(),
+        ));
+    }
+    rule
+}
+
+/// Reloads the udev rules so a new rule applies to hotplugged devices
+pub fn reload_udev_rules(dry_run: bool) -> io::Result<()> {
+    if dry_run {
+        println!("[DRY RUN] Would run: udevadm control --reload-rules");
+        return Ok(());
+    }
+    let status = Command::new("udevadm").args(["control", "--reload-rules"]).status()?;
+    if !status.success() {
+        println!("Warning: udevadm control --reload-rules failed with status: {}", status);
+    }
+    Ok(())
+}
+
+/// Selects the device-specific reset for a device through sysfs
+///
+/// Returns the previous methods (space-separated) so the change can be
+/// undone. The kernel rejects device_specific unless a quirk or vendor-reset
+/// provides one for the device.
+pub fn set_device_specific_reset(sysfs_path: &Path, dry_run: bool) -> io::Result<String> {
+    let reset_method_path = sysfs_path.join("reset_method");
+    let original = fs::read_to_string(&reset_method_path)
+        .map_err(|e| io::Error::new(e.kind(), format!("Cannot read {} (needs Linux 5.15+): {}", reset_method_path.display(), e)))?
+        .trim()
+        .to_string();
+    if dry_run {
+        println!("[DRY RUN] Would write device_specific to {}", reset_method_path.display());
+        return Ok(original);
+    }
+    let mut file = fs::OpenOptions::new().write(true).open(&reset_method_path)?;
+    write!(file, "device_specific")?;
+    Ok(original)
+}
@@ -1,5 +1,6 @@
 use exliar_vfio::core::system::SystemInfo;
 use exliar_vfio::gpu::detection::detect_gpus;
+use exliar_vfio::gpu::iommu::IommuPeerKind;
 use exliar_vfio::gpu::vendor::GpuVendorHandler;
 use exliar_vfio::gpu::vendor::amd::AmdGpuHandler;
 use exliar_vfio::gpu::vendor::nvidia::NvidiaGpuHandler;
@@ -60,14 +61,103 @@ fn run_cli_mode() {
         println!("  Device ID: {}", gpu.device_id);
         println!("  Driver: {}", gpu.driver.as_deref().unwrap_or("None"));
         println!("  Integrated: {}", if gpu.is_integrated { "Yes" } else { "No" });
+        match &gpu.profile {
+            Some(profile) => println!("  Family: {}", profile.classification()),
+            None => println!("  Family: Unknown (not in GPU database)"),
+        }
+        match gpu.vram_size {
+            Some(mb) => println!("  VRAM: {} MB", mb),
+            None => println!("  VRAM: Unknown"),
+        }
+        for bar in &gpu.bars {
+            let marker = if gpu.vram_aperture().is_some_and(|a| a.index == bar.index) { " <- VRAM aperture" } else { "" };
+            println!("  {}{}", bar, marker);
+        }
+        for function in &gpu.companion_functions {
+            println!("  Companion Function: {} {} [{}]", function.bdf, function.device_name, function.id_pair());
+        }
+        for function in &gpu.shared_slot_functions {
+            println!("  Shares Slot (not passed through): {} {} [{}]", function.bdf, function.device_name, function.id_pair());
+        }
+        if let Some(sriov) = &gpu.sriov {
+            println!("  SR-IOV: {} of {} VFs enabled", sriov.num_vfs, sriov.total_vfs);
+            for vf in &gpu.virtual_functions {
+                println!("    - VF {} [{}] driver: {}", vf.bdf, vf.id_pair(), vf.driver.as_deref().unwrap_or("None"));
+            }
+        }
+        for mdev_type in &gpu.mdev_types {
+            println!("  Mdev Type: {} ({} free{})", mdev_type.id, mdev_type.available_instances,
+                     mdev_type.memory_mb.map_or(String::new(), |mb| format!(", {} MB", mb)));
+        }
+        for mdev in &gpu.mdevs {
+            println!("  Mdev Device: {} ({})", mdev.uuid, mdev.type_id);
+        }
+        match gpu.iommu_group {
+            Some(group) => {
+                println!("  IOMMU Group: {}{}", group, if gpu.is_iommu_isolated() { " (isolated)" } else { "" });
+                for peer in &gpu.iommu_group_peers {
+                    let note = match peer.kind {
+                        IommuPeerKind::Bridge => "bridge, can stay on host",
+                        IommuPeerKind::CompanionFunction => "same slot, passed through with GPU",
+                        IommuPeerKind::Endpoint => "must also be bound to vfio-pci",
+                    };
+                    println!("    - {} {} ({})", peer.bdf, peer.description, note);
+                }
+            }
+            None => println!("  IOMMU Group: None (IOMMU disabled?)"),
+        }
+
+        println!("  Boot VGA: {}", if gpu.boot_vga { "Yes" } else { "No" });
+        if let Some(hybrid) = &gpu.hybrid {
+            println!("  Hybrid Graphics: {}", hybrid.summary());
+        }
+        for card in &gpu.drm_cards {
+            for connector in &card.connectors {
+                println!("  Display: {}-{} {}{}", card.name, connector.name, connector.status,
+                         if connector.enabled { " (enabled)" } else { "" });
+            }
+        }
+
+        if let Some(link) = &gpu.pcie_link {
+            println!("  PCIe Link: {}{}", link, if link.is_degraded() { " (degraded)" } else { "" });
+        }
+        for hop in &gpu.upstream_links {
+            println!("    - {} {}: {}", hop.bdf, hop.role, hop.link.as_ref().map_or("no link info".to_string(), |link| link.to_string()));
+        }
+        println!("  NUMA: {}", gpu.numa.summary());
+        println!("  ACS Isolation: {}", gpu.acs.source);
+        for bridge in &gpu.acs.path {
+            let state = if !bridge.readable {
+                "unreadable".to_string()
+            } else if bridge.provides_isolation() {
+                "ok".to_string()
+            } else {
+                format!("missing {}", bridge.missing_flags().join("/"))
+            };
+            println!("    - {} {}: ACS {}", bridge.bdf, bridge.role(), state);
+        }
+
+        match &gpu.pci_capabilities {
+            Some(caps) => println!("  PCI Capabilities: {}", caps.names().join(", ")),
+            None => println!("  PCI Capabilities: Unreadable (run as root)"),
+        }
 
         // Print capabilities
         println!("  Capabilities:");
         println!("    Reset Support: {}", if gpu.capabilities.supports_reset { "Yes" } else { "No" });
-        println!("    Reset Bug: {}", if gpu.capabilities.has_reset_bug { "Yes (affected)" } else { "No" });
+        println!("    Reset Bug: {}", match gpu.capabilities.has_reset_bug {
+            Some(true) => "Yes (affected)",
+            Some(false) => "No",
+            None => "Unknown (run a reset test)",
+        });
+        if !gpu.capabilities.reset_methods.is_empty() {
+            let methods: Vec<String> = gpu.capabilities.reset_methods.iter().map(|m| m.to_string()).collect();
+            println!("    Reset Methods: {}", methods.join(", "));
+        }
         println!("    Code 43 Workaround Needed: {}",
                 if gpu.capabilities.needs_code_43_workaround { "Yes" } else { "No" });
         println!("    GVT-g Support: {}", if gpu.capabilities.supports_gvt { "Yes" } else { "No" });
+        println!("    ACS Override Required: {}", if gpu.capabilities.requires_acs_override { "Yes" } else { "No" });
 
         // Find appropriate handler
         let handler = handlers.iter().find(|h| h.supports_device(gpu));
@@ -84,7 +174,7 @@ fn run_cli_mode() {
 
             // Get quirks
             println!("\n  Required quirks:");
-            match handler.apply_quirks(gpu) {
+            match handler.apply_quirks(gpu, &system_info.kernel_version) {
                 Ok(quirks) => {
                     if quirks.is_empty() {
                         println!("    None required");
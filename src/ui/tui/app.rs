@@ -30,6 +30,8 @@ pub(super) fn detect_system_info(app: &mut AppState) { // Make function visible
     let kernel_version = sys_info.kernel_version.full_version.clone();
     let bootloader_info = format!("{:?}", sys_info.bootloader);
     let virtualization_enabled = sys_info.virtualization_enabled;
+    let iommu_summary = sys_info.iommu.summary();
+    let iommu_issues = sys_info.iommu.issues(&sys_info.cpu_vendor);
 
     // Store the system info in the app state
     app.system_info = Some(sys_info); // sys_info is moved here
@@ -54,6 +56,15 @@ pub(super) fn detect_system_info(app: &mut AppState) { // Make function visible
         } else {
             app.add_log("Warning: Virtualization is disabled", LogLevel::Warning);
         }
+
+        // Log IOMMU status; the CPU flags don't say whether the kernel enabled it
+        if iommu_issues.is_empty() {
+            app.add_log(&format!("IOMMU: {}", iommu_summary), LogLevel::Success);
+        } else {
+            for issue in iommu_issues {
+                app.add_log(&format!("Warning: {}", issue), LogLevel::Warning);
+            }
+        }
     }
 }
 
@@ -74,12 +85,30 @@ pub(super) fn detect_and_log_gpus(app: &mut AppState) { // Make function visible
             app.add_log(&format!("GPU {}: {} ({})", i+1, gpu.model_name(), gpu.vendor()), LogLevel::Info);
 
             // Check for potential issues
-            if gpu.capabilities.has_reset_bug {
-                app.add_log(&format!("Warning: GPU {} is affected by reset bug", i+1), LogLevel::Warning);
+            match gpu.capabilities.has_reset_bug {
+                Some(true) => app.add_log(&format!("Warning: GPU {} is affected by reset bug", i+1), LogLevel::Warning),
+                None => app.add_log(&format!("Note: GPU {} may have the reset bug; run a reset test to find out", i+1), LogLevel::Info),
+                Some(false) => {}
             }
             if gpu.capabilities.needs_code_43_workaround {
                 app.add_log(&format!("Note: GPU {} needs Code 43 workaround", i+1), LogLevel::Info);
             }
+            match gpu.iommu_group {
+                Some(_) if !gpu.is_iommu_isolated() => {
+                    app.add_log(&format!("Warning: GPU {} shares its IOMMU group with {} other endpoint(s)",
+                                         i+1, gpu.endpoint_peers().count()), LogLevel::Warning);
+                }
+                None => {
+                    app.add_log(&format!("Warning: GPU {} has no IOMMU group (IOMMU may be disabled)", i+1), LogLevel::Warning);
+                }
+                _ => {}
+            }
+            if gpu.is_driving_display() {
+                app.add_log(&format!("Note: GPU {} is driving {} host display(s)", i+1, gpu.connected_displays().len()), LogLevel::Info);
+            }
+            if gpu.capabilities.requires_acs_override {
+                app.add_log(&format!("Warning: GPU {} isolation needs the ACS override ({})", i+1, gpu.acs.source), LogLevel::Warning);
+            }
         }
     }
 
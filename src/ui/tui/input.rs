@@ -5,6 +5,19 @@ use super::state::{AppState, LogLevel};
 use super::app::{detect_system_info, detect_and_log_gpus}; // Import the functions
 // Import Change enum for state tracking
 use crate::core::state::Change; 
+use crate::core::system::IommuStatus;
+use crate::gpu::GpuVendor;
+use crate::gpu::display::is_sole_host_display;
+use crate::gpu::reset::ResetTestOutcome;
+use crate::gpu::sriov::read_sriov_info;
+use crate::gpu::mdev::mdevctl_definition_path;
+use crate::gpu::rom::user_rom_path;
+use crate::gpu::hybrid::generate_hybrid_kit;
+use crate::gpu::igd::{analyze_igd, extract_igd_firmware};
+use crate::gpu::pci::SYSFS_ROOT;
+use crate::gpu::vendor::intel::{create_gvtg_device, igd_assignment_mode, remove_gvtg_device, IgdAssignmentMode};
+use crate::gpu::vendor::nvidia::extract_vbios;
+use crate::gpu::vendor_reset::is_vendor_reset_supported;
 
 /// Handles key events for the application
 pub fn handle_key_event(app: &mut AppState, key_code: KeyCode, modifiers: KeyModifiers) {
@@ -59,26 +72,343 @@ pub fn handle_key_event(app: &mut AppState, key_code: KeyCode, modifiers: KeyMod
                 if let Some(gpus) = &app.gpus {
                     if app.selected_gpu_index < gpus.len() {
                         let selected_gpu = &gpus[app.selected_gpu_index];
+                        let sole_display = is_sole_host_display(selected_gpu, gpus);
                         app.selected_passthrough_gpu_index = Some(app.selected_gpu_index);
+                        app.display_guard_overridden = false; // A new selection needs a new confirmation
                         app.add_log(&format!("Selected GPU {} ({}) for passthrough.",
                                              selected_gpu.bdf(), selected_gpu.model_name()),
                                     LogLevel::Success);
+                        if sole_display {
+                            app.add_log("Warning: This GPU is driving the host's only display(s)! Passing it through will leave the host headless.",
+                                        LogLevel::Warning);
+                        }
                         app.show_gpu_details = false; // Go back to dashboard after selection
                     }
                 }
             }
         }
+        KeyCode::Char('T') => { // Confirm a reset test of the selected GPU
+            if !app.show_gpu_details {
+                return;
+            }
+            let Some(gpu) = app.gpus.as_ref().and_then(|g| g.get(app.selected_gpu_index)).cloned() else { return };
+            app.reset_test_confirmed = Some(gpu.bdf.clone());
+            app.add_log(&format!("Reset test of {} CONFIRMED. Press 't' to run it.", gpu.bdf), LogLevel::Warning);
+        }
+        KeyCode::Char('t') => { // Test whether the selected GPU survives a reset
+            if !app.show_gpu_details {
+                return;
+            }
+            let Some(gpu) = app.gpus.as_ref().and_then(|g| g.get(app.selected_gpu_index)).cloned() else { return };
+            // The test unbinds the host driver, which would blank any attached display
+            if gpu.is_driving_display() {
+                app.add_log(&format!("Refusing to reset-test {}: it is driving {} display(s). Disconnect them first.",
+                                     gpu.bdf, gpu.connected_displays().len()), LogLevel::Error);
+                return;
+            }
+            // Unbinding a live driver and resetting the device needs an explicit go-ahead
+            if app.reset_test_confirmed.as_deref() != Some(gpu.bdf.as_str()) {
+                app.add_log(&format!("The reset test unbinds {} from {} and resets it; anything using the GPU will lose it.",
+                                     gpu.bdf, gpu.driver.as_deref().unwrap_or("its driver")), LogLevel::Warning);
+                app.add_log("Press 'T' to confirm, then 't' to run the test.", LogLevel::Warning);
+                return;
+            }
+            app.reset_test_confirmed = None; // Each test needs its own confirmation
+            app.add_log(&format!("Running reset test on {} (temporarily binding to vfio-pci)...", gpu.bdf), LogLevel::Info);
+            let Some(test_result) = app.vfio_manager.as_ref().map(|manager| manager.reset_test(&gpu, false)) else {
+                app.add_log("VFIO Manager not initialized.", LogLevel::Error);
+                return;
+            };
+            match test_result {
+                Ok(result) => {
+                    let level = match result.outcome {
+                        ResetTestOutcome::Passed => LogLevel::Success,
+                        ResetTestOutcome::Failed => LogLevel::Error,
+                        ResetTestOutcome::NotRun => LogLevel::Warning,
+                    };
+                    app.add_log(&format!("Reset test {:?}: {}", result.outcome, result.detail), level);
+                    if let Some(stored) = app.gpus.as_mut().and_then(|g| g.get_mut(app.selected_gpu_index)) {
+                        match result.outcome {
+                            ResetTestOutcome::Passed => stored.capabilities.supports_reset = true,
+                            ResetTestOutcome::Failed => stored.capabilities.supports_reset = false,
+                            ResetTestOutcome::NotRun => {}
+                        }
+                        // Settles the reset bug of AMD cards the database doesn't list
+                        if stored.vendor == GpuVendor::AMD && stored.capabilities.has_reset_bug.is_none() {
+                            stored.capabilities.has_reset_bug = match result.outcome {
+                                ResetTestOutcome::Passed => Some(false),
+                                ResetTestOutcome::Failed => Some(true),
+                                ResetTestOutcome::NotRun => None,
+                            };
+                        }
+                        stored.capabilities.reset_test = Some(result);
+                    }
+                }
+                Err(e) => {
+                    app.add_log(&format!("Reset test failed: {}", e), LogLevel::Error);
+                    // Record the functions left on vfio-pci so they can be rolled back
+                    let mut record_errors = Vec::new();
+                    if let Some(state_tracker) = app.state_tracker.as_mut() {
+                        for device in e.left_on_vfio {
+                            if let Err(e) = state_tracker.record_change(Change::DriverBound {
+                                device_bdf: device.bdf,
+                                new_driver: "vfio-pci".to_string(),
+                                original_driver: device.driver,
+                            }) {
+                                record_errors.push(e);
+                            }
+                        }
+                    }
+                    for e in record_errors {
+                        app.add_log(&format!("Failed to record state change: {}", e), LogLevel::Error);
+                    }
+                }
+            }
+        }
+        KeyCode::Char(key @ ('+' | '-')) => { // Add or remove an SR-IOV virtual function
+            if !app.show_gpu_details {
+                return;
+            }
+            let Some(gpu) = app.gpus.as_ref().and_then(|g| g.get(app.selected_gpu_index)).cloned() else { return };
+            let Some(sriov) = gpu.sriov.as_ref() else {
+                app.add_log(&format!("{} does not support SR-IOV.", gpu.bdf), LogLevel::Warning);
+                return;
+            };
+            let original_num_vfs = sriov.num_vfs;
+            let num_vfs = if key == '+' { original_num_vfs + 1 } else { original_num_vfs.saturating_sub(1) };
+            if num_vfs == original_num_vfs || num_vfs > sriov.total_vfs {
+                app.add_log(&format!("{} already has {} of {} VFs.", gpu.bdf, original_num_vfs, sriov.total_vfs), LogLevel::Info);
+                return;
+            }
+            if original_num_vfs > 0 && gpu.virtual_functions.iter().any(|vf| vf.driver.as_deref() == Some("vfio-pci")) {
+                app.add_log("Changing the VF count recreates every VF, including those bound to vfio-pci.", LogLevel::Warning);
+            }
+            let Some(result) = app.vfio_manager.as_ref().map(|manager| if num_vfs == 0 {
+                manager.destroy_vfs(&gpu, false).map(|_| Vec::new())
+            } else {
+                manager.create_vfs(&gpu, num_vfs, false)
+            }) else {
+                app.add_log("VFIO Manager not initialized.", LogLevel::Error);
+                return;
+            };
+            match result {
+                Ok(vfs) => {
+                    app.add_log(&format!("{} now has {} virtual function(s).", gpu.bdf, vfs.len()), LogLevel::Success);
+                    let change = if num_vfs == 0 {
+                        Change::VfsDestroyed { pf_bdf: gpu.bdf.clone(), original_num_vfs }
+                    } else {
+                        Change::VfsCreated { pf_bdf: gpu.bdf.clone(), num_vfs, original_num_vfs }
+                    };
+                    let record_result = app.state_tracker.as_mut().map(|tracker| tracker.record_change(change));
+                    if let Some(Err(e)) = record_result {
+                        app.add_log(&format!("Failed to record state change: {}", e), LogLevel::Error);
+                    }
+                    if let Some(stored) = app.gpus.as_mut().and_then(|g| g.get_mut(app.selected_gpu_index)) {
+                        stored.sriov = read_sriov_info(&std::path::Path::new("/sys/bus/pci/devices").join(&gpu.bdf));
+                        stored.virtual_functions = vfs;
+                    }
+                }
+                Err(e) => app.add_log(&format!("Failed to change VF count of {}: {}", gpu.bdf, e), LogLevel::Error),
+            }
+        }
+        KeyCode::Char('f') => { // Bind the next SR-IOV virtual function to vfio-pci
+            if !app.show_gpu_details {
+                return;
+            }
+            let Some(gpu) = app.gpus.as_ref().and_then(|g| g.get(app.selected_gpu_index)).cloned() else { return };
+            let Some((vf_index, vf)) = gpu.virtual_functions.iter().enumerate()
+                .find(|(_, vf)| vf.driver.as_deref() != Some("vfio-pci")) else {
+                app.add_log(&format!("{} has no virtual functions left to bind (press '+' to create one).", gpu.bdf), LogLevel::Info);
+                return;
+            };
+            let Some(result) = app.vfio_manager.as_ref().map(|manager| manager.bind_vf(vf, false)) else {
+                app.add_log("VFIO Manager not initialized.", LogLevel::Error);
+                return;
+            };
+            match result {
+                Ok(()) => {
+                    app.add_log(&format!("VF {} bound to vfio-pci.", vf.bdf), LogLevel::Success);
+                    let change = Change::VfBound {
+                        vf_bdf: vf.bdf.clone(),
+                        pf_bdf: gpu.bdf.clone(),
+                        original_driver: vf.driver.clone(),
+                    };
+                    let record_result = app.state_tracker.as_mut().map(|tracker| tracker.record_change(change));
+                    if let Some(Err(e)) = record_result {
+                        app.add_log(&format!("Failed to record state change: {}", e), LogLevel::Error);
+                    }
+                    if let Some(stored) = app.gpus.as_mut().and_then(|g| g.get_mut(app.selected_gpu_index)) {
+                        stored.virtual_functions[vf_index].driver = Some("vfio-pci".to_string());
+                    }
+                }
+                Err(e) => app.add_log(&format!("Failed to bind VF {}: {}", vf.bdf, e), LogLevel::Error),
+            }
+        }
+        KeyCode::Char('m') => { // Create a GVT-g mediated device on the selected GPU
+            if !app.show_gpu_details {
+                return;
+            }
+            let Some(gpu) = app.gpus.as_ref().and_then(|g| g.get(app.selected_gpu_index)).cloned() else { return };
+            if gpu.mdev_types.is_empty() {
+                app.add_log(&format!("{} offers no mediated device types (GVT-g needs i915.enable_gvt=1).", gpu.bdf), LogLevel::Warning);
+                return;
+            }
+            // The smallest type with free instances; larger ones can be created with create_gvtg_device
+            match create_gvtg_device(&gpu, "exliar-vfio", 0) {
+                Ok(mdev) => {
+                    app.add_log(&format!("Created GVT-g device {} ({}).", mdev.uuid, mdev.type_id), LogLevel::Success);
+                    let definition_path = mdevctl_definition_path(&mdev.parent_bdf, &mdev.uuid);
+                    let change = Change::MdevCreated {
+                        parent_bdf: mdev.parent_bdf.clone(),
+                        uuid: mdev.uuid.clone(),
+                        mdev_type: mdev.type_id.clone(),
+                        definition_path: definition_path.exists().then_some(definition_path),
+                    };
+                    let record_result = app.state_tracker.as_mut().map(|tracker| tracker.record_change(change));
+                    if let Some(Err(e)) = record_result {
+                        app.add_log(&format!("Failed to record state change: {}", e), LogLevel::Error);
+                    }
+                    if let Some(stored) = app.gpus.as_mut().and_then(|g| g.get_mut(app.selected_gpu_index)) {
+                        if let Some(mdev_type) = stored.mdev_types.iter_mut().find(|t| t.id == mdev.type_id) {
+                            mdev_type.available_instances = mdev_type.available_instances.saturating_sub(1);
+                        }
+                        stored.mdevs.push(mdev);
+                    }
+                }
+                Err(e) => app.add_log(&format!("Failed to create GVT-g device: {}", e), LogLevel::Error),
+            }
+        }
+        KeyCode::Char('M') => { // Remove the most recently listed mediated device
+            if !app.show_gpu_details {
+                return;
+            }
+            let Some(mdev) = app.gpus.as_ref()
+                .and_then(|g| g.get(app.selected_gpu_index))
+                .and_then(|gpu| gpu.mdevs.last())
+                .cloned() else {
+                app.add_log("The selected GPU has no mediated devices.", LogLevel::Info);
+                return;
+            };
+            match remove_gvtg_device(&mdev) {
+                Ok(()) => {
+                    app.add_log(&format!("Removed GVT-g device {}.", mdev.uuid), LogLevel::Success);
+                    // Nothing is left for a rollback to remove
+                    let forget_result = app.state_tracker.as_mut().map(|tracker| tracker.forget_changes(|change| {
+                        matches!(change, Change::MdevCreated { uuid, .. } if *uuid == mdev.uuid)
+                    }));
+                    if let Some(Err(e)) = forget_result {
+                        app.add_log(&format!("Failed to update recorded state: {}", e), LogLevel::Error);
+                    }
+                    if let Some(stored) = app.gpus.as_mut().and_then(|g| g.get_mut(app.selected_gpu_index)) {
+                        stored.mdevs.retain(|m| m.uuid != mdev.uuid);
+                        if let Some(mdev_type) = stored.mdev_types.iter_mut().find(|t| t.id == mdev.type_id) {
+                            mdev_type.available_instances += 1;
+                        }
+                    }
+                }
+                Err(e) => app.add_log(&e, LogLevel::Error),
+            }
+        }
+        KeyCode::Char('x') => { // Extract the firmware a VM needs from the selected GPU
+            if !app.show_gpu_details {
+                return;
+            }
+            let Some(gpu) = app.gpus.as_ref().and_then(|g| g.get(app.selected_gpu_index)).cloned() else { return };
+            match gpu.vendor {
+                // Muxless laptop GPUs have no ROM BAR; the VBIOS comes from ACPI
+                _ if gpu.hybrid.as_ref().is_some_and(|hybrid| hybrid.is_muxless()) => match generate_hybrid_kit(&gpu, false) {
+                    Ok(files) => {
+                        match &files.vbios {
+                            Some(path) => app.add_log(&format!("VBIOS of {} at {}.", gpu.bdf, path.display()), LogLevel::Success),
+                            None => app.add_log(&format!("No VBIOS for {} found in the ACPI tables; supply a ROM file.", gpu.bdf), LogLevel::Warning),
+                        }
+                        if let Some(ssdt) = &files.ssdt {
+                            app.add_log(&format!("Fake battery SSDT written to {}.", ssdt.display()), LogLevel::Success);
+                        }
+                    }
+                    Err(e) => app.add_log(&format!("Failed to write the hybrid graphics kit: {}", e), LogLevel::Error),
+                },
+                GpuVendor::NVIDIA => {
+                    let user_path = user_rom_path(&gpu.vendor_id, &gpu.device_id);
+                    let fallback = user_path.exists().then_some(user_path.as_path());
+                    match extract_vbios(&gpu, fallback, false) {
+                        Ok(path) => app.add_log(&format!("VBIOS of {} saved to {}.", gpu.bdf, path.display()), LogLevel::Success),
+                        Err(e) => app.add_log(&e, LogLevel::Error),
+                    }
+                }
+                GpuVendor::Intel if gpu.is_integrated && igd_assignment_mode(&gpu) == IgdAssignmentMode::Legacy => {
+                    // OpRegion, VBT and VBIOS for legacy-mode assignment
+                    let igd = analyze_igd(std::path::Path::new(SYSFS_ROOT), &gpu);
+                    for blocker in igd.legacy_mode_blockers() {
+                        app.add_log(&format!("Legacy IGD assignment not possible: {}", blocker), LogLevel::Warning);
+                    }
+                    match extract_igd_firmware(&gpu, &igd, false) {
+                        Ok(files) => {
+                            for (name, path) in [("OpRegion", &files.opregion), ("VBT", &files.vbt), ("VBIOS", &files.rom)] {
+                                match path {
+                                    Some(path) => app.add_log(&format!("IGD {} saved to {}.", name, path.display()), LogLevel::Success),
+                                    None => app.add_log(&format!("IGD {} not available.", name), LogLevel::Warning),
+                                }
+                            }
+                        }
+                        Err(e) => app.add_log(&format!("Failed to extract IGD firmware: {}", e), LogLevel::Error),
+                    }
+                }
+                _ => app.add_log(&format!("{} has no firmware to extract.", gpu.bdf), LogLevel::Info),
+            }
+        }
+        KeyCode::Char('C') => { // Override the host display protection for the selected GPU
+            let sole_display = app.get_selected_passthrough_gpu()
+                .zip(app.gpus.as_ref())
+                .is_some_and(|(gpu, gpus)| is_sole_host_display(gpu, gpus));
+            if sole_display {
+                app.display_guard_overridden = true;
+                app.add_log("Host display protection OVERRIDDEN. The host will lose its display after reboot; make sure you have SSH or another GPU. Press 'c' to configure.",
+                            LogLevel::Warning);
+            } else {
+                app.add_log("No override needed: the selected GPU is not the host's only display GPU.", LogLevel::Info);
+            }
+        }
         KeyCode::Char('c') => { // Configure system for selected GPU
+            // Refuse to take away the host's only display unless explicitly overridden
+            let sole_display = app.get_selected_passthrough_gpu()
+                .zip(app.gpus.as_ref())
+                .is_some_and(|(gpu, gpus)| is_sole_host_display(gpu, gpus));
+            if sole_display && !app.display_guard_overridden {
+                app.add_log("REFUSING TO CONFIGURE: the selected GPU is the only one with connected displays.", LogLevel::Error);
+                app.add_log("Passing it through leaves the host without a console. Connect a display to another GPU, or press 'C' to override.",
+                            LogLevel::Warning);
+                return;
+            }
+            // Kernel parameters can't help if the firmware keeps the IOMMU off
+            let iommu_issues = app.system_info.as_ref()
+                .map(|si| (si.iommu.is_active(), si.iommu.issues(&si.cpu_vendor)));
+            if let Some((iommu_active, issues)) = iommu_issues {
+                let params_set = app.system_info.as_ref().is_some_and(|si| {
+                    IommuStatus::required_parameters(&si.cpu_vendor).iter().all(|param| si.iommu.has_parameter(param))
+                });
+                if !iommu_active && params_set {
+                    app.add_log("REFUSING TO CONFIGURE: the IOMMU is off although its kernel parameters are active.", LogLevel::Error);
+                    app.add_log("Enable VT-d/AMD-Vi (IOMMU) in the firmware setup, reboot and press 'r'.", LogLevel::Warning);
+                    return;
+                }
+                for issue in issues {
+                    app.add_log(&format!("Warning: {}", issue), LogLevel::Warning);
+                }
+            }
             if let Some(gpu_index) = app.selected_passthrough_gpu_index {
                 // --- Prepare Data (Immutable Borrows OK) ---
                 let gpu_bdf = app.gpus.as_ref().and_then(|g| g.get(gpu_index)).map(|gpu| gpu.bdf.clone());
                 let gpu_model = app.gpus.as_ref().and_then(|g| g.get(gpu_index)).map(|gpu| gpu.model_name.clone());
-                // TODO: Get *all* related device IDs for the selected GPU, not just the main one
-                let gpu_ids = app.gpus.as_ref().and_then(|g| g.get(gpu_index)).map(|gpu| vec![format!("{}:{}", gpu.vendor_id, gpu.device_id)]);
+                // Every function of the slot (GPU, HDMI audio, USB-C, UCSI) goes to vfio-pci together
+                let gpu_ids = app.gpus.as_ref().and_then(|g| g.get(gpu_index)).map(|gpu| gpu.passthrough_ids());
                 let bootloader_name = app.system_info.as_ref().map(|si| format!("{:?}", si.bootloader)); // Get bootloader name for logging/state
+                let initramfs_name = app.system_info.as_ref().map(|si| format!("{:?}", si.initramfs_system)).unwrap_or_default();
+                let iommu_params = app.system_info.as_ref().map(|si| IommuStatus::required_parameters(&si.cpu_vendor)).unwrap_or_default();
 
                 if let (Some(bdf), Some(model), Some(ids), Some(boot_name)) = (gpu_bdf, gpu_model, gpu_ids, bootloader_name) {
                     app.add_log(&format!("Starting configuration for GPU {} ({})", bdf, model), LogLevel::Info);
+                    app.add_log(&format!("Passing through device IDs: {}", ids.join(", ")), LogLevel::Info);
                     app.current_action = Some(format!("Configuring for {}", model));
 
                     // --- Perform Actions (Mutable Borrows Separated) ---
@@ -86,37 +416,45 @@ pub fn handle_key_event(app: &mut AppState, key_code: KeyCode, modifiers: KeyMod
                     let mut bootloader_updated = false;
                     let mut initramfs_updated = false;
                     let mut changes_to_record: Vec<Change> = Vec::new(); // Buffer changes
+                    let mut vendor_reset_warning: Option<String> = None;
 
                     // 1. Configure Modprobe
                     if let Some(vfio_manager) = &app.vfio_manager {
                         config_results.push(
                             vfio_manager.configure_modprobe(&ids, false)
                                 .map_err(|e| format!("Modprobe config failed: {}", e))
-                                .map(|_| {
-                                    // TODO: Determine actual file paths modified for accurate state tracking
-                                    // Example: Assuming vfio_conf_path and backup path are known/returned
-                                    // let vfio_conf_path = std::path::PathBuf::from("/etc/modprobe.d/vfio.conf");
-                                    // let modules_load_path = std::path::PathBuf::from("/etc/modules-load.d/vfio-pci-load.conf");
-                                    // // Need a way to get backup paths if created
-                                    // changes_to_record.push(Change::FileModified { path: vfio_conf_path, backup_path: PathBuf::new() });
-                                    // changes_to_record.push(Change::FileModified { path: modules_load_path, backup_path: PathBuf::new() });
-                                })
+                                .map(|changes| changes_to_record.extend(changes))
                         );
                     } else {
                          config_results.push(Err("VFIO Manager not initialized.".to_string()));
                     }
 
+                    // 1b. Set up vendor-reset for AMD GPUs with the reset bug it can fix
+                    let reset_bug_gpu = app.gpus.as_ref().and_then(|g| g.get(gpu_index))
+                        .filter(|gpu| gpu.capabilities.has_reset_bug == Some(true) && is_vendor_reset_supported(&gpu.device_id))
+                        .cloned();
+                    if let (Some(gpu), Some(vfio_manager)) = (reset_bug_gpu, &app.vfio_manager) {
+                        match vfio_manager.setup_vendor_reset(&gpu, false) {
+                            Ok(changes) => changes_to_record.extend(changes),
+                            // Not fatal: passthrough works, the GPU just can't be reset between VM runs
+                            Err(e) => {
+                                vendor_reset_warning = Some(format!("vendor-reset not set up: {}", e));
+                                // Files written before the failure must still be rolled back
+                                changes_to_record.extend(e.changes);
+                            }
+                        }
+                    }
+
                     // 2. Add Kernel Parameters & Update Bootloader (if needed and previous steps ok)
                     if config_results.last().map_or(false, |r| r.is_ok()) {
                         if let Some(boot_manager) = app.bootloader_manager.as_mut() {
                             // TODO: Check if params already exist before adding
-                            // TODO: Determine correct params based on CPU vendor (from app.system_info)
-                            let required_params = ["intel_iommu=on", "iommu=pt"]; // Example
-                            match boot_manager.add_parameters(&required_params, false) {
+                            let required_params = &iommu_params;
+                            match boot_manager.add_parameters(required_params, false) {
                                 Ok(params_changed) => {
                                     if params_changed {
                                         // Record change
-                                        for param in required_params {
+                                        for param in required_params.iter() {
                                              changes_to_record.push(Change::KernelParamAdded {
                                                  parameter: param.to_string(),
                                                  bootloader: boot_name.clone(),
@@ -141,13 +479,30 @@ pub fn handle_key_event(app: &mut AppState, key_code: KeyCode, modifiers: KeyMod
                         }
                     }
 
-                    // 3. Update Initramfs (if previous steps ok)
+                    // 3. Add the VFIO modules to the initramfs and update it (if previous steps ok)
+                    if config_results.last().is_some_and(|r| r.is_ok()) {
+                        if let Some(vfio_manager) = &app.vfio_manager {
+                            config_results.push(
+                                vfio_manager.configure_initramfs_modules(false)
+                                    .map_err(|e| format!("Initramfs module config failed: {}", e))
+                                    .map(|changes| changes_to_record.extend(changes))
+                            );
+                        }
+                    }
                     if config_results.last().map_or(false, |r| r.is_ok()) {
                         if let Some(vfio_manager) = &app.vfio_manager {
                             config_results.push(
                                 vfio_manager.update_initramfs(false)
                                     .map_err(|e| format!("Initramfs update failed: {}", e))
-                                    .map(|_| { initramfs_updated = true; }) // Mark initramfs as updated
+                                    .map(|rebuilt| {
+                                        if rebuilt {
+                                            initramfs_updated = true;
+                                            changes_to_record.push(Change::InitramfsRebuilt {
+                                                initramfs_system: initramfs_name.clone(),
+                                                rebuilt_at: chrono::Utc::now().timestamp(),
+                                            });
+                                        }
+                                    })
                             );
                         }
                         // No else needed for vfio_manager check here as it was checked before
@@ -183,6 +538,10 @@ pub fn handle_key_event(app: &mut AppState, key_code: KeyCode, modifiers: KeyMod
                     }
 
 
+                    if let Some(warning) = vendor_reset_warning {
+                        log_buffer.push((warning, LogLevel::Warning));
+                    }
+
                     // Add buffered logs to the main app state
                     for (msg, level) in log_buffer {
                         app.add_log(&msg, level);
@@ -208,10 +567,8 @@ pub fn handle_key_event(app: &mut AppState, key_code: KeyCode, modifiers: KeyMod
                     if overall_success {
                         app.add_log("Initial configuration applied successfully.", LogLevel::Success);
                         app.configuration_applied = true;
-                        if bootloader_updated || initramfs_updated {
-                            app.reboot_required = true;
-                            app.add_log("Reboot required to apply all changes.", LogLevel::Warning);
-                        }
+                        // Reports exactly which parameters and files the next boot applies
+                        app.refresh_pending_reboot();
                     } else {
                         app.add_log("Configuration failed. See errors above.", LogLevel::Error);
                         // Consider offering rollback?
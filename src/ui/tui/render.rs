@@ -11,6 +11,13 @@ use ratatui::{
 // Use our defined PastelColor enum and Theme
 use crate::ui::colors::{PastelColor, Theme};
 use crate::core::system::DistroFamily; // Re-add the import
+use crate::core::hugepages::format_size;
+use crate::gpu::acs::AcsIsolationSource;
+use crate::gpu::link::LinkStatus;
+use crate::gpu::numa::format_cpulist;
+use crate::gpu::display::is_sole_host_display;
+use crate::gpu::iommu::IommuPeerKind;
+use crate::gpu::reset::ResetTestOutcome;
 use super::state::AppState;
 
 /// Convert our custom PastelColor to ratatui Color
@@ -202,6 +209,28 @@ fn render_system_info(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme)
             Span::styled(format!("{:?}", system_info.cpu_vendor), value_style),
         ]));
 
+        // Add CPU topology (base for pinning), with the L3 domains and isolated CPUs if relevant
+        let topology = &system_info.cpu_topology;
+        info_lines.push(Line::from(vec![
+            Span::styled("🧩 CPU Topology: ", label_style),
+            Span::styled(
+                format!("{}{}", topology.summary(), if topology.smt_active() { " (SMT)" } else { "" }),
+                value_style,
+            ),
+        ]));
+        if topology.l3_domains.len() > 1 {
+            info_lines.push(Line::from(Span::styled(
+                format!("   L3: {}", topology.l3_summary()),
+                Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)),
+            )));
+        }
+        if !topology.isolated.is_empty() {
+            info_lines.push(Line::from(vec![
+                Span::styled("   Isolated: ", label_style),
+                Span::styled(format_cpulist(&topology.isolated), value_style),
+            ]));
+        }
+
         // Add virtualization status
         info_lines.push(Line::from(vec![
             Span::styled("🔄 Virtualization: ", label_style),
@@ -215,6 +244,42 @@ fn render_system_info(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme)
             ),
         ]));
 
+        // Add runtime IOMMU status
+        let iommu = &system_info.iommu;
+        let iommu_issues = iommu.issues(&system_info.cpu_vendor);
+        info_lines.push(Line::from(vec![
+            Span::styled("🛡️  IOMMU: ", label_style),
+            Span::styled(
+                iommu.summary(),
+                if iommu_issues.is_empty() {
+                    Style::default().fg(pastel_to_ratatui_color(theme.success))
+                } else {
+                    Style::default().fg(pastel_to_ratatui_color(theme.error))
+                }
+            ),
+        ]));
+        for issue in &iommu_issues {
+            info_lines.push(Line::from(Span::styled(
+                format!("   ⚠ {}", issue),
+                Style::default().fg(pastel_to_ratatui_color(theme.error)),
+            )));
+        }
+
+        // Add hugepage pools and THP mode, with the hugetlbfs mounts VMs can use
+        info_lines.push(Line::from(vec![
+            Span::styled("📐 Hugepages: ", label_style),
+            Span::styled(system_info.hugepages.summary(), value_style),
+        ]));
+        if !system_info.hugepages.mounts.is_empty() {
+            let mounts: Vec<String> = system_info.hugepages.mounts.iter()
+                .map(|(path, page_size)| format!("{} ({})", path.display(), page_size.map_or("?".to_string(), format_size)))
+                .collect();
+            info_lines.push(Line::from(Span::styled(
+                format!("   hugetlbfs: {}", mounts.join(", ")),
+                Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)),
+            )));
+        }
+
         // Add distribution info if available
         if let Some(ref distro) = system_info.distribution {
              let family_str = match &distro.family {
@@ -265,6 +330,33 @@ fn render_system_info(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme)
             ]));
         }
 
+        // Add pending reboot status (what the next boot would change)
+        if let Some(pending) = &app.pending_reboot {
+            info_lines.push(Line::from(vec![
+                Span::styled("⏳ Reboot: ", label_style),
+                Span::styled(
+                    pending.summary(),
+                    if pending.is_required() {
+                        Style::default().fg(pastel_to_ratatui_color(theme.error))
+                    } else {
+                        Style::default().fg(pastel_to_ratatui_color(theme.success))
+                    }
+                ),
+            ]));
+            if !pending.params_to_add.is_empty() {
+                info_lines.push(Line::from(Span::styled(
+                    format!("   + {}", pending.params_to_add.join(" ")),
+                    Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)),
+                )));
+            }
+            if !pending.params_to_remove.is_empty() {
+                info_lines.push(Line::from(Span::styled(
+                    format!("   - {}", pending.params_to_remove.join(" ")),
+                    Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)),
+                )));
+            }
+        }
+
         // Create paragraph
         let paragraph = Paragraph::new(info_lines)
             .wrap(Wrap { trim: true });
@@ -395,6 +487,25 @@ fn render_gpu_summary(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme)
                     Span::styled(gpu.driver.as_deref().unwrap_or("None"), value_style),
                 ]));
 
+                // Show whether the GPU is driving the host's displays
+                let display_count = gpu.connected_displays().len();
+                info_lines.push(Line::from(vec![
+                    Span::styled("  • Displays: ", label_style),
+                    Span::styled(
+                        match (display_count, gpu.boot_vga) {
+                            (0, true) => "None connected (boot VGA)".to_string(),
+                            (0, false) => "None connected".to_string(),
+                            (n, true) => format!("{} connected (boot VGA, host display)", n),
+                            (n, false) => format!("{} connected (host display)", n),
+                        },
+                        if display_count > 0 {
+                            Style::default().fg(pastel_to_ratatui_color(theme.error)) // Peach (in use by host)
+                        } else {
+                            value_style
+                        },
+                    ),
+                ]));
+
                 // Show key capabilities with colored status indicators
                 let reset_style = if gpu.capabilities.supports_reset {
                     Style::default().fg(pastel_to_ratatui_color(theme.success)) // Mint (good)
@@ -402,10 +513,10 @@ fn render_gpu_summary(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme)
                     Style::default().fg(pastel_to_ratatui_color(theme.error)) // Pink (bad)
                 };
 
-                let reset_bug_style = if gpu.capabilities.has_reset_bug {
-                    Style::default().fg(pastel_to_ratatui_color(theme.error)) // Pink (bad)
-                } else {
-                    Style::default().fg(pastel_to_ratatui_color(theme.success)) // Mint (good)
+                let reset_bug_style = match gpu.capabilities.has_reset_bug {
+                    Some(true) => Style::default().fg(pastel_to_ratatui_color(theme.error)), // Pink (bad)
+                    Some(false) => Style::default().fg(pastel_to_ratatui_color(theme.success)), // Mint (good)
+                    None => Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)),
                 };
 
                 info_lines.push(Line::from(vec![
@@ -416,7 +527,11 @@ fn render_gpu_summary(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme)
                     ),
                     Span::styled("  Reset Bug: ", label_style),
                     Span::styled(
-                        if gpu.capabilities.has_reset_bug { "✓" } else { "✗" },
+                        match gpu.capabilities.has_reset_bug {
+                            Some(true) => "✓",
+                            Some(false) => "✗",
+                            None => "?",
+                        },
                         reset_bug_style,
                     ),
                 ]));
@@ -534,6 +649,314 @@ fn render_gpu_details(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme)
                 Span::styled(if gpu.is_integrated { "Yes" } else { "No" }, value_style),
             ]));
 
+            // Classification from the compatibility database
+            info_lines.push(Line::from(vec![
+                Span::styled("Family: ", label_style),
+                match &gpu.profile {
+                    Some(profile) => Span::styled(
+                        format!("{}{}", profile.classification(), if profile.model_listed { " (known model)" } else { "" }),
+                        value_style,
+                    ),
+                    None => Span::styled("Not in GPU database", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                },
+            ]));
+
+            // VRAM and BAR layout
+            info_lines.push(Line::from(vec![
+                Span::styled("VRAM: ", label_style),
+                Span::styled(
+                    match gpu.vram_size {
+                        Some(mb) => format!("{} MB", mb),
+                        None => "Unknown (not reported by driver)".to_string(),
+                    },
+                    value_style,
+                ),
+            ]));
+
+            if let Some(aperture) = gpu.vram_aperture() {
+                info_lines.push(Line::from(vec![
+                    Span::styled("VRAM Aperture: ", label_style),
+                    Span::styled(aperture.to_string(), value_style),
+                ]));
+            }
+
+            if !gpu.bars.is_empty() {
+                let bar_list: Vec<String> = gpu.bars.iter().map(|bar| bar.to_string()).collect();
+                info_lines.push(Line::from(vec![
+                    Span::styled("BARs: ", label_style),
+                    Span::styled(bar_list.join(" | "), value_style),
+                ]));
+            }
+
+            // Resizable BAR and above-4G decoding
+            if let Some(rebar) = &gpu.rebar {
+                for bar in &rebar.bars {
+                    let max = bar.supported_sizes.last().copied().unwrap_or(bar.current_size);
+                    info_lines.push(Line::from(vec![
+                        Span::styled("Resizable BAR: ", label_style),
+                        Span::styled(
+                            format!("BAR{} at {} MiB (max {} MiB){}", bar.index, bar.current_size >> 20, max >> 20,
+                                    if bar.sysfs_resize { ", resizable via sysfs" } else { "" }),
+                            value_style,
+                        ),
+                    ]));
+                }
+            }
+
+            info_lines.push(Line::from(vec![
+                Span::styled("Above 4G Decoding: ", label_style),
+                Span::styled(
+                    match gpu.above_4g_decoding {
+                        Some(true) => "Enabled",
+                        Some(false) => "Disabled",
+                        None => "Unknown",
+                    },
+                    value_style,
+                ),
+            ]));
+
+            // Boot console and connected displays
+            info_lines.push(Line::from(vec![
+                Span::styled("Boot VGA: ", label_style),
+                Span::styled(if gpu.boot_vga { "Yes (host console)" } else { "No" }, value_style),
+            ]));
+            if gpu.drm_cards.is_empty() {
+                info_lines.push(Line::from(vec![
+                    Span::styled("Displays: ", label_style),
+                    Span::styled("No DRM card (not bound to a display driver)", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                ]));
+            }
+            for card in &gpu.drm_cards {
+                info_lines.push(Line::from(vec![
+                    Span::styled("Displays: ", label_style),
+                    Span::styled(format!("{} ({} connector(s))", card.name, card.connectors.len()), value_style),
+                ]));
+                for connector in card.connectors.iter().filter(|c| c.is_connected()) {
+                    info_lines.push(Line::from(vec![
+                        Span::styled(format!("  ↳ {} ", connector.name), label_style),
+                        Span::styled(
+                            if connector.enabled { "connected, active" } else { "connected, inactive" },
+                            Style::default().fg(pastel_to_ratatui_color(theme.error)), // Peach (in use by host)
+                        ),
+                    ]));
+                }
+            }
+            if let Some(hybrid) = &gpu.hybrid {
+                info_lines.push(Line::from(vec![
+                    Span::styled("Hybrid Graphics: ", label_style),
+                    Span::styled(hybrid.summary(), value_style),
+                ]));
+                if hybrid.is_muxless() {
+                    info_lines.push(Line::from(vec![
+                        Span::styled("  ↳ VBIOS from ACPI, fake battery and subsystem ID are set up by the vendor quirks",
+                            Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                    ]));
+                }
+            }
+            if is_sole_host_display(gpu, gpus) {
+                info_lines.push(Line::from(vec![
+                    Span::styled("⚠ This GPU drives the host's only display(s); passing it through leaves the host headless",
+                        Style::default().fg(pastel_to_ratatui_color(theme.error)).add_modifier(Modifier::BOLD)),
+                ]));
+            }
+
+            // Capabilities parsed from config space
+            info_lines.push(Line::from(vec![
+                Span::styled("PCI Capabilities: ", label_style),
+                match &gpu.pci_capabilities {
+                    Some(caps) => Span::styled(caps.names().join(", "), value_style),
+                    None => Span::styled("Unreadable (run as root)", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                },
+            ]));
+
+            // Other functions of the same slot, passed through as one unit
+            if !gpu.companion_functions.is_empty() {
+                info_lines.push(Line::from(vec![
+                    Span::styled("Slot Functions: ", label_style),
+                    Span::styled(format!("{} (passed through together)", gpu.slot_bdfs().len()), value_style),
+                ]));
+                for function in &gpu.companion_functions {
+                    info_lines.push(Line::from(vec![
+                        Span::styled(format!("  ↳ {} ", function.bdf), label_style),
+                        Span::styled(format!("{}: {} [{}]", function.class, function.device_name, function.id_pair()), value_style),
+                    ]));
+                }
+            }
+            for function in &gpu.shared_slot_functions {
+                info_lines.push(Line::from(vec![
+                    Span::styled(format!("  ↳ {} ", function.bdf), label_style),
+                    Span::styled(format!("{}: {} [{}] ", function.class, function.device_name, function.id_pair()), value_style),
+                    Span::styled("(shares slot, not passed through)", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                ]));
+            }
+
+            // SR-IOV virtual functions
+            if let Some(sriov) = &gpu.sriov {
+                info_lines.push(Line::from(vec![
+                    Span::styled("SR-IOV: ", label_style),
+                    Span::styled(format!("{} of {} VFs enabled", sriov.num_vfs, sriov.total_vfs), value_style),
+                    Span::styled(" ('+'/'-' to change, 'f' to bind a VF)", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                ]));
+                for vf in &gpu.virtual_functions {
+                    let driver = vf.driver.as_deref().unwrap_or("no driver");
+                    info_lines.push(Line::from(vec![
+                        Span::styled(format!("  ↳ {} ", vf.bdf), label_style),
+                        Span::styled(format!("[{}] ", vf.id_pair()), value_style),
+                        Span::styled(
+                            driver.to_string(),
+                            if driver == "vfio-pci" {
+                                Style::default().fg(pastel_to_ratatui_color(theme.success))
+                            } else {
+                                value_style
+                            },
+                        ),
+                    ]));
+                }
+            }
+
+            // Mediated device types (GVT-g) and created instances
+            if !gpu.mdev_types.is_empty() {
+                info_lines.push(Line::from(vec![
+                    Span::styled("Mdev Types: ", label_style),
+                    Span::styled(format!("{} ({} created)", gpu.mdev_types.len(), gpu.mdevs.len()), value_style),
+                    Span::styled(" ('m' to create, 'M' to remove)", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                ]));
+                for mdev_type in &gpu.mdev_types {
+                    let mut text = format!("{} free", mdev_type.available_instances);
+                    if let Some(mb) = mdev_type.memory_mb {
+                        text.push_str(&format!(", {} MB", mb));
+                    }
+                    if let Some(resolution) = &mdev_type.resolution {
+                        text.push_str(&format!(", up to {}", resolution));
+                    }
+                    info_lines.push(Line::from(vec![
+                        Span::styled(format!("  ↳ {} ", mdev_type.id), label_style),
+                        Span::styled(text, value_style),
+                    ]));
+                }
+                for mdev in &gpu.mdevs {
+                    info_lines.push(Line::from(vec![
+                        Span::styled(format!("  ↳ {} ", mdev.uuid), label_style),
+                        Span::styled(&mdev.type_id, Style::default().fg(pastel_to_ratatui_color(theme.success))),
+                    ]));
+                }
+            }
+
+            // IOMMU group and isolation status
+            let (group_text, group_style) = match gpu.iommu_group {
+                Some(group) if gpu.is_iommu_isolated() => (
+                    format!("{} (isolated)", group),
+                    Style::default().fg(pastel_to_ratatui_color(theme.success)), // Mint (good)
+                ),
+                Some(group) => (
+                    format!("{} (shared with {} other endpoint(s))", group, gpu.endpoint_peers().count()),
+                    Style::default().fg(pastel_to_ratatui_color(theme.error)), // Peach (warning)
+                ),
+                None => (
+                    "Unavailable (is the IOMMU enabled?)".to_string(),
+                    Style::default().fg(pastel_to_ratatui_color(theme.error)), // Pink (bad)
+                ),
+            };
+            info_lines.push(Line::from(vec![
+                Span::styled("IOMMU Group: ", label_style),
+                Span::styled(group_text, group_style),
+            ]));
+
+            for peer in &gpu.iommu_group_peers {
+                let (note, note_style) = match peer.kind {
+                    IommuPeerKind::Bridge => (" [bridge, stays on host]", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                    IommuPeerKind::CompanionFunction => (" [same slot, passed through with GPU]", Style::default().fg(pastel_to_ratatui_color(theme.success))),
+                    IommuPeerKind::Endpoint => (" [must also use vfio-pci]", Style::default().fg(pastel_to_ratatui_color(theme.error))),
+                };
+                info_lines.push(Line::from(vec![
+                    Span::styled(format!("  ↳ {} ", peer.bdf), label_style),
+                    Span::styled(&peer.description, value_style),
+                    Span::styled(note, note_style),
+                ]));
+            }
+
+            // Where the isolation comes from on the upstream PCIe path
+            info_lines.push(Line::from(vec![
+                Span::styled("ACS Isolation: ", label_style),
+                Span::styled(
+                    gpu.acs.source.to_string(),
+                    match gpu.acs.source {
+                        AcsIsolationSource::Hardware => Style::default().fg(pastel_to_ratatui_color(theme.success)), // Mint (good)
+                        AcsIsolationSource::Unknown => value_style,
+                        _ => Style::default().fg(pastel_to_ratatui_color(theme.error)), // Peach (warning)
+                    },
+                ),
+            ]));
+
+            for bridge in &gpu.acs.path {
+                let (acs_text, acs_style) = if !bridge.readable {
+                    ("ACS unreadable".to_string(), Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)))
+                } else if !bridge.needs_acs() {
+                    ("ACS not required".to_string(), Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)))
+                } else if bridge.provides_isolation() {
+                    ("ACS enabled".to_string(), Style::default().fg(pastel_to_ratatui_color(theme.success)))
+                } else {
+                    (format!("ACS missing {}", bridge.missing_flags().join("/")), Style::default().fg(pastel_to_ratatui_color(theme.error)))
+                };
+                info_lines.push(Line::from(vec![
+                    Span::styled(format!("  ↳ {} ", bridge.bdf), label_style),
+                    Span::styled(format!("{}: ", bridge.role()), value_style),
+                    Span::styled(acs_text, acs_style),
+                ]));
+            }
+
+            // NUMA node the GPU is attached to; the generated CPU pinning uses its CPUs
+            info_lines.push(Line::from(vec![
+                Span::styled("NUMA: ", label_style),
+                Span::styled(gpu.numa.summary(), value_style),
+            ]));
+            if gpu.numa.is_multi_node() {
+                for node in &gpu.numa.host_nodes {
+                    let local = gpu.numa.node == Some(node.id);
+                    info_lines.push(Line::from(vec![
+                        Span::styled(format!("  ↳ node {} ", node.id), label_style),
+                        Span::styled(
+                            format!("CPUs {}{}{}", format_cpulist(&node.cpus),
+                                    node.memory_mb.map_or(String::new(), |mb| format!(", {} MB", mb)),
+                                    if local { " (GPU-local)" } else { "" }),
+                            if local { Style::default().fg(pastel_to_ratatui_color(theme.success)) } else { value_style },
+                        ),
+                    ]));
+                }
+            }
+
+            // PCIe topology from the root port down to the GPU, degraded links highlighted
+            if gpu.pcie_link.is_some() || !gpu.upstream_links.is_empty() {
+                info_lines.push(Line::from(vec![
+                    Span::styled("PCIe Topology:", label_style),
+                ]));
+                let link_span = |link: Option<&LinkStatus>| match link {
+                    Some(link) if link.is_degraded() => Span::styled(
+                        format!("{} ⚠", link),
+                        Style::default().fg(pastel_to_ratatui_color(theme.error)).add_modifier(Modifier::BOLD),
+                    ),
+                    Some(link) => Span::styled(link.to_string(), Style::default().fg(pastel_to_ratatui_color(theme.success))),
+                    None => Span::styled("no link info", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                };
+                let hops = gpu.upstream_links.iter()
+                    .map(|hop| (hop.bdf.as_str(), hop.role, hop.link.as_ref()))
+                    .chain(std::iter::once((gpu.bdf.as_str(), "GPU", gpu.pcie_link.as_ref())));
+                for (depth, (bdf, role, link)) in hops.enumerate() {
+                    let prefix = if depth == 0 { "  ".to_string() } else { format!("  {}└─ ", "   ".repeat(depth - 1)) };
+                    info_lines.push(Line::from(vec![
+                        Span::styled(format!("{}{} ", prefix, bdf), label_style),
+                        Span::styled(format!("{}: ", role), value_style),
+                        link_span(link),
+                    ]));
+                }
+                if gpu.pcie_link.as_ref().is_some_and(|link| link.speed_degraded() && !link.width_degraded()) {
+                    info_lines.push(Line::from(vec![
+                        Span::styled("  GPUs lower the link speed when idle; check again under load",
+                            Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                    ]));
+                }
+            }
+
             info_lines.push(Line::from(""));
 
             // GPU capabilities with detailed explanations
@@ -562,17 +985,45 @@ fn render_gpu_details(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme)
                 Span::styled("    Can the GPU be reset without rebooting the host system", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)))
             ]));
 
+            // Kernel reset methods and the on-demand reset test
+            info_lines.push(Line::from(vec![
+                Span::styled("  Reset Methods: ", label_style),
+                if gpu.capabilities.reset_methods.is_empty() {
+                    Span::styled("Unknown (kernel 5.15+ exposes reset_method)", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)))
+                } else {
+                    Span::styled(
+                        gpu.capabilities.reset_methods.iter().map(|m| m.to_string()).collect::<Vec<_>>().join(", "),
+                        value_style,
+                    )
+                },
+            ]));
+
+            let (test_text, test_style) = match &gpu.capabilities.reset_test {
+                Some(test) => (
+                    format!("{:?}{} - {} ({})", test.outcome,
+                            test.method.as_ref().map_or(String::new(), |m| format!(" via {}", m)),
+                            test.detail, test.tested_at),
+                    match test.outcome {
+                        ResetTestOutcome::Passed => Style::default().fg(pastel_to_ratatui_color(theme.success)),
+                        ResetTestOutcome::Failed => Style::default().fg(pastel_to_ratatui_color(theme.error)),
+                        ResetTestOutcome::NotRun => value_style,
+                    },
+                ),
+                None => ("Not run (press 'T', then 't' to test)".to_string(), Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+            };
+            info_lines.push(Line::from(vec![
+                Span::styled("  Reset Test: ", label_style),
+                Span::styled(test_text, test_style),
+            ]));
+
             // Reset bug
             info_lines.push(Line::from(vec![
                 Span::styled("  Reset Bug: ", label_style),
-                Span::styled(
-                    if gpu.capabilities.has_reset_bug { "Yes (affected)" } else { "No" },
-                    if gpu.capabilities.has_reset_bug {
-                        Style::default().fg(pastel_to_ratatui_color(theme.error)) // Pink (bad)
-                    } else {
-                        Style::default().fg(pastel_to_ratatui_color(theme.success)) // Mint (good)
-                    }
-                ),
+                match gpu.capabilities.has_reset_bug {
+                    Some(true) => Span::styled("Yes (affected)", Style::default().fg(pastel_to_ratatui_color(theme.error))), // Pink (bad)
+                    Some(false) => Span::styled("No", Style::default().fg(pastel_to_ratatui_color(theme.success))), // Mint (good)
+                    None => Span::styled("Unknown (run the reset test)", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))),
+                },
             ]));
 
             info_lines.push(Line::from(vec![
@@ -616,6 +1067,47 @@ fn render_gpu_details(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme)
                 Span::styled("    Custom video BIOS can be loaded for better compatibility", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)))
             ]));
 
+            // ACS override
+            info_lines.push(Line::from(vec![
+                Span::styled("  ACS Override: ", label_style),
+                Span::styled(
+                    if gpu.capabilities.requires_acs_override { "Required" } else { "Not needed" },
+                    if gpu.capabilities.requires_acs_override {
+                        Style::default().fg(pastel_to_ratatui_color(theme.error)) // Peach (warning)
+                    } else {
+                        Style::default().fg(pastel_to_ratatui_color(theme.success)) // Mint (good)
+                    }
+                ),
+            ]));
+
+            info_lines.push(Line::from(vec![
+                Span::styled(
+                    if gpu.capabilities.requires_acs_override {
+                        "    pcie_acs_override only relabels groups: devices behind the same bridge can still DMA to each other, so the guest may reach host devices"
+                    } else {
+                        "    The GPU is separated from other devices without patching the kernel's isolation checks"
+                    },
+                    Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray))
+                )
+            ]));
+
+            // BAR configuration
+            info_lines.push(Line::from(vec![
+                Span::styled("  BAR Configuration: ", label_style),
+                Span::styled(
+                    if gpu.capabilities.bar_config_breaks_passthrough { "Needs override" } else { "OK" },
+                    if gpu.capabilities.bar_config_breaks_passthrough {
+                        Style::default().fg(pastel_to_ratatui_color(theme.error)) // Peach (warning)
+                    } else {
+                        Style::default().fg(pastel_to_ratatui_color(theme.success)) // Mint (good)
+                    }
+                ),
+            ]));
+
+            info_lines.push(Line::from(vec![
+                Span::styled("    Large or resized BARs may not map in the guest without a bigger MMIO window", Style::default().fg(pastel_to_ratatui_color(PastelColor::Gray)))
+            ]));
+
             // Add action prompt
             info_lines.push(Line::from(""));
             if Some(app.selected_gpu_index) == app.selected_passthrough_gpu_index {
@@ -691,6 +1183,32 @@ fn render_footer(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
             Span::styled(" change GPU | ", Style::default().fg(pastel_to_ratatui_color(theme.text))),
             Span::styled("s", Style::default().fg(pastel_to_ratatui_color(theme.accent)).add_modifier(Modifier::BOLD)), // Select key
             Span::styled("elect GPU | ", Style::default().fg(pastel_to_ratatui_color(theme.text))),
+            Span::styled("t", Style::default().fg(pastel_to_ratatui_color(theme.accent)).add_modifier(Modifier::BOLD)),
+            Span::styled("est reset | ", Style::default().fg(pastel_to_ratatui_color(theme.text))),
+            Span::styled("x", Style::default().fg(pastel_to_ratatui_color(theme.accent)).add_modifier(Modifier::BOLD)),
+            Span::styled(" extract firmware | ", Style::default().fg(pastel_to_ratatui_color(theme.text))),
+        ]);
+        let selected_has_sriov = app.gpus.as_ref()
+            .and_then(|g| g.get(app.selected_gpu_index))
+            .is_some_and(|gpu| gpu.sriov.is_some());
+        let selected_has_mdev = app.gpus.as_ref()
+            .and_then(|g| g.get(app.selected_gpu_index))
+            .is_some_and(|gpu| !gpu.mdev_types.is_empty());
+        if selected_has_mdev {
+            help_text.extend(vec![
+                Span::styled("m", Style::default().fg(pastel_to_ratatui_color(theme.accent)).add_modifier(Modifier::BOLD)),
+                Span::styled("dev create | ", Style::default().fg(pastel_to_ratatui_color(theme.text))),
+            ]);
+        }
+        if selected_has_sriov {
+            help_text.extend(vec![
+                Span::styled("+/-", Style::default().fg(pastel_to_ratatui_color(theme.accent)).add_modifier(Modifier::BOLD)),
+                Span::styled(" VFs | ", Style::default().fg(pastel_to_ratatui_color(theme.text))),
+                Span::styled("f", Style::default().fg(pastel_to_ratatui_color(theme.accent)).add_modifier(Modifier::BOLD)),
+                Span::styled(" bind VF | ", Style::default().fg(pastel_to_ratatui_color(theme.text))),
+            ]);
+        }
+        help_text.extend(vec![
             Span::styled("Esc", Style::default().fg(pastel_to_ratatui_color(theme.accent)).add_modifier(Modifier::BOLD)),
             Span::styled(" back | ", Style::default().fg(pastel_to_ratatui_color(theme.text))),
         ]);
@@ -706,6 +1224,15 @@ fn render_footer(f: &mut Frame, app: &AppState, area: Rect, theme: &Theme) {
                 Span::styled("c", Style::default().fg(pastel_to_ratatui_color(theme.accent)).add_modifier(Modifier::BOLD)),
                 Span::styled("onfigure | ", Style::default().fg(pastel_to_ratatui_color(theme.text))),
             ]);
+            let sole_display = app.get_selected_passthrough_gpu()
+                .zip(app.gpus.as_ref())
+                .is_some_and(|(gpu, gpus)| is_sole_host_display(gpu, gpus));
+            if sole_display && !app.display_guard_overridden {
+                help_text.extend(vec![
+                    Span::styled("C", Style::default().fg(pastel_to_ratatui_color(theme.error)).add_modifier(Modifier::BOLD)),
+                    Span::styled(" override display guard | ", Style::default().fg(pastel_to_ratatui_color(theme.text))),
+                ]);
+            }
         }
     }
 
@@ -4,6 +4,7 @@ use crate::core::system::SystemInfo;
 use crate::gpu::GpuDevice;
 use crate::core::vfio::VfioManager;
 use crate::core::state::StateTracker;
+use crate::core::reboot::PendingReboot;
 use crate::core::bootloader::{BootloaderManager, get_bootloader_manager};
 use ratatui::style::Color;
 use std::path::PathBuf;
@@ -56,7 +57,9 @@ pub struct AppState {
     // State related to configuration - Make fields pub
     pub selected_passthrough_gpu_index: Option<usize>, // Index of GPU selected for passthrough
     pub configuration_applied: bool, // Track if initial config steps done
-    pub reboot_required: bool,
+    pub display_guard_overridden: bool, // User confirmed passing through the host's only display GPU
+    pub reset_test_confirmed: Option<String>, // BDF of the GPU the user confirmed a reset test for
+    pub pending_reboot: Option<PendingReboot>, // What the next boot changes (None until managers are initialized)
     pub current_action: Option<String>, // To show what action is being performed
 }
 
@@ -76,7 +79,9 @@ impl Default for AppState {
             state_tracker: None,
             selected_passthrough_gpu_index: None, // Initialize as None
             configuration_applied: false,
-            reboot_required: false,
+            display_guard_overridden: false,
+            reset_test_confirmed: None,
+            pending_reboot: None,
             current_action: None,
         }
     }
@@ -119,13 +124,30 @@ impl AppState {
             }
 
             self.add_log("Core managers initialized.", LogLevel::Info);
+            self.refresh_pending_reboot();
         } else {
             self.add_log("Cannot initialize managers: System info not detected.", LogLevel::Error);
         }
     }
 
+    /// Compares the saved configuration with the running system and logs what a reboot would apply
+    pub fn refresh_pending_reboot(&mut self) {
+        let Some(sys_info) = &self.system_info else { return };
+        let pending = PendingReboot::detect(
+            self.bootloader_manager.as_deref(),
+            self.state_tracker.as_ref(),
+            &sys_info.iommu.cmdline,
+        );
+        if pending.is_required() {
+            self.add_log("Reboot required: the running system doesn't reflect the saved configuration.", LogLevel::Warning);
+            for detail in pending.details() {
+                self.add_log(&format!("  {}", detail), LogLevel::Warning);
+            }
+        }
+        self.pending_reboot = Some(pending);
+    }
+
     /// Get the currently selected GPU for passthrough, if any
-    #[allow(dead_code)] // Allow unused function for now
     pub fn get_selected_passthrough_gpu(&self) -> Option<&GpuDevice> {
         if let (Some(idx), Some(gpus)) = (self.selected_passthrough_gpu_index, &self.gpus) {
             gpus.get(idx)